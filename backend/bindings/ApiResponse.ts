@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ResponseMeta } from "./ResponseMeta";
+
+/**
+ * Standard `{ data, meta }` envelope for successful responses.
+ */
+export type ApiResponse<T> = { data: T, meta?: ResponseMeta, };
\ No newline at end of file
@@ -0,0 +1,33 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Login response payload.
+ *
+ * NOTE: For web clients, the access token is set as an httpOnly cookie.
+ * For native clients (detected via X-Client-Type header), tokens are in the body.
+ *
+ * TS BINDINGS: fields omitted via `skip_serializing_if` are never `null` on
+ * the wire, so they are exported as optional (`field?: T`), not `T | null`.
+ */
+export type LoginResponse = { success: boolean, message: string, 
+/**
+ * Access token - only populated for native clients
+ */
+access_token?: string, 
+/**
+ * Refresh token - only populated for native clients
+ */
+refresh_token?: string, 
+/**
+ * Seconds until access token expires
+ */
+expires_in?: number, 
+/**
+ * Follow-up steps the client should prompt for (e.g. `verify_email`)
+ */
+required_actions?: Array<string>, 
+/**
+ * Set when the login was accepted by the `DEMO_AUTH` stand-in, which
+ * accepts any credentials
+ */
+demo?: boolean, };
\ No newline at end of file
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Optional logout payload: native clients (no cookies) pass their refresh
+ * token so it is revoked along with the access token.
+ */
+export type LogoutRequest = { refresh_token: string | null, };
\ No newline at end of file
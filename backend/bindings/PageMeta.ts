@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Pagination details for list responses.
+ */
+export type PageMeta = { 
+/**
+ * Effective page size (after clamping to the server maximum).
+ */
+limit: number, offset: number, 
+/**
+ * Total number of items available.
+ */
+total: number, };
\ No newline at end of file
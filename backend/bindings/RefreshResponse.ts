@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Refresh token response payload
+ */
+export type RefreshResponse = { success: boolean, access_token: string, 
+/**
+ * Rotated refresh token; replaces the one that was sent
+ */
+refresh_token?: string, expires_in: number, };
\ No newline at end of file
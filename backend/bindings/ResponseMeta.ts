@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PageMeta } from "./PageMeta";
+
+/**
+ * Response metadata attached to an `ApiResponse`.
+ */
+export type ResponseMeta = { request_id?: string, 
+/**
+ * RFC 3339 timestamp of when the response was produced.
+ */
+timestamp: string, page?: PageMeta, };
\ No newline at end of file
@@ -1,3 +1,20 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type User = { id: bigint, email: string, name: string, };
\ No newline at end of file
+/**
+ * User entity - maps to the `users` database table.
+ *
+ * Derives:
+ * - Queryable: Can be queried from the database
+ * - Identifiable: Has an `id` field for lookups
+ * - Selectable: For type-safe column selection
+ * - TS: Generates TypeScript types
+ */
+export type User = { id: bigint, email: string, name: string, is_active: boolean, created_at: string, updated_at: string, 
+/**
+ * Set for admin-provisioned accounts until the user picks a password
+ */
+must_change_password: boolean, 
+/**
+ * Whether the user has confirmed they own `email`
+ */
+email_verified: boolean, };
\ No newline at end of file
@@ -0,0 +1,5 @@
+-- Whether the user has confirmed they own their address; login reports
+-- `verify_email` in `required_actions` until it is set. Accounts that
+-- predate the column are treated as verified.
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE users SET email_verified = TRUE;
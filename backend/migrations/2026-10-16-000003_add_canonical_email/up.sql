@@ -0,0 +1,8 @@
+-- Uniqueness key for `email`, written by the application on insert: the
+-- address lowercased and, with NORMALIZE_EMAIL_ALIASES, with provider
+-- aliases collapsed (`a.b+x@gmail.com` -> `ab@gmail.com`). Existing rows get
+-- the plain lowercased address.
+ALTER TABLE users ADD COLUMN canonical_email VARCHAR(255);
+UPDATE users SET canonical_email = lower(email);
+ALTER TABLE users ALTER COLUMN canonical_email SET NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_canonical_email_key UNIQUE (canonical_email);
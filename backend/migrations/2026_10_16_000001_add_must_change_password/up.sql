@@ -0,0 +1,3 @@
+-- Accounts provisioned by an admin must pick their own password before
+-- using the API; cleared by POST /api/v1/auth/change-password
+ALTER TABLE users ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
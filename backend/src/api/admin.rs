@@ -0,0 +1,612 @@
+// ==============================================================================
+// ADMIN ENDPOINTS
+// ==============================================================================
+//
+// Operational endpoints restricted to callers with the `admin` role
+// (granted via `ADMIN_EMAILS`). Every action is written to the audit log.
+//
+// POST /api/v1/admin/revoke-before
+//   Incident response: reject every token issued before a cutoff. Blunt —
+//   all users issued tokens before the cutoff must log in again.
+//
+// GET /api/v1/admin/users?limit=&offset=
+//   Paginated user listing. `limit` is clamped to `MAX_PAGE_SIZE`; the
+//   applied value is reported in `meta.page.limit`.
+//
+// PUT /api/v1/admin/read-only  {"enabled": bool}
+//   Switch read-only mode (see `read_only`) on or off at runtime.
+//
+// GET /api/v1/admin/users/{id}/security
+//   One view of a user's security state for support and incident response:
+//   account active flag, login lockout (per-account limiter) with the
+//   attempts refused during it, and live session count. Lockout and session
+//   state are in memory, so they reflect this process only.
+//
+// POST /api/v1/admin/users/import?atomic=true|false  {"users": [...]}
+//   Bulk-create users ({email, password, name} per row, at most
+//   `MAX_IMPORT_ROWS`).
+//   - atomic=true (default): all or nothing. Any invalid row is a 400 naming
+//     it; a duplicate email is a 409; otherwise 200 with the created users
+//   - atomic=false: best effort. 207 Multi-Status with one result per row
+//     (`index`, `status`, and `user` or `error`); good rows are created
+//     even when others fail
+//   Each created user gets a `created` audit event and, with `WEBHOOK_URL`
+//   set, a `user.created` webhook.
+//
+// ==============================================================================
+
+use axum::extract::{Extension, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+
+use super::audit::AuthEventKind;
+use super::auth_user::AuthUser;
+use super::json::ApiJson;
+use super::jwt;
+use super::password;
+use super::request_id::RequestId;
+use super::response::{ApiResponse, PageMeta, ResponseMeta};
+use super::tx::Tx;
+use super::ApiError;
+use crate::features::users::domain::entities::{CreateUserRequest, User};
+use crate::features::users::infrastructure::repository;
+use crate::jobs;
+use crate::AppState;
+
+/// Page size when the request doesn't specify `limit`.
+const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Body for `POST /admin/revoke-before`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RevokeBeforeRequest {
+    /// Unix timestamp (seconds); tokens issued earlier are rejected.
+    pub before: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeBeforeResponse {
+    /// Effective cutoff (never lower than a previously set one).
+    pub min_iat: i64,
+}
+
+pub async fn revoke_before(
+    State(state): State<AppState>,
+    user: AuthUser,
+    ApiJson(request): ApiJson<RevokeBeforeRequest>,
+) -> Result<ApiJson<RevokeBeforeResponse>, ApiError> {
+    if !user.is_admin() {
+        return Err(ApiError::Forbidden("Admin role required".to_string()));
+    }
+
+    // A future cutoff would also reject every token issued until then
+    if request.before > state.clock.unix() {
+        return Err(ApiError::BadRequest("before must not be in the future".to_string()));
+    }
+
+    let min_iat = jwt::revoke_issued_before(&state.kv_store, request.before)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to share revocation cutoff: {err}");
+            ApiError::ServiceUnavailable("Cutoff applied on this instance only; retry to apply it everywhere".to_string())
+        })?;
+    tracing::warn!(
+        target: "audit",
+        admin_id = user.user_id,
+        before = request.before,
+        min_iat,
+        "Revoked all tokens issued before cutoff"
+    );
+
+    Ok(ApiJson(RevokeBeforeResponse { min_iat }))
+}
+
+/// Body for `PUT /admin/read-only` (and its response).
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReadOnlyMode {
+    pub enabled: bool,
+}
+
+pub async fn set_read_only(
+    State(state): State<AppState>,
+    user: AuthUser,
+    ApiJson(request): ApiJson<ReadOnlyMode>,
+) -> Result<ApiJson<ReadOnlyMode>, ApiError> {
+    if !user.is_admin() {
+        return Err(ApiError::Forbidden("Admin role required".to_string()));
+    }
+
+    let previous = state.read_only.swap(request.enabled, Ordering::Relaxed);
+    tracing::warn!(
+        target: "audit",
+        admin_id = user.user_id,
+        enabled = request.enabled,
+        previous,
+        "Read-only mode changed"
+    );
+
+    Ok(ApiJson(ReadOnlyMode { enabled: request.enabled }))
+}
+
+/// Query for `GET /admin/users`.
+#[derive(Debug, Deserialize)]
+pub struct ListUsersQuery {
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    user: AuthUser,
+    request_id: Option<Extension<RequestId>>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<ApiResponse<Vec<User>>, ApiError> {
+    if !user.is_admin() {
+        return Err(ApiError::Forbidden("Admin role required".to_string()));
+    }
+
+    let pool = state
+        .db_pool
+        .clone()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Database unavailable".to_string()))?;
+
+    let limit = effective_limit(query.limit, state.config.max_page_size);
+    let (users, total) = repository::list_users(pool, query.offset, limit).await?;
+    let request_id = request_id.map(|Extension(RequestId(id))| id);
+    Ok(users_page(users, query.offset, limit, total, request_id))
+}
+
+/// Requested page size clamped to `1..=max` (never an error).
+fn effective_limit(requested: Option<usize>, max: usize) -> usize {
+    requested.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, max.max(1))
+}
+
+fn users_page(users: Vec<User>, offset: usize, limit: usize, total: i64, request_id: Option<String>) -> ApiResponse<Vec<User>> {
+    let mut meta = ResponseMeta::now().with_page(PageMeta { limit, offset, total });
+    if let Some(id) = request_id {
+        meta = meta.with_request_id(id);
+    }
+    ApiResponse::new(users).with_meta(meta)
+}
+
+/// Response for `GET /admin/users/{id}/security`.
+#[derive(Debug, Serialize)]
+pub struct UserSecurityState {
+    pub user_id: i64,
+    pub email: String,
+    /// `users.is_active`
+    pub active: bool,
+    /// Login is currently refused by the per-account limiter.
+    pub locked_out: bool,
+    /// Seconds until login is allowed again (only while locked out).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked_out_for_seconds: Option<u64>,
+    /// Login attempts refused during the current lockout.
+    pub failed_attempts: u32,
+    /// Refresh token families that can still be refreshed.
+    pub active_sessions: usize,
+}
+
+pub async fn user_security(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(user_id): Path<i64>,
+) -> Result<ApiJson<UserSecurityState>, ApiError> {
+    if !user.is_admin() {
+        return Err(ApiError::Forbidden("Admin role required".to_string()));
+    }
+
+    let pool = state
+        .db_pool
+        .clone()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Database unavailable".to_string()))?;
+
+    let target = repository::get_user_by_id(pool, user_id).await?;
+    tracing::info!(target: "audit", admin_id = user.user_id, user_id, "Viewed user security state");
+    Ok(ApiJson(security_state(&state, &target).await?))
+}
+
+/// Gather `user`'s lockout and session state from the shared stores.
+async fn security_state(state: &AppState, user: &User) -> Result<UserSecurityState, ApiError> {
+    let now = state.clock.now();
+    let throttled = state.login_limiter.throttled(&user.email, now).await;
+    let active_sessions = state.sessions.active(user.id, now.timestamp()).await.map_err(|err| {
+        tracing::error!("Session registry unavailable: {err}");
+        ApiError::ServiceUnavailable("Session data temporarily unavailable".to_string())
+    })?;
+    Ok(UserSecurityState {
+        user_id: user.id,
+        email: user.email.clone(),
+        active: user.is_active,
+        locked_out: throttled.is_some(),
+        locked_out_for_seconds: throttled.map(|t| t.retry_after.as_secs().max(1)),
+        failed_attempts: throttled.map_or(0, |t| t.refused),
+        active_sessions,
+    })
+}
+
+/// Most rows one import request may carry.
+const MAX_IMPORT_ROWS: usize = 1000;
+
+/// Query for `POST /admin/users/import`.
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default = "default_atomic")]
+    pub atomic: bool,
+}
+
+fn default_atomic() -> bool {
+    true
+}
+
+/// Body for `POST /admin/users/import`. Rows are parsed one by one, so a
+/// malformed row is reported against its index instead of failing the body.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportRequest {
+    pub users: Vec<serde_json::Value>,
+}
+
+/// Outcome of one row of a best-effort import.
+#[derive(Debug, Serialize)]
+pub struct ImportItemResult {
+    pub index: usize,
+    /// HTTP status this row would have had on its own (201, 400, 409, ...).
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 207 body for `atomic=false` imports.
+#[derive(Debug, Serialize)]
+pub struct MultiStatus {
+    pub results: Vec<ImportItemResult>,
+}
+
+pub async fn import_users(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<ImportQuery>,
+    ApiJson(request): ApiJson<ImportRequest>,
+) -> Result<Response, ApiError> {
+    if !user.is_admin() {
+        return Err(ApiError::Forbidden("Admin role required".to_string()));
+    }
+    if request.users.len() > MAX_IMPORT_ROWS {
+        return Err(ApiError::BadRequest(format!("At most {MAX_IMPORT_ROWS} users per import")));
+    }
+
+    let pool = state
+        .db_pool
+        .clone()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Database unavailable".to_string()))?;
+
+    let rows: Vec<Result<CreateUserRequest, ApiError>> = request
+        .users
+        .into_iter()
+        .map(|row| serde_json::from_value(row).map_err(|e| ApiError::BadRequest(format!("Invalid user: {e}"))))
+        .collect();
+
+    if query.atomic {
+        let rows = rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, row)| row.map_err(|e| ApiError::BadRequest(format!("Row {index}: {}", e.public_message()))))
+            .collect::<Result<Vec<_>, _>>()?;
+        let password_hashes = password::hash_passwords(rows.iter().map(|row| row.password.clone()).collect()).await?;
+        let normalize_aliases = state.config.normalize_email_aliases;
+        // Hash first: the transaction holds a connection only for the insert
+        let mut tx = Tx::begin(&state).await?;
+        let result = tx.run(move |conn| repository::insert_users(conn, &rows, &password_hashes, normalize_aliases)).await;
+        let created = tx.finish(result).await?;
+        tracing::info!(target: "audit", admin_id = user.user_id, created = created.len(), "Imported users");
+        announce_created(&state, created.iter());
+        return Ok(ApiResponse::new(created).into_response());
+    }
+
+    // Insert the rows that parsed, then slot their results back in place
+    let valid: Vec<CreateUserRequest> = rows.iter().filter_map(|row| row.as_ref().ok().cloned()).collect();
+    let mut inserted = repository::import_users_each(pool, valid, state.config.normalize_email_aliases).await?.into_iter();
+    let results: Vec<Result<User, ApiError>> = rows
+        .into_iter()
+        .map(|row| match row {
+            Ok(_) => inserted.next().unwrap_or_else(|| Err(ApiError::InternalError("Import failed".to_string()))),
+            Err(e) => Err(e),
+        })
+        .collect();
+
+    announce_created(&state, results.iter().filter_map(|r| r.as_ref().ok()));
+    let created = results.iter().filter(|r| r.is_ok()).count();
+    tracing::info!(
+        target: "audit",
+        admin_id = user.user_id,
+        created,
+        failed = results.len() - created,
+        "Imported users (best effort)"
+    );
+    Ok(multi_status(results))
+}
+
+/// Record a `created` audit event for each of `users` and, when
+/// `WEBHOOK_URL` is set, queue a `user.created` webhook for it.
+fn announce_created<'a>(state: &AppState, users: impl Iterator<Item = &'a User>) {
+    let webhooks = state.config.webhook_url.as_deref().zip(state.jobs.as_ref());
+    for created in users {
+        state.auth_events.record(created.id, AuthEventKind::Created, state.clock.now(), None, None);
+        if let Some((url, queue)) = webhooks {
+            let job = jobs::webhook_event(url, "user.created", serde_json::json!({ "id": created.id }));
+            if let Err(err) = queue.enqueue(job) {
+                tracing::warn!(user_id = created.id, "Failed to queue user.created webhook: {err}");
+            }
+        }
+    }
+}
+
+/// 207 response with one entry per row, in input order.
+fn multi_status(results: Vec<Result<User, ApiError>>) -> Response {
+    let results = results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| match result {
+            Ok(user) => ImportItemResult {
+                index,
+                status: StatusCode::CREATED.as_u16(),
+                user: Some(user),
+                error: None,
+            },
+            Err(e) => ImportItemResult {
+                index,
+                status: e.status_code().as_u16(),
+                user: None,
+                error: Some(e.public_message()),
+            },
+        })
+        .collect();
+    (StatusCode::MULTI_STATUS, ApiJson(MultiStatus { results })).into_response()
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::api::jwt::generate_token_pair;
+    use chrono::Utc;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::{routing::post, Router};
+    use tower::ServiceExt;
+    use crate::test_support::{bearer_request, TestApp};
+
+    async fn post_revoke(roles: &[String], before: i64) -> StatusCode {
+        let app = Router::new()
+            .route("/admin/revoke-before", post(revoke_before))
+            .with_state(AppState::new(crate::config::AppConfig::default(), None));
+        let pair = generate_token_pair(&SystemClock, 1, "ops@example.com", roles).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/admin/revoke-before")
+            .header(header::AUTHORIZATION, format!("Bearer {}", pair.access_token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(format!(r#"{{"before":{before}}}"#)))
+            .unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_revoke_before_requires_admin() {
+        let before = Utc::now().timestamp() - 3_000;
+        assert_eq!(post_revoke(&[], before).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_admin_can_set_cutoff() {
+        let before = Utc::now().timestamp() - 3_000;
+        assert_eq!(post_revoke(&["admin".to_string()], before).await, StatusCode::OK);
+        assert!(jwt::min_issued_at() >= before);
+    }
+
+    #[tokio::test]
+    async fn test_future_cutoff_rejected() {
+        let before = Utc::now().timestamp() + 3_600;
+        assert_eq!(post_revoke(&["admin".to_string()], before).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_admin_toggles_read_only_mode() {
+        let state = AppState::new(Default::default(), None);
+        let app = Router::new()
+            .route("/admin/read-only", axum::routing::put(set_read_only))
+            .with_state(state.clone());
+        let put = |roles: &[String], enabled: bool| {
+            let pair = generate_token_pair(&SystemClock, 1, "ops@example.com", roles).unwrap();
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/read-only")
+                .header(header::AUTHORIZATION, format!("Bearer {}", pair.access_token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(format!(r#"{{"enabled":{enabled}}}"#)))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(put(&[], true)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!state.read_only.load(Ordering::Relaxed));
+
+        let admin = ["admin".to_string()];
+        let response = app.clone().oneshot(put(&admin, true)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.read_only.load(Ordering::Relaxed));
+
+        app.oneshot(put(&admin, false)).await.unwrap();
+        assert!(!state.read_only.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_limit_is_clamped_to_max_page_size() {
+        assert_eq!(effective_limit(Some(10_000), 100), 100);
+        assert_eq!(effective_limit(Some(50), 100), 50);
+        assert_eq!(effective_limit(Some(0), 100), 1);
+        assert_eq!(effective_limit(None, 100), DEFAULT_PAGE_SIZE);
+        assert_eq!(effective_limit(None, 5), 5);
+    }
+
+    #[test]
+    fn test_page_meta_reports_effective_limit() {
+        let limit = effective_limit(Some(10_000), crate::config::DEFAULT_MAX_PAGE_SIZE);
+        let json = serde_json::to_value(users_page(Vec::new(), 40, limit, 250, None)).unwrap();
+
+        assert_eq!(json["meta"]["page"]["limit"], 100);
+        assert_eq!(json["meta"]["page"]["offset"], 40);
+        assert_eq!(json["meta"]["page"]["total"], 250);
+        assert_eq!(json["data"], serde_json::json!([]));
+        assert!(json["meta"].get("request_id").is_none());
+    }
+
+    #[test]
+    fn test_page_meta_carries_request_id() {
+        let json = serde_json::to_value(users_page(Vec::new(), 0, 20, 0, Some("req-42".to_string()))).unwrap();
+        assert_eq!(json["meta"]["request_id"], "req-42");
+    }
+
+    fn user(id: i64, email: &str) -> User {
+        User {
+            id,
+            email: email.to_string(),
+            password_hash: String::new(),
+            name: "Pat".to_string(),
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            must_change_password: false,
+            email_verified: true,
+            canonical_email: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_security_state_aggregates_lockout_and_sessions() {
+        let mut state = AppState::new(Default::default(), None);
+        // Mid-window, so the lockout can't expire during the test
+        let clock = std::sync::Arc::new(crate::clock::MockClock::new(chrono::DateTime::from_timestamp(1_700_000_010, 0).unwrap()));
+        state.clock = clock.clone();
+        let target = user(7, "pat@example.com");
+        let now = state.clock.now();
+
+        // Exhaust the per-account limit, then two refused attempts
+        while state.login_limiter.check("Pat@example.com", now).await.is_ok() {}
+        let _ = state.login_limiter.check("pat@example.com", now).await;
+        state.sessions.record(7, "laptop", now.timestamp()).await;
+        state.sessions.record(7, "phone", now.timestamp()).await;
+        state.sessions.record(8, "someone-else", now.timestamp()).await;
+
+        let json = serde_json::to_value(security_state(&state, &target).await.unwrap()).unwrap();
+        assert_eq!(json["user_id"], 7);
+        assert_eq!(json["active"], true);
+        assert_eq!(json["locked_out"], true);
+        assert!(json["locked_out_for_seconds"].as_u64().unwrap() >= 1);
+        assert_eq!(json["failed_attempts"], 2);
+        assert_eq!(json["active_sessions"], 2);
+
+        let json = serde_json::to_value(security_state(&state, &user(9, "calm@example.com")).await.unwrap()).unwrap();
+        assert_eq!(json["locked_out"], false);
+        assert!(json.get("locked_out_for_seconds").is_none());
+        assert_eq!(json["failed_attempts"], 0);
+        assert_eq!(json["active_sessions"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_user_security_requires_admin() {
+        let app = TestApp::builder().build().await;
+        let get = |roles: &[String]| {
+            let pair = generate_token_pair(app.clock.as_ref(), 1, "ops@example.com", roles).unwrap();
+            bearer_request("GET", "/api/v1/admin/users/7/security", &pair.access_token, serde_json::Value::Null)
+        };
+
+        assert_eq!(app.send(get(&[])).await.status, StatusCode::FORBIDDEN);
+        // Admins get past the role check; the user record needs the database
+        let response = app.send(get(&["admin".to_string()])).await;
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_best_effort_import_reports_each_row() {
+        let results = vec![
+            Ok(user(1, "a@example.com")),
+            Err(ApiError::BadRequest("Invalid user: invalid email".to_string())),
+            Ok(user(2, "b@example.com")),
+        ];
+        let response = multi_status(results);
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let statuses: Vec<_> = json["results"].as_array().unwrap().iter().map(|r| r["status"].clone()).collect();
+        assert_eq!(statuses, [201, 400, 201]);
+        assert_eq!(json["results"][1]["index"], 1);
+        assert_eq!(json["results"][1]["error"], "Invalid user: invalid email");
+        assert!(json["results"][1].get("user").is_none());
+        assert_eq!(json["results"][2]["user"]["email"], "b@example.com");
+        assert!(json["results"][2]["user"].get("password_hash").is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_import_with_one_bad_row() {
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let app = TestApp::builder().db_pool(pool).build().await;
+        let pair = generate_token_pair(app.clock.as_ref(), 1, "ops@example.com", &["admin".to_string()]).unwrap();
+        let run = "import-".to_string() + &uuid::Uuid::new_v4().simple().to_string()[..8];
+        let body = |prefix: &str| {
+            serde_json::json!({ "users": [
+                { "email": format!("{prefix}-{run}-1@example.com"), "password": "GoodPass123", "name": "One" },
+                { "email": "not an email", "password": "GoodPass123", "name": "Bad" },
+                { "email": format!("{prefix}-{run}-2@example.com"), "password": "GoodPass123", "name": "Two" },
+            ]})
+        };
+        let import = |atomic: bool, body: serde_json::Value| {
+            let uri = format!("/api/v1/admin/users/import?atomic={atomic}");
+            bearer_request("POST", &uri, &pair.access_token, body)
+        };
+
+        // All or nothing: the bad row fails the whole import
+        let response = app.send(import(true, body("atomic"))).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+        // Best effort: good rows are created around the bad one
+        let response = app.send(import(false, body("partial"))).await;
+        assert_eq!(response.status, StatusCode::MULTI_STATUS);
+        let json = response.json;
+        let statuses: Vec<_> = json["results"].as_array().unwrap().iter().map(|r| r["status"].clone()).collect();
+        assert_eq!(statuses, [201, 400, 201]);
+
+        // Good rows commit together; a repeated import conflicts and creates nothing
+        let good = serde_json::json!({ "users": [
+            { "email": format!("whole-{run}-1@example.com"), "password": "GoodPass123", "name": "One" },
+            { "email": format!("whole-{run}-2@example.com"), "password": "GoodPass123", "name": "Two" },
+        ]});
+        let response = app.send(import(true, good.clone())).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.json["data"].as_array().unwrap().len(), 2);
+        assert_eq!(app.send(import(true, good)).await.status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_import_requires_admin() {
+        let app = TestApp::builder().build().await;
+        let pair = generate_token_pair(app.clock.as_ref(), 1, "ops@example.com", &[]).unwrap();
+        let body = serde_json::json!({ "users": [] });
+        let request = bearer_request("POST", "/api/v1/admin/users/import?atomic=false", &pair.access_token, body);
+        assert_eq!(app.send(request).await.status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,225 @@
+// ==============================================================================
+// AUTH EVENT AUDIT STORE
+// ==============================================================================
+//
+// Records authentication events (login / refresh / logout, plus account
+// creation) per user so users and support can review recent account activity
+// (`GET /api/v1/me/activity`).
+//
+// PRIVACY:
+// - The client IP is never stored in the clear: only a salted hash (to tell
+//   "same address as before" apart from "new address") and a coarse network
+//   prefix (/24 for IPv4, /48 for IPv6) as an approximate location
+// - The User-Agent is reduced to a coarse client family ("Firefox", "mobile app", ...)
+//
+// LIMITATIONS:
+// - In memory only: history resets on restart and is per-process
+// - Only the newest `MAX_EVENTS_PER_USER` events are kept per user
+//
+// ==============================================================================
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// Events retained per user; older ones are dropped first.
+const MAX_EVENTS_PER_USER: usize = 100;
+
+/// Kind of authentication event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthEventKind {
+    Login,
+    Refresh,
+    Logout,
+    /// Account created on the user's behalf (`admin::import_users`).
+    Created,
+}
+
+/// A recorded authentication event, as returned to the user.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthEvent {
+    pub kind: AuthEventKind,
+    pub at: DateTime<Utc>,
+    /// Coarse network the request came from, e.g. `203.0.113.0/24`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    /// Salted hash of the full client IP (stable for the process lifetime).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_hash: Option<String>,
+    /// Coarse client family derived from the User-Agent.
+    pub client: &'static str,
+    /// Id of the request that caused the event (`EMBED_CORRELATION_ID`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// In-memory per-user log of authentication events.
+#[derive(Debug)]
+pub struct AuthEventStore {
+    events: Mutex<HashMap<i64, VecDeque<AuthEvent>>>,
+    salt: [u8; 16],
+}
+
+impl Default for AuthEventStore {
+    fn default() -> Self {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            events: Mutex::new(HashMap::new()),
+            salt,
+        }
+    }
+}
+
+impl AuthEventStore {
+    /// Record an event that happened at `at` (normally `state.clock.now()`),
+    /// tagged with the current request's correlation id if enabled.
+    pub fn record(
+        &self,
+        user_id: i64,
+        kind: AuthEventKind,
+        at: DateTime<Utc>,
+        ip: Option<IpAddr>,
+        user_agent: Option<&str>,
+    ) {
+        let event = AuthEvent {
+            kind,
+            at,
+            network: ip.map(coarse_network),
+            ip_hash: ip.map(|ip| self.hash_ip(ip)),
+            client: client_family(user_agent.unwrap_or("")),
+            correlation_id: super::request_id::correlation_id(),
+        };
+
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        let log = events.entry(user_id).or_default();
+        if log.len() == MAX_EVENTS_PER_USER {
+            log.pop_front();
+        }
+        log.push_back(event);
+    }
+
+    /// Newest-first page of `user_id`'s events, plus the total available.
+    pub fn recent(&self, user_id: i64, offset: usize, limit: usize) -> (Vec<AuthEvent>, usize) {
+        let events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(log) = events.get(&user_id) else {
+            return (Vec::new(), 0);
+        };
+
+        let mut page: Vec<AuthEvent> = log.iter().cloned().collect();
+        // Recorded in arrival order; sort so the page is newest-first even if
+        // events were recorded out of order
+        page.sort_by_key(|e| std::cmp::Reverse(e.at));
+
+        let total = page.len();
+        (page.into_iter().skip(offset).take(limit).collect(), total)
+    }
+
+    fn hash_ip(&self, ip: IpAddr) -> String {
+        use blake2::digest::consts::U32;
+        use blake2::{Blake2b, Digest};
+
+        let mut hasher = Blake2b::<U32>::new();
+        hasher.update(self.salt);
+        hasher.update(ip.to_string().as_bytes());
+        hex::encode(&hasher.finalize()[..8])
+    }
+}
+
+/// Network prefix used as an approximate location (/24 IPv4, /48 IPv6).
+fn coarse_network(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{a}.{b}.{c}.0/24")
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2])
+        }
+    }
+}
+
+/// Coarse client family from a User-Agent string.
+fn client_family(user_agent: &str) -> &'static str {
+    let ua = user_agent.to_lowercase();
+    if ua.is_empty() {
+        "unknown"
+    } else if ua.contains("okhttp") || ua.contains("cfnetwork") || ua.contains("expo") {
+        "mobile app"
+    } else if ua.contains("edg/") {
+        "Edge"
+    } else if ua.contains("firefox/") {
+        "Firefox"
+    } else if ua.contains("chrome/") {
+        "Chrome"
+    } else if ua.contains("safari/") {
+        "Safari"
+    } else {
+        "other"
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_recent_is_newest_first_and_per_user() {
+        let store = AuthEventStore::default();
+        let start = Utc::now();
+        let ip: IpAddr = "203.0.113.42".parse().unwrap();
+
+        store.record(1, AuthEventKind::Login, start, Some(ip), Some("Mozilla/5.0 Firefox/120.0"));
+        store.record(2, AuthEventKind::Login, start + Duration::seconds(1), None, None);
+        store.record(1, AuthEventKind::Refresh, start + Duration::seconds(2), Some(ip), None);
+        store.record(1, AuthEventKind::Logout, start + Duration::seconds(3), None, None);
+
+        let (events, total) = store.recent(1, 0, 10);
+        let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(total, 3);
+        assert_eq!(kinds, [AuthEventKind::Logout, AuthEventKind::Refresh, AuthEventKind::Login]);
+
+        let (events, total) = store.recent(2, 0, 10);
+        assert_eq!(total, 1);
+        assert_eq!(events[0].kind, AuthEventKind::Login);
+
+        assert_eq!(store.recent(3, 0, 10).1, 0);
+    }
+
+    #[test]
+    fn test_ip_is_hashed_and_coarsened() {
+        let store = AuthEventStore::default();
+        let ip: IpAddr = "203.0.113.42".parse().unwrap();
+        store.record(1, AuthEventKind::Login, Utc::now(), Some(ip), Some("Mozilla/5.0 Firefox/120.0"));
+
+        let json = serde_json::to_string(&store.recent(1, 0, 1).0).unwrap();
+        assert!(!json.contains("203.0.113.42"));
+        assert!(json.contains("203.0.113.0/24"));
+        assert!(json.contains("Firefox"));
+    }
+
+    #[test]
+    fn test_pagination_and_retention_cap() {
+        let store = AuthEventStore::default();
+        let start = Utc::now();
+        for i in 0..(MAX_EVENTS_PER_USER as i64 + 5) {
+            store.record(1, AuthEventKind::Refresh, start + Duration::seconds(i), None, None);
+        }
+
+        let (first, total) = store.recent(1, 0, 10);
+        let (second, _) = store.recent(1, 10, 10);
+        assert_eq!(total, MAX_EVENTS_PER_USER);
+        assert_eq!(first.len(), 10);
+        assert!(first[9].at > second[0].at);
+    }
+}
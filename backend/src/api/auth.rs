@@ -30,16 +30,31 @@
 // ==============================================================================
 
 use axum::{
-    extract::State,
-    http::{header, HeaderMap, StatusCode},
+    extract::{rejection::QueryRejection, FromRequest, Query, Request, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
     response::{IntoResponse, Response},
-    Json,
 };
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::sync::OnceLock;
+use ts_rs::TS;
 
+use crate::features::users::domain::entities::User;
+use crate::features::users::domain::Email;
+use crate::features::users::infrastructure::repository;
 use crate::AppState;
-use super::jwt::{generate_token_pair, generate_access_token, validate_refresh_token, TokenPair};
+use super::audit::AuthEventKind;
+use super::auth_user::{AuthUser, PasswordChangeUser};
+use super::cookies::{cookie_pairs, CookieJar};
+use super::ip_pinning::{ClientIp, PinningDecision};
+use super::json::{bounded_string, ApiJson};
+use super::jwt::{
+    generate_rotated_pair, generate_token_pair, generate_token_pair_with, revoke_token,
+    revoke_user_tokens_before, validate_access_token_checked, validate_refresh_token_checked, Claims, TokenPair, EXPECTED_REFRESH_TOKEN, SESSION_TOO_OLD,
+};
+use super::password::{self, MAX_PASSWORD_LENGTH};
+use super::refresh_rotation::{RotatedTokens, RotationError};
+use super::ApiError;
 
 // ==============================================================================
 // COOKIE CONFIGURATION
@@ -57,18 +72,38 @@ const ACCESS_TOKEN_COOKIE_NAME: &str = "access_token";
 /// Cookie name for the refresh token.
 const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
 
-/// Access token cookie max age in seconds (15 minutes).
-/// Short-lived tokens reduce the window of exposure if somehow compromised.
-const ACCESS_TOKEN_MAX_AGE_SECONDS: i64 = 900; // 15 minutes
+/// Path scope of the access cookie.
+const ACCESS_TOKEN_COOKIE_PATH: &str = "/";
+
+/// Path scope of the refresh cookie (only sent to the auth endpoints).
+///
+/// Browsers only delete a cookie when the clearing `Set-Cookie` has the same
+/// name, Path and Domain as the one that set it, so set and clear MUST both
+/// go through `build_refresh_cookie`.
+const REFRESH_TOKEN_COOKIE_PATH: &str = "/api/v1/auth";
 
 /// Refresh token cookie max age in seconds (7 days).
 const REFRESH_TOKEN_MAX_AGE_SECONDS: i64 = 604800; // 7 days
 
-/// Check if we're running in production (requires Secure cookies)
-fn is_production() -> bool {
-    env::var("ENVIRONMENT")
-        .map(|v| v.to_lowercase() == "production" || v.to_lowercase() == "prod")
-        .unwrap_or(false)
+/// Whether the access cookie omits `HttpOnly` so JavaScript can read it.
+///
+/// STRONGLY DISCOURAGED: this re-opens the XSS token-theft hole that httpOnly
+/// cookies exist to close. Only for SPA/BFF flows that must read the access
+/// token during bootstrap. The refresh cookie is ALWAYS HttpOnly.
+///
+/// Requires `COOKIE_ACCESS_JS_READABLE=true`; in production additionally
+/// requires `COOKIE_ACCESS_JS_READABLE_IN_PRODUCTION=true` (startup config
+/// validation also enforces this).
+fn access_cookie_js_readable(production: bool) -> bool {
+    js_readable_allowed(
+        crate::config::env_flag("COOKIE_ACCESS_JS_READABLE"),
+        production,
+        crate::config::env_flag("COOKIE_ACCESS_JS_READABLE_IN_PRODUCTION"),
+    )
+}
+
+fn js_readable_allowed(requested: bool, production: bool, production_override: bool) -> bool {
+    requested && (!production || production_override)
 }
 
 // ==============================================================================
@@ -80,42 +115,202 @@ fn is_production() -> bool {
 /// SECURITY NOTE:
 /// - Password is transmitted over HTTPS (TLS) in production
 /// - Never log passwords or include them in error messages
-#[derive(Debug, Deserialize)]
+///
+/// Unknown keys are rejected (400) so typos like `passwrod` don't go unnoticed.
+///
+/// Field lengths are capped while deserializing (email: `MAX_EMAIL_LENGTH`,
+/// password: `MAX_PASSWORD_LENGTH` bytes), so oversized input is a 400
+/// before any other work is done.
+#[derive(Debug, Deserialize, TS)]
+#[serde(deny_unknown_fields)]
+#[ts(export)]
 pub struct LoginRequest {
+    #[serde(deserialize_with = "email_field")]
     pub email: String,
+    #[serde(deserialize_with = "password_field")]
     pub password: String,
 }
 
+/// Login credentials from either a JSON or a form-encoded body.
+///
+/// Simple HTML forms post `application/x-www-form-urlencoded`; every other
+/// content type goes through `ApiJson`. Both apply the same field limits and
+/// reject unknown fields, with errors in the `ApiError` envelope.
+#[derive(Debug)]
+pub struct LoginCredentials(pub LoginRequest);
+
+impl<S: Send + Sync> FromRequest<S> for LoginCredentials {
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_form = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.to_ascii_lowercase().starts_with("application/x-www-form-urlencoded"));
+
+        if is_form {
+            let axum::Form(request) = axum::Form::<LoginRequest>::from_request(req, state)
+                .await
+                .map_err(|rejection| ApiError::BadRequest(rejection.body_text()))?;
+            return Ok(Self(request));
+        }
+        let ApiJson(request) = ApiJson::<LoginRequest>::from_request(req, state).await?;
+        Ok(Self(request))
+    }
+}
+
+/// Longest valid email address (RFC 5321: 64 local + @ + 255 domain).
+pub const MAX_EMAIL_LENGTH: usize = 320;
+
+fn email_field<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    bounded_string(deserializer, MAX_EMAIL_LENGTH)
+}
+
+fn password_field<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    bounded_string(deserializer, MAX_PASSWORD_LENGTH)
+}
+
 /// Login response payload.
 /// 
 /// NOTE: For web clients, the access token is set as an httpOnly cookie.
 /// For native clients (detected via X-Client-Type header), tokens are in the body.
-#[derive(Debug, Serialize)]
+///
+/// TS BINDINGS: fields omitted via `skip_serializing_if` are never `null` on
+/// the wire, so they are exported as optional (`field?: T`), not `T | null`.
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
 pub struct LoginResponse {
     pub success: bool,
     pub message: String,
     /// Access token - only populated for native clients
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
     pub access_token: Option<String>,
     /// Refresh token - only populated for native clients
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
     pub refresh_token: Option<String>,
     /// Seconds until access token expires
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
     pub expires_in: Option<i64>,
+    /// Follow-up steps the client should prompt for (e.g. `verify_email`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[ts(as = "Option<Vec<String>>", optional)]
+    pub required_actions: Vec<String>,
+    /// Set when the login was accepted by the `DEMO_AUTH` stand-in, which
+    /// accepts any credentials
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[ts(as = "Option<bool>", optional)]
+    pub demo: bool,
+}
+
+impl LoginResponse {
+    /// Successful login; `token_response` fills in the tokens.
+    fn success(message: &str, required_actions: Vec<String>) -> Self {
+        Self {
+            success: true,
+            message: message.to_string(),
+            access_token: None,
+            refresh_token: None,
+            expires_in: None,
+            required_actions,
+            demo: false,
+        }
+    }
+
+    /// Failed login with no tokens.
+    fn failure(message: &str) -> Self {
+        Self {
+            success: false,
+            message: message.to_string(),
+            access_token: None,
+            refresh_token: None,
+            expires_in: None,
+            required_actions: Vec::new(),
+            demo: false,
+        }
+    }
+}
+
+/// Account state that decides which follow-up actions a login reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountStatus {
+    pub email_verified: bool,
+    pub mfa_enabled: bool,
+    /// Whether policy requires MFA for this account.
+    pub mfa_required: bool,
+    /// Admin-provisioned account that hasn't picked its own password yet;
+    /// its tokens are limited to `POST /auth/change-password`.
+    pub must_change_password: bool,
+}
+
+/// From the stored record. There is no MFA yet, so it is never enabled
+/// nor required.
+impl From<&User> for AccountStatus {
+    fn from(user: &User) -> Self {
+        Self {
+            email_verified: user.email_verified,
+            must_change_password: user.must_change_password,
+            ..Self::default()
+        }
+    }
+}
+
+impl AccountStatus {
+    /// Actions the user still has to complete, in the order to prompt them.
+    pub fn required_actions(&self) -> Vec<String> {
+        let mut actions = Vec::new();
+        if self.must_change_password {
+            actions.push("change_password".to_string());
+        }
+        if !self.email_verified {
+            actions.push("verify_email".to_string());
+        }
+        if self.mfa_required && !self.mfa_enabled {
+            actions.push("set_mfa".to_string());
+        }
+        actions
+    }
+}
+
+/// Logout query parameters.
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutParams {
+    /// Respond with `204 No Content` instead of a JSON body.
+    #[serde(default)]
+    pub no_content: bool,
+}
+
+/// Optional logout payload: native clients (no cookies) pass their refresh
+/// token so it is revoked along with the access token.
+#[derive(Debug, Default, Deserialize, TS)]
+#[ts(export)]
+pub struct LogoutRequest {
+    #[serde(default)]
+    pub refresh_token: Option<String>,
 }
 
 /// Refresh token request payload
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, TS)]
+#[serde(deny_unknown_fields)]
+#[ts(export)]
 pub struct RefreshRequest {
     pub refresh_token: String,
 }
 
 /// Refresh token response payload
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
 pub struct RefreshResponse {
     pub success: bool,
     pub access_token: String,
+    /// Rotated refresh token; replaces the one that was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub refresh_token: Option<String>,
+    #[ts(type = "number")]
     pub expires_in: i64,
 }
 
@@ -141,9 +336,10 @@ pub struct RefreshResponse {
 // ==============================================================================
 
 pub async fn login(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
     headers: HeaderMap,
-    Json(request): Json<LoginRequest>,
+    LoginCredentials(request): LoginCredentials,
 ) -> Response {
     // ==========================================================================
     // INPUT VALIDATION
@@ -151,13 +347,22 @@ pub async fn login(
     if request.email.is_empty() || request.password.is_empty() {
         return (
             StatusCode::BAD_REQUEST,
-            Json(LoginResponse {
-                success: false,
-                message: "Email and password are required".to_string(),
-                access_token: None,
-                refresh_token: None,
-                expires_in: None,
-            }),
+            ApiJson(LoginResponse::failure("Email and password are required")),
+        )
+            .into_response();
+    }
+
+    // ==========================================================================
+    // PER-ACCOUNT RATE LIMIT
+    // ==========================================================================
+    // The per-IP governor can't see a distributed attack on a single account.
+    // Checked before any password work so throttled attempts stay cheap.
+    if let Err(retry_after) = state.login_limiter.check(&request.email, state.clock.now()).await {
+        tracing::warn!("Login rate limit exceeded for account");
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+            ApiJson(LoginResponse::failure("Too many login attempts. Please try again later")),
         )
             .into_response();
     }
@@ -165,50 +370,131 @@ pub async fn login(
     // ==========================================================================
     // DATABASE LOOKUP & PASSWORD VERIFICATION
     // ==========================================================================
-    // TODO: Replace with actual database lookup when migrations are complete
-    // 
-    // In production:
-    // 1. Look up user by email in database
-    // 2. Verify password against stored hash using password::verify_password()
-    // 3. Return 401 if user not found or password mismatch
-    //
-    // For now, we use a demo user for testing the JWT flow
+    // With a database the user is looked up by email and the password
+    // checked against the stored hash; account state and roles come from
+    // that record. Without one a demo user stands in, but only with
+    // DEMO_AUTH=true: it accepts any credentials, so without the flag login
+    // is a 501 rather than a silent accept-anyone.
     // ==========================================================================
-    
-    let demo_user_id: i64 = 1;
-    let demo_email = &request.email;
-    
-    // TODO: Uncomment when database is ready
-    // let user = match get_user_by_email(&state.db_pool, &request.email).await {
-    //     Ok(u) => u,
-    //     Err(_) => return unauthorized_response("Invalid email or password"),
-    // };
-    // 
-    // if !password::verify_password(&request.password, &user.password_hash)? {
-    //     return unauthorized_response("Invalid email or password");
-    // }
+
+    let subject = match state.db_pool.clone() {
+        Some(pool) => match authenticate(pool, &request).await {
+            Ok(user) => LoginSubject {
+                user_id: user.id,
+                roles: state.config.roles_for(&user.email),
+                account: AccountStatus::from(&user),
+                email: user.email,
+                demo: false,
+            },
+            Err(response) => return response,
+        },
+        None if state.config.demo_auth => {
+            tracing::warn!("DEMO_AUTH is on: login accepts ANY credentials. Never enable this in production");
+            LoginSubject {
+                user_id: 1,
+                email: request.email.clone(),
+                // Never any roles: no password was checked, so granting them
+                // from the typed email (`ADMIN_EMAILS`) would hand admin to anyone
+                roles: Vec::new(),
+                // The demo user has nothing left to complete
+                account: AccountStatus {
+                    email_verified: true,
+                    ..AccountStatus::default()
+                },
+                demo: true,
+            }
+        }
+        None => {
+            return (
+                StatusCode::NOT_IMPLEMENTED,
+                ApiJson(LoginResponse::failure(
+                    "Login is not implemented yet (set DEMO_AUTH=true to use the demo user)",
+                )),
+            )
+                .into_response();
+        }
+    };
 
     // ==========================================================================
     // GENERATE JWT TOKENS
     // ==========================================================================
-    let token_pair = match generate_token_pair(demo_user_id, demo_email) {
+    let token_pair = match generate_token_pair_with(
+        state.clock.as_ref(),
+        subject.user_id,
+        &subject.email,
+        &subject.roles,
+        subject.account.must_change_password,
+    ) {
         Ok(pair) => pair,
         Err(e) => {
             tracing::error!("Failed to generate tokens: {:?}", e);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(LoginResponse {
-                    success: false,
-                    message: "Authentication failed".to_string(),
-                    access_token: None,
-                    refresh_token: None,
-                    expires_in: None,
-                }),
+                ApiJson(LoginResponse::failure("Authentication failed")),
             )
                 .into_response();
         }
     };
 
+    state.auth_events.record(subject.user_id, AuthEventKind::Login, state.clock.now(), client_ip, user_agent(&headers));
+    state.sessions.record(subject.user_id, &token_pair.family, state.clock.unix()).await;
+
+    let mut response = LoginResponse::success("Login successful", subject.account.required_actions());
+    response.demo = subject.demo;
+    token_response(&headers, token_pair, response, state.config.is_production())
+}
+
+/// Who a successful login issues tokens for.
+struct LoginSubject {
+    user_id: i64,
+    email: String,
+    roles: Vec<String>,
+    account: AccountStatus,
+    /// Demo user (`DEMO_AUTH`), not a stored account.
+    demo: bool,
+}
+
+/// Hash checked when no account matches, so unknown emails take as long
+/// as wrong passwords and can't be told apart by timing.
+static DUMMY_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
+
+/// Look up the active user for `request.email` and verify the password.
+///
+/// Unknown, inactive and wrong-password logins all get the same 401.
+async fn authenticate(pool: crate::DbPool, request: &LoginRequest) -> Result<User, Response> {
+    let invalid = || {
+        (
+            StatusCode::UNAUTHORIZED,
+            ApiJson(LoginResponse::failure("Invalid email or password")),
+        )
+            .into_response()
+    };
+
+    let user = match Email::parse(&request.email) {
+        Ok(email) => match repository::get_user_by_email(pool, email).await {
+            Ok(user) => Some(user),
+            Err(ApiError::NotFound(_)) => None,
+            Err(e) => return Err(e.into_response()),
+        },
+        Err(_) => None,
+    };
+
+    let hash = match &user {
+        Some(user) => user.password_hash.as_str(),
+        None => DUMMY_PASSWORD_HASH
+            .get_or_init(|| password::hash_password(&uuid::Uuid::new_v4().to_string()).unwrap_or_default()),
+    };
+    let verified = password::verify_password(&request.password, hash).map_err(IntoResponse::into_response)?;
+
+    match user {
+        Some(user) if verified => Ok(user),
+        _ => Err(invalid()),
+    }
+}
+
+/// Deliver freshly issued tokens with `body` (a successful `LoginResponse`)
+/// according to the client type.
+fn token_response(headers: &HeaderMap, token_pair: TokenPair, mut body: LoginResponse, production: bool) -> Response {
     // ==========================================================================
     // DETECT CLIENT TYPE (WEB vs NATIVE)
     // ==========================================================================
@@ -217,41 +503,26 @@ pub async fn login(
         .map(|v| v.to_str().unwrap_or("").to_lowercase() == "native")
         .unwrap_or(false);
 
+    body.expires_in = Some(token_pair.expires_in);
+
     // ==========================================================================
     // BUILD RESPONSE BASED ON CLIENT TYPE
     // ==========================================================================
     if is_native_client {
         // Native clients: Return tokens in response body
         // They will store in SecureStore (hardware-backed encryption)
-        (
-            StatusCode::OK,
-            Json(LoginResponse {
-                success: true,
-                message: "Login successful".to_string(),
-                access_token: Some(token_pair.access_token),
-                refresh_token: Some(token_pair.refresh_token),
-                expires_in: Some(token_pair.expires_in),
-            }),
-        )
-            .into_response()
+        body.access_token = Some(token_pair.access_token);
+        body.refresh_token = Some(token_pair.refresh_token);
+        (StatusCode::OK, ApiJson(body)).into_response()
     } else {
-        // Web clients: Set httpOnly cookies (immune to XSS)
-        let access_cookie = build_auth_cookie(&token_pair.access_token, false);
-        let refresh_cookie = build_refresh_cookie(&token_pair.refresh_token, false);
+        // Web clients: Set httpOnly cookies (immune to XSS); not in the body
+        let access_cookie = build_auth_cookie(&token_pair.access_token, token_pair.expires_in, production);
+        let refresh_cookie = build_refresh_cookie(&token_pair.refresh_token, false, production);
         
         (
             StatusCode::OK,
-            [
-                (header::SET_COOKIE, access_cookie),
-                (header::SET_COOKIE, refresh_cookie),
-            ],
-            Json(LoginResponse {
-                success: true,
-                message: "Login successful".to_string(),
-                access_token: None, // In cookie, not body
-                refresh_token: None, // In cookie, not body
-                expires_in: Some(token_pair.expires_in),
-            }),
+            CookieJar::new().add(access_cookie).add(refresh_cookie),
+            ApiJson(body),
         )
             .into_response()
     }
@@ -266,20 +537,74 @@ pub async fn login(
 // Clears the authentication cookie by setting it to expire immediately.
 // The browser will delete the cookie and stop sending it with requests.
 //
+// RESPONSE SHAPE:
+//   - Default: 200 with a JSON body (backward compatible)
+//   - `?no_content=true`, or an `Accept` header that only allows `*/*`:
+//     204 No Content with the same cookie-clearing headers
+//
+// REVOCATION:
+// The presented access token and refresh token(s) (cookie, or the optional
+// `{"refresh_token": ...}` body from native clients) are revoked by `jti`,
+// so copies of them stop working immediately instead of at expiry. This
+// includes access tokens `AuthUser` would refuse (pending password change,
+// scoped). A missing or malformed body or query string never fails the logout.
+//
 // ==============================================================================
 
-pub async fn logout() -> Response {
+pub async fn logout(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+    params: Result<Query<LogoutParams>, QueryRejection>,
+    body: Result<ApiJson<LogoutRequest>, ApiError>,
+) -> Response {
+    let params = params.map(|Query(params)| params).unwrap_or_default();
+
+    // Stateful CSRF: revoke every token issued to this session
+    if let Some(store) = &state.csrf_store {
+        if let Some(session) = super::csrf::session_key(&headers, &state).await {
+            store.invalidate_session(&session).await;
+        }
+    }
+
+    // Only tokens that validate are recorded, so junk can't fill the store
+    let access_claims = match extract_token_from_request(&headers) {
+        Some(token) => validate_access_token_checked(&token, state.clock.as_ref(), &state.revocations).await.ok(),
+        None => None,
+    };
+    if let Some(claims) = &access_claims {
+        if let Ok(user_id) = claims.user_id() {
+            state.auth_events.record(user_id, AuthEventKind::Logout, state.clock.now(), client_ip, user_agent(&headers));
+        }
+        revoke(&state, claims).await;
+    }
+
+    let refresh_tokens = match body {
+        Ok(ApiJson(LogoutRequest { refresh_token: Some(token) })) => vec![token],
+        _ => extract_refresh_tokens_from_cookie(&headers),
+    };
+    for token in refresh_tokens {
+        if let Ok(claims) = validate_refresh_token_checked(&token, state.clock.as_ref(), &state.revocations).await {
+            revoke(&state, &claims).await;
+        }
+    }
+
     // Clear both access and refresh cookies
-    let access_cookie = build_auth_cookie("", true);
-    let refresh_cookie = build_refresh_cookie("", true);
+    let access_cookie = build_auth_cookie("", 0, state.config.is_production());
+    let refresh_cookie = build_refresh_cookie("", true, state.config.is_production());
+
+    if params.no_content || accepts_only_wildcard(&headers) {
+        return (
+            StatusCode::NO_CONTENT,
+            CookieJar::new().add(access_cookie).add(refresh_cookie),
+        )
+            .into_response();
+    }
 
     (
         StatusCode::OK,
-        [
-            (header::SET_COOKIE, access_cookie),
-            (header::SET_COOKIE, refresh_cookie),
-        ],
-        Json(serde_json::json!({
+        CookieJar::new().add(access_cookie).add(refresh_cookie),
+        ApiJson(serde_json::json!({
             "success": true,
             "message": "Logged out successfully"
         })),
@@ -287,6 +612,14 @@ pub async fn logout() -> Response {
         .into_response()
 }
 
+/// Revoke the token behind `claims` until it expires. Logout never fails,
+/// so a store error is only logged.
+async fn revoke(state: &AppState, claims: &Claims) {
+    if let Err(err) = revoke_token(&state.revocations, claims, state.clock.unix()).await {
+        tracing::error!("Failed to revoke token at logout: {err}");
+    }
+}
+
 // ==============================================================================
 // REFRESH TOKEN ENDPOINT
 // ==============================================================================
@@ -296,80 +629,147 @@ pub async fn logout() -> Response {
 // Uses the refresh token to obtain a new access token without re-authenticating.
 // This allows short-lived access tokens while maintaining user sessions.
 //
+// With `REFRESH_IP_PINNING` enabled, a session suddenly used from a new IP is
+// audited (`warn`) or rejected (`strict`); see `ip_pinning`.
+//
+// The refresh token is rotated on every call: the response carries a new one
+// and the old one is retired (briefly still honoured for concurrent
+// refreshes); see `refresh_rotation`. With `MAX_REFRESH_ROTATIONS` set, a
+// session that has rotated that many times gets a 401 with
+// `reauthenticate: true` and must log in again.
+//
 // ==============================================================================
 
 pub async fn refresh(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
     headers: HeaderMap,
-    body: Option<Json<RefreshRequest>>,
+    body: Option<ApiJson<RefreshRequest>>,
 ) -> Response {
     // ==========================================================================
     // EXTRACT REFRESH TOKEN
     // ==========================================================================
     // Check body first (native clients), then cookie (web clients)
     
-    let refresh_token = if let Some(Json(req)) = body {
+    let candidates = if let Some(ApiJson(req)) = body {
         // Native client: token in request body
-        Some(req.refresh_token)
+        vec![req.refresh_token]
     } else {
-        // Web client: token in cookie
-        extract_refresh_token_from_cookie(&headers)
-    };
-    
-    let refresh_token = match refresh_token {
-        Some(t) if !t.is_empty() => t,
-        _ => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "Refresh token required"
-                })),
-            )
-                .into_response();
-        }
+        // Web client: token in cookie (possibly several; see below)
+        extract_refresh_tokens_from_cookie(&headers)
     };
+    let candidates: Vec<String> = candidates.into_iter().filter(|t| !t.is_empty()).collect();
+    if candidates.is_empty() {
+        return unauthorized_response("Refresh token required");
+    }
 
     // ==========================================================================
     // VALIDATE REFRESH TOKEN
     // ==========================================================================
-    let claims = match validate_refresh_token(&refresh_token) {
+    // With duplicate cookies (stale + current) use the first one that
+    // validates; if none does, report why the first one failed.
+    let mut validated = None;
+    for token in &candidates {
+        let result = validate_refresh_token_checked(token, state.clock.as_ref(), &state.revocations).await;
+        let valid = result.is_ok();
+        if valid || validated.is_none() {
+            validated = Some(result);
+        }
+        if valid {
+            break;
+        }
+    }
+    let claims = match validated.expect("at least one candidate") {
         Ok(c) => c,
-        Err(_) => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "Invalid or expired refresh token"
-                })),
-            )
-                .into_response();
+        // A common client bug; say so instead of the generic message
+        Err(ApiError::Unauthorized(msg)) if msg == EXPECTED_REFRESH_TOKEN => {
+            return unauthorized_response(EXPECTED_REFRESH_TOKEN)
         }
+        // REFRESH_TOKEN_ABSOLUTE_MAX_DAYS reached: only a new login helps
+        Err(ApiError::Unauthorized(msg)) if msg == SESSION_TOO_OLD => return reauthenticate_response(SESSION_TOO_OLD),
+        // Can't tell whether it was revoked (logged out): fail closed
+        Err(err @ ApiError::ServiceUnavailable(_)) => return err.into_response(),
+        // Invalid, expired, or revoked at logout
+        Err(_) => return unauthorized_response("Invalid or expired refresh token"),
     };
 
+    // ==========================================================================
+    // IP PINNING
+    // ==========================================================================
+    if let Some(ip) = client_ip {
+        match state.refresh_ip_tracker.check(claims.family(), ip) {
+            PinningDecision::Allow => {}
+            PinningDecision::Warn => {
+                tracing::warn!(
+                    target: "audit",
+                    user_id = %claims.sub,
+                    %ip,
+                    "Refresh token used from a new IP address"
+                );
+            }
+            PinningDecision::Reject => {
+                tracing::warn!(
+                    target: "audit",
+                    user_id = %claims.sub,
+                    %ip,
+                    "Rejected refresh from a new IP address"
+                );
+                return unauthorized_response("Session used from a new location. Please log in again");
+            }
+        }
+    }
+
     // ==========================================================================
     // GENERATE NEW ACCESS TOKEN
     // ==========================================================================
     let user_id = match claims.user_id() {
         Ok(id) => id,
-        Err(_) => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "Invalid token claims"
-                })),
-            )
-                .into_response();
-        }
+        Err(_) => return unauthorized_response("Invalid token claims"),
     };
+    state.auth_events.record(user_id, AuthEventKind::Refresh, state.clock.now(), client_ip, user_agent(&headers));
+
+    // ==========================================================================
+    // ROTATE REFRESH TOKEN
+    // ==========================================================================
+    // Concurrent refreshes with the same token within the grace window all
+    // receive the same new pair; see `refresh_rotation`.
+    let now = state.clock.now();
+    let rotated = state
+        .refresh_rotations
+        .rotate(&claims.jti, claims.family(), claims.exp, now, || {
+            let pair = generate_rotated_pair(state.clock.as_ref(), &claims)?;
+            Ok(RotatedTokens {
+                access_token: pair.access_token,
+                refresh_token: pair.refresh_token,
+                expires_in: pair.expires_in,
+            })
+        })
+        .await;
 
-    let new_access_token = match generate_access_token(user_id, &claims.email) {
-        Ok(t) => t,
+    let tokens = match rotated {
+        Ok(tokens) => tokens,
+        Err(ApiError::Unauthorized(msg)) if msg == RotationError::LimitReached.to_string() => {
+            tracing::info!(
+                target: "audit",
+                user_id = %claims.sub,
+                "Refresh refused: session reached its rotation limit"
+            );
+            return reauthenticate_response("Session has been refreshed too many times. Please log in again");
+        }
+        Err(ApiError::Unauthorized(_)) => {
+            tracing::warn!(
+                target: "audit",
+                user_id = %claims.sub,
+                "Rejected reuse of a rotated refresh token"
+            );
+            return unauthorized_response("Refresh token already used. Please log in again");
+        }
+        Err(e @ ApiError::ServiceUnavailable(_)) => return e.into_response(),
         Err(e) => {
             tracing::error!("Failed to generate access token: {:?}", e);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
+                ApiJson(serde_json::json!({
                     "success": false,
                     "message": "Token generation failed"
                 })),
@@ -377,6 +777,7 @@ pub async fn refresh(
                 .into_response();
         }
     };
+    state.sessions.record(user_id, claims.family(), now.timestamp()).await;
 
     // ==========================================================================
     // DETECT CLIENT TYPE AND RESPOND
@@ -390,43 +791,208 @@ pub async fn refresh(
         // Native: return token in body
         (
             StatusCode::OK,
-            Json(RefreshResponse {
+            ApiJson(RefreshResponse {
                 success: true,
-                access_token: new_access_token,
-                expires_in: 900, // 15 minutes
+                access_token: tokens.access_token,
+                refresh_token: Some(tokens.refresh_token),
+                expires_in: tokens.expires_in,
             }),
         )
             .into_response()
     } else {
-        // Web: set new cookie
-        let cookie = build_auth_cookie(&new_access_token, false);
+        // Web: set new cookies
+        let access_cookie = build_auth_cookie(&tokens.access_token, tokens.expires_in, state.config.is_production());
+        let refresh_cookie = build_refresh_cookie(&tokens.refresh_token, false, state.config.is_production());
         (
             StatusCode::OK,
-            [(header::SET_COOKIE, cookie)],
-            Json(serde_json::json!({
+            CookieJar::new().add(access_cookie).add(refresh_cookie),
+            ApiJson(serde_json::json!({
                 "success": true,
-                "expires_in": 900
+                "expires_in": tokens.expires_in
             })),
         )
             .into_response()
     }
 }
 
-/// Extract refresh token from cookie header
-fn extract_refresh_token_from_cookie(headers: &HeaderMap) -> Option<String> {
-    if let Some(cookie_header) = headers.get(header::COOKIE) {
-        if let Ok(cookies_str) = cookie_header.to_str() {
-            for cookie in cookies_str.split(';') {
-                let cookie = cookie.trim();
-                if let Some(value) = cookie.strip_prefix(&format!("{}=", REFRESH_TOKEN_COOKIE_NAME)) {
-                    if !value.is_empty() {
-                        return Some(value.to_string());
-                    }
-                }
-            }
-        }
+// ==============================================================================
+// CHANGE PASSWORD ENDPOINT
+// ==============================================================================
+//
+// POST /api/v1/auth/change-password
+//
+// Replaces the caller's password after checking the current one, clears
+// `must_change_password`, and emails a change notice. Reachable with tokens
+// that are still waiting on a password change (`PasswordChangeUser`); those
+// tokens stay blocked everywhere else until they expire, so the response
+// carries a fresh token pair (body for native clients, cookies for web) just
+// like login.
+//
+// Before the new pair is issued, the presented token and every other token
+// the user holds (other devices' access and refresh tokens) are revoked, so
+// a password change also ends sessions opened with the old password.
+// Tokens issued within the same second as the change survive the cutoff;
+// the presented one is revoked by `jti` as well.
+//
+// Attempts count against the per-account login limiter, since a wrong
+// current password is a password guess.
+//
+// ==============================================================================
+
+/// Change password request payload.
+#[derive(Debug, Deserialize, TS)]
+#[serde(deny_unknown_fields)]
+#[ts(export)]
+pub struct ChangePasswordRequest {
+    #[serde(deserialize_with = "password_field")]
+    pub current_password: String,
+    #[serde(deserialize_with = "password_field")]
+    pub new_password: String,
+}
+
+pub async fn change_password(
+    State(state): State<AppState>,
+    PasswordChangeUser(user): PasswordChangeUser,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<ChangePasswordRequest>,
+) -> Result<Response, ApiError> {
+    if let Err(retry_after) = state.login_limiter.check(&user.email, state.clock.now()).await {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+            ApiJson(LoginResponse::failure("Too many attempts. Please try again later")),
+        )
+            .into_response());
+    }
+
+    let pool = state
+        .db_pool
+        .clone()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Database unavailable".to_string()))?;
+    let account = repository::get_user_by_id(pool.clone(), user.user_id).await?;
+
+    if !password::verify_password(&request.current_password, &account.password_hash)? {
+        return Ok(unauthorized_response("Current password is incorrect"));
     }
-    None
+    if request.new_password == request.current_password {
+        return Err(ApiError::BadRequest("New password must differ from the current one".to_string()));
+    }
+
+    let password_hash = password::hash_password(&request.new_password)?;
+    // Sessions go first: if they can't be ended, the password stays as it was
+    end_sessions(&state, &user).await?;
+    repository::set_password(pool, user.user_id, password_hash).await?;
+    crate::jobs::notify_password_changed(state.jobs.as_ref(), state.config.mail_from.as_deref(), &account.email);
+    tracing::info!(target: "audit", user_id = user.user_id, "Password changed");
+
+    issue_after_password_change(&state, &user, &headers).await
+}
+
+/// Revoke `user`'s presented token and every token issued to them so far.
+/// Fails closed (503) when the revocation store can't be reached.
+async fn end_sessions(state: &AppState, user: &AuthUser) -> Result<(), ApiError> {
+    let now = state.clock.unix();
+    let revoked = async {
+        revoke_token(&state.revocations, &user.claims, now).await?;
+        revoke_user_tokens_before(&state.revocations, &user.claims.sub, now).await
+    };
+    revoked.await.map_err(|err| {
+        tracing::error!("Failed to end sessions on password change: {err}");
+        ApiError::ServiceUnavailable("Sessions temporarily unavailable".to_string())
+    })
+}
+
+/// The fresh, unrestricted token pair answering a password change.
+async fn issue_after_password_change(state: &AppState, user: &AuthUser, headers: &HeaderMap) -> Result<Response, ApiError> {
+    let token_pair = generate_token_pair(state.clock.as_ref(), user.user_id, &user.email, &user.roles)?;
+    state.sessions.record(user.user_id, &token_pair.family, state.clock.unix()).await;
+    Ok(token_response(headers, token_pair, LoginResponse::success("Password changed", Vec::new()), state.config.is_production()))
+}
+
+/// 401 with the standard `WWW-Authenticate` bearer challenge and the
+/// auth endpoints' `{ success, message }` body.
+fn unauthorized_response(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, super::BEARER_CHALLENGE)],
+        ApiJson(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+        .into_response()
+}
+
+/// 401 telling the client the session can't be extended and it must send
+/// the user through login again (`reauthenticate: true`), rather than retry.
+fn reauthenticate_response(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, super::BEARER_CHALLENGE)],
+        ApiJson(serde_json::json!({
+            "success": false,
+            "message": message,
+            "reauthenticate": true
+        })),
+    )
+        .into_response()
+}
+
+/// True when the client sent an `Accept` header that doesn't ask for JSON
+/// (e.g. `Accept: */*`), meaning it doesn't care about a response body.
+fn accepts_only_wildcard(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    let media_types: Vec<&str> = accept
+        .split(',')
+        .map(|part| part.split(';').next().unwrap_or("").trim())
+        .filter(|media_type| !media_type.is_empty())
+        .collect();
+
+    !media_types.is_empty() && media_types.iter().all(|media_type| *media_type == "*/*")
+}
+
+/// `User-Agent` header, if present and valid UTF-8.
+fn user_agent(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok())
+}
+
+/// Every refresh token cookie, in header order.
+///
+/// A browser can hold more than one `refresh_token` cookie (e.g. a stale one
+/// with a different Path or Domain next to the current one) and the order it
+/// sends them in isn't reliable, so callers try each rather than the first.
+fn extract_refresh_tokens_from_cookie(headers: &HeaderMap) -> Vec<String> {
+    cookie_values(headers, REFRESH_TOKEN_COOKIE_NAME).collect()
+}
+
+/// Maximum number of `name=value` pairs inspected per request when looking up
+/// a cookie. Browsers send far fewer; the cap bounds the work an attacker can
+/// force with a huge `Cookie` header. Cookies past the cap are ignored.
+const MAX_COOKIE_PAIRS: usize = 50;
+
+/// Find the first non-empty value of cookie `name`.
+///
+/// - Inspects at most `MAX_COOKIE_PAIRS` pairs across all `Cookie` headers
+/// - Stops as soon as the cookie is found
+/// - Skips malformed segments (no `=`, non-ASCII header values)
+fn find_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    cookie_values(headers, name).next()
+}
+
+/// Non-empty values of cookie `name`, lazily, within `MAX_COOKIE_PAIRS`.
+fn cookie_values<'a>(headers: &'a HeaderMap, name: &'a str) -> impl Iterator<Item = String> + 'a {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .take(MAX_COOKIE_PAIRS)
+        .flat_map(cookie_pairs)
+        .filter(move |(key, value)| *key == name && !value.is_empty())
+        .map(|(_, value)| value.to_string())
 }
 
 // ==============================================================================
@@ -437,21 +1003,28 @@ fn extract_refresh_token_from_cookie(headers: &HeaderMap) -> Option<String> {
 ///
 /// # Arguments
 /// * `token` - The token value (or empty string for logout)
-/// * `clear` - If true, sets Max-Age=0 to delete the cookie
+/// * `max_age` - The access token's lifetime (`TokenPair::expires_in`), so
+///   the cookie lasts exactly as long as its token; 0 deletes the cookie
 ///
 /// # Cookie Attributes
 /// - `HttpOnly`: Prevents JavaScript access (XSS protection)
 /// - `SameSite=Lax`: Prevents CSRF for most requests
 /// - `Path=/`: Cookie valid for all routes
-/// - `Secure`: Only send over HTTPS (auto-enabled in production)
-fn build_auth_cookie(token: &str, clear: bool) -> String {
-    let max_age = if clear { 0 } else { ACCESS_TOKEN_MAX_AGE_SECONDS };
-    let secure_flag = if is_production() { "; Secure" } else { "" };
+/// - `Secure`: Only send over HTTPS (when `production`)
+fn build_auth_cookie(token: &str, max_age: i64, production: bool) -> String {
+    format_auth_cookie(token, max_age, production, !access_cookie_js_readable(production))
+}
+
+fn format_auth_cookie(token: &str, max_age: i64, secure: bool, http_only: bool) -> String {
+    let secure_flag = if secure { "; Secure" } else { "" };
+    let http_only_flag = if http_only { "; HttpOnly" } else { "" };
 
     format!(
-        "{}={}; HttpOnly; SameSite=Lax; Path=/; Max-Age={}{}",
+        "{}={}{}; SameSite=Lax; Path={}; Max-Age={}{}",
         ACCESS_TOKEN_COOKIE_NAME,
         token,
+        http_only_flag,
+        ACCESS_TOKEN_COOKIE_PATH,
         max_age,
         secure_flag
     )
@@ -460,14 +1033,17 @@ fn build_auth_cookie(token: &str, clear: bool) -> String {
 /// Builds the Set-Cookie header value for the refresh token.
 ///
 /// Similar to access token but with longer expiry and restricted path.
-fn build_refresh_cookie(token: &str, clear: bool) -> String {
+/// Clearing (`clear = true`) keeps every scoping attribute identical so the
+/// browser actually deletes the cookie.
+fn build_refresh_cookie(token: &str, clear: bool, production: bool) -> String {
     let max_age = if clear { 0 } else { REFRESH_TOKEN_MAX_AGE_SECONDS };
-    let secure_flag = if is_production() { "; Secure" } else { "" };
+    let secure_flag = if production { "; Secure" } else { "" };
 
     format!(
-        "{}={}; HttpOnly; SameSite=Lax; Path=/api/v1/auth; Max-Age={}{}",
+        "{}={}; HttpOnly; SameSite=Lax; Path={}; Max-Age={}{}",
         REFRESH_TOKEN_COOKIE_NAME,
         token,
+        REFRESH_TOKEN_COOKIE_PATH,
         max_age,
         secure_flag
     )
@@ -478,23 +1054,81 @@ fn build_refresh_cookie(token: &str, clear: bool) -> String {
 // ==============================================================================
 //
 // This function extracts the access token from the request.
-// It checks both:
-// 1. Cookie (for web clients)
-// 2. Authorization header (for native clients)
+// It checks, in order:
+// 1. Authorization header (for native clients)
+// 2. Custom header named by `TOKEN_HEADER_NAME`, if set (for API gateways
+//    that forward the token as e.g. `X-Access-Token`)
+// 3. Cookie (for web clients)
 //
 // This allows the same endpoints to work for both web and native.
 //
+// AUTHORIZATION SCHEME:
+// The scheme is matched case-insensitively (`Bearer`, `bearer`, `BEARER`)
+// and the token is trimmed. Any other scheme (`Basic`, `Token`, ...) never
+// yields a token. By default the request then falls back to the custom
+// header and cookie, since a site behind HTTP Basic auth sends
+// `Authorization: Basic` alongside its cookies. With
+// `AUTH_SCHEME_STRICT=true` such a request is unauthenticated instead.
+//
 // ==============================================================================
 
+static TOKEN_HEADER_NAME: OnceLock<Option<HeaderName>> = OnceLock::new();
+
+static AUTH_SCHEME_STRICT: OnceLock<bool> = OnceLock::new();
+
+/// Whether a non-Bearer `Authorization` header rejects the request's
+/// credentials outright, from `AUTH_SCHEME_STRICT` (default false).
+fn auth_scheme_strict() -> bool {
+    *AUTH_SCHEME_STRICT.get_or_init(|| crate::config::env_flag("AUTH_SCHEME_STRICT"))
+}
+
+/// Extra header to read the access token from, from `TOKEN_HEADER_NAME`.
+///
+/// Unset or empty disables it; an invalid header name is ignored with a warning.
+fn token_header_name() -> Option<&'static HeaderName> {
+    TOKEN_HEADER_NAME
+        .get_or_init(|| {
+            let name = env::var("TOKEN_HEADER_NAME").ok()?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(name) => Some(name),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid TOKEN_HEADER_NAME {:?}", name);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
 /// Extracts the access token from the request.
 /// 
 /// Priority:
 /// 1. Authorization: Bearer <token> header (native clients)
-/// 2. access_token cookie (web clients)
+/// 2. `TOKEN_HEADER_NAME` header, raw token value (gateways)
+/// 3. access_token cookie (web clients)
 /// 
 /// Returns None if no token is found.
 #[allow(dead_code)] // Will be used by auth middleware when protected routes are added
 pub fn extract_token_from_request(headers: &axum::http::HeaderMap) -> Option<String> {
+    extract_token(headers, token_header_name(), auth_scheme_strict())
+}
+
+/// Token from an `Authorization` value; `None` for another scheme or an empty token.
+fn bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.trim().split_once(char::is_whitespace)?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+fn extract_token(
+    headers: &axum::http::HeaderMap,
+    custom_header: Option<&HeaderName>,
+    strict_scheme: bool,
+) -> Option<String> {
     // ==========================================================================
     // CHECK AUTHORIZATION HEADER FIRST (Native clients)
     // ==========================================================================
@@ -505,10 +1139,25 @@ pub fn extract_token_from_request(headers: &axum::http::HeaderMap) -> Option<Str
     // ==========================================================================
 
     if let Some(auth_header) = headers.get(header::AUTHORIZATION) {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                return Some(token.to_string());
-            }
+        match auth_header.to_str().ok().and_then(bearer_token) {
+            Some(token) => return Some(token.to_string()),
+            None if strict_scheme => return None,
+            None => {}
+        }
+    }
+
+    // ==========================================================================
+    // CHECK CUSTOM HEADER (API gateways)
+    // ==========================================================================
+
+    if let Some(name) = custom_header {
+        let token = headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty());
+        if let Some(token) = token {
+            return Some(token.to_string());
         }
     }
 
@@ -521,21 +1170,7 @@ pub fn extract_token_from_request(headers: &axum::http::HeaderMap) -> Option<Str
     //
     // ==========================================================================
 
-    if let Some(cookie_header) = headers.get(header::COOKIE) {
-        if let Ok(cookies_str) = cookie_header.to_str() {
-            // Parse cookies (simple implementation - production should use cookie crate)
-            for cookie in cookies_str.split(';') {
-                let cookie = cookie.trim();
-                if let Some(value) = cookie.strip_prefix(&format!("{}=", ACCESS_TOKEN_COOKIE_NAME)) {
-                    if !value.is_empty() {
-                        return Some(value.to_string());
-                    }
-                }
-            }
-        }
-    }
-
-    None
+    find_cookie(headers, ACCESS_TOKEN_COOKIE_NAME)
 }
 
 // ==============================================================================
@@ -545,24 +1180,76 @@ pub fn extract_token_from_request(headers: &axum::http::HeaderMap) -> Option<Str
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::cookies::parse_set_cookie;
+    use crate::clock::SystemClock;
+    use crate::test_support::{bearer_request, TestApp};
     use axum::http::HeaderValue;
 
     #[test]
     fn test_build_auth_cookie_sets_httponly() {
-        let cookie = build_auth_cookie("test_token", false);
-        assert!(cookie.contains("HttpOnly"), "Cookie must be HttpOnly for XSS protection");
+        let cookie = parse_set_cookie(&build_auth_cookie("test_token", 900, false));
+        assert!(cookie.http_only(), "Cookie must be HttpOnly for XSS protection");
     }
 
     #[test]
     fn test_build_auth_cookie_sets_samesite() {
-        let cookie = build_auth_cookie("test_token", false);
-        assert!(cookie.contains("SameSite=Lax"), "Cookie should have SameSite for CSRF protection");
+        let cookie = parse_set_cookie(&build_auth_cookie("test_token", 900, false));
+        assert_eq!(cookie.same_site(), Some("Lax"), "Cookie should have SameSite for CSRF protection");
     }
 
     #[test]
     fn test_build_auth_cookie_clear_sets_zero_max_age() {
-        let cookie = build_auth_cookie("", true);
-        assert!(cookie.contains("Max-Age=0"), "Clear cookie must expire immediately");
+        let cookie = parse_set_cookie(&build_auth_cookie("", 0, false));
+        assert_eq!(cookie.max_age(), Some(0), "Clear cookie must expire immediately");
+    }
+
+    #[test]
+    fn test_js_readable_access_cookie_drops_httponly() {
+        assert!(!parse_set_cookie(&format_auth_cookie("t", 900, false, false)).http_only());
+        assert!(parse_set_cookie(&format_auth_cookie("t", 900, false, true)).http_only());
+    }
+
+    #[test]
+    fn test_cookies_secure_only_in_production() {
+        assert!(parse_set_cookie(&build_auth_cookie("t", 900, true)).secure());
+        assert!(parse_set_cookie(&build_refresh_cookie("t", false, true)).secure());
+        assert!(!parse_set_cookie(&build_auth_cookie("t", 900, false)).secure());
+        assert!(!parse_set_cookie(&build_refresh_cookie("t", false, false)).secure());
+    }
+
+    #[test]
+    fn test_refresh_cookie_always_httponly() {
+        assert!(parse_set_cookie(&build_refresh_cookie("t", false, false)).http_only());
+    }
+
+    #[test]
+    fn test_refresh_clear_cookie_path_matches_set_cookie() {
+        let set = parse_set_cookie(&build_refresh_cookie("t", false, false));
+        let clear = parse_set_cookie(&build_refresh_cookie("", true, false));
+        assert_eq!(set.path(), Some("/api/v1/auth"));
+        assert_eq!(clear.path(), set.path());
+        assert_eq!(clear.domain(), set.domain());
+        assert_eq!(clear.max_age(), Some(0));
+    }
+
+    #[test]
+    fn test_access_clear_cookie_path_matches_set_cookie() {
+        let set = parse_set_cookie(&build_auth_cookie("t", 900, false));
+        let clear = parse_set_cookie(&build_auth_cookie("", 0, false));
+        assert_eq!(clear.path(), set.path());
+    }
+
+    #[test]
+    fn test_access_and_refresh_cookie_names_do_not_collide() {
+        assert_ne!(ACCESS_TOKEN_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME);
+    }
+
+    #[test]
+    fn test_js_readable_requires_production_override() {
+        assert!(!js_readable_allowed(false, false, false));
+        assert!(js_readable_allowed(true, false, false));
+        assert!(!js_readable_allowed(true, true, false));
+        assert!(js_readable_allowed(true, true, true));
     }
 
     #[test]
@@ -602,4 +1289,860 @@ mod tests {
         // Bearer header should take priority (for native clients)
         assert_eq!(token, Some("header_token".to_string()));
     }
+
+    #[test]
+    fn test_extract_token_from_custom_header() {
+        let custom = HeaderName::from_static("x-access-token");
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(&custom, HeaderValue::from_static("gateway_token"));
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("access_token=cookie_token"),
+        );
+
+        // Custom header beats the cookie, and is ignored when not configured
+        assert_eq!(extract_token(&headers, Some(&custom), false).as_deref(), Some("gateway_token"));
+        assert_eq!(extract_token(&headers, None, false).as_deref(), Some("cookie_token"));
+    }
+
+    #[test]
+    fn test_authorization_takes_priority_over_custom_header() {
+        let custom = HeaderName::from_static("x-access-token");
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(&custom, HeaderValue::from_static("gateway_token"));
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer header_token"),
+        );
+        assert_eq!(extract_token(&headers, Some(&custom), false).as_deref(), Some("header_token"));
+    }
+
+    #[test]
+    fn test_bearer_scheme_is_case_insensitive_and_trimmed() {
+        for value in ["Bearer tok", "bearer tok", "BEARER   tok  ", " Bearer\ttok"] {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+            assert_eq!(extract_token(&headers, None, true).as_deref(), Some("tok"), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_authorization_scheme_yields_no_token() {
+        for value in ["Basic dXNlcjpwYXNz", "Token tok", "Bearertok", "Bearer   ", "tok"] {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+            headers.insert(header::COOKIE, HeaderValue::from_static("access_token=cookie_token"));
+
+            // Lenient: the cookie still authenticates (e.g. behind HTTP Basic auth)
+            assert_eq!(extract_token(&headers, None, false).as_deref(), Some("cookie_token"), "{value:?}");
+            // Strict: the request carries no usable credentials
+            assert_eq!(extract_token(&headers, None, true), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_login_response_binding_marks_skipped_fields_optional() {
+        let binding = LoginResponse::export_to_string().unwrap();
+        assert!(binding.contains("access_token?: string"), "{binding}");
+        assert!(binding.contains("refresh_token?: string"), "{binding}");
+        assert!(binding.contains("expires_in?: number"), "{binding}");
+        assert!(binding.contains("required_actions?: Array<string>"), "{binding}");
+        assert!(binding.contains("demo?: boolean"), "{binding}");
+        assert!(!binding.contains("string | null"), "{binding}");
+    }
+
+    #[test]
+    fn test_unverified_user_must_verify_email() {
+        let account = AccountStatus::default();
+        assert_eq!(account.required_actions(), ["verify_email"]);
+
+        let account = AccountStatus {
+            email_verified: true,
+            mfa_required: true,
+            ..AccountStatus::default()
+        };
+        assert_eq!(account.required_actions(), ["set_mfa"]);
+
+        let account = AccountStatus {
+            email_verified: true,
+            must_change_password: true,
+            ..AccountStatus::default()
+        };
+        assert_eq!(account.required_actions(), ["change_password"]);
+    }
+
+    #[tokio::test]
+    async fn test_flagged_user_only_reaches_change_password() {
+        let app = TestApp::builder().build().await;
+        let pair = generate_token_pair_with(app.clock.as_ref(), 1, "new@example.com", &[], true).unwrap();
+
+        let response = app
+            .send(bearer_request("GET", "/api/v1/me/activity", &pair.access_token, serde_json::Value::Null))
+            .await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert_eq!(response.json["code"], "PASSWORD_CHANGE_REQUIRED");
+
+        // Gets past authentication; the password itself lives in the database
+        let body = serde_json::json!({ "current_password": "Provisioned1", "new_password": "MyOwnPass42" });
+        let response = app
+            .send(bearer_request("POST", "/api/v1/auth/change-password", &pair.access_token, body))
+            .await;
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_password_change_unblocks_flagged_user() {
+        use crate::schema::users;
+        use diesel::prelude::*;
+
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let email = format!("provisioned-{}@example.com", uuid::Uuid::new_v4());
+        let user_id: i64 = diesel::insert_into(users::table)
+            .values((
+                users::email.eq(&email),
+                users::canonical_email.eq(&email),
+                users::password_hash.eq(password::hash_password("Provisioned1").unwrap()),
+                users::name.eq("Provisioned"),
+                users::must_change_password.eq(true),
+                users::email_verified.eq(true),
+            ))
+            .returning(users::id)
+            .get_result(&mut pool.get().unwrap())
+            .unwrap();
+
+        let app = TestApp::builder().db_pool(pool.clone()).build().await;
+        let login = |password: &str| serde_json::json!({ "email": email, "password": password });
+        let response = app.post_json("/api/v1/auth/login", login("WrongPass1")).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+
+        // Login succeeds but the tokens only reach change-password
+        let response = app.post_json("/api/v1/auth/login", login("Provisioned1")).await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json);
+        assert!(response.json.get("demo").is_none());
+        assert_eq!(response.json["required_actions"], serde_json::json!(["change_password"]));
+        let flagged = response.json["access_token"].as_str().unwrap().to_string();
+        let activity = |token: &str| bearer_request("GET", "/api/v1/me/activity", token, serde_json::Value::Null);
+        let response = app.send(activity(&flagged)).await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert_eq!(response.json["code"], "PASSWORD_CHANGE_REQUIRED");
+
+        let body = serde_json::json!({ "current_password": "Provisioned1", "new_password": "MyOwnPass42" });
+        let response = app
+            .send(bearer_request("POST", "/api/v1/auth/change-password", &flagged, body))
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json);
+
+        let token = response.json["access_token"].as_str().unwrap();
+        assert_eq!(app.send(activity(token)).await.status, StatusCode::OK);
+        // The flagged token is revoked, not just still restricted
+        assert_eq!(app.send(activity(&flagged)).await.status, StatusCode::UNAUTHORIZED);
+        let user = repository::get_user_by_id(pool, user_id).await.unwrap();
+        assert!(!user.must_change_password);
+        assert!(password::verify_password("MyOwnPass42", &user.password_hash).unwrap());
+
+        // Logging in again is no longer restricted
+        let response = app.post_json("/api/v1/auth/login", login("MyOwnPass42")).await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json);
+        assert!(response.json.get("required_actions").is_none());
+        let token = response.json["access_token"].as_str().unwrap();
+        assert_eq!(app.send(activity(token)).await.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_password_change_ends_old_sessions_and_unblocks() {
+        let app = TestApp::builder().build().await;
+        let activity = |token: &str| bearer_request("GET", "/api/v1/me/activity", token, serde_json::Value::Null);
+        let refresh = |token: &str| {
+            bearer_request("POST", "/api/v1/auth/refresh", "", serde_json::json!({ "refresh_token": token }))
+        };
+
+        // Flagged: the login's tokens only reach change-password
+        let flagged = generate_token_pair_with(app.clock.as_ref(), 42, "flagged@example.com", &[], true).unwrap();
+        let response = app.send(activity(&flagged.access_token)).await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert_eq!(response.json["code"], "PASSWORD_CHANGE_REQUIRED");
+        // Another device, signed in with the old password
+        let other = generate_token_pair(app.clock.as_ref(), 42, "flagged@example.com", &[]).unwrap();
+        app.clock.advance(chrono::Duration::seconds(1));
+
+        // Changed: the handler's steps after the database update
+        let claims = validate_access_token_checked(&flagged.access_token, app.clock.as_ref(), &app.state.revocations)
+            .await
+            .unwrap();
+        let user = AuthUser {
+            user_id: 42,
+            email: claims.email.clone(),
+            roles: claims.roles.clone(),
+            claims,
+        };
+        end_sessions(&app.state, &user).await.unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Client-Type", HeaderValue::from_static("native"));
+        let response = issue_after_password_change(&app.state, &user, &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // Unblocked with the new pair; every token from before is revoked
+        let access_token = json["access_token"].as_str().unwrap();
+        assert_eq!(app.send(activity(access_token)).await.status, StatusCode::OK);
+        for token in [&flagged.access_token, &other.access_token] {
+            let response = app.send(activity(token)).await;
+            assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(response.json["error"], "Token revoked");
+        }
+        for token in [&flagged.refresh_token, &other.refresh_token] {
+            assert_eq!(app.send(refresh(token)).await.status, StatusCode::UNAUTHORIZED);
+        }
+        let refresh_token = json["refresh_token"].as_str().unwrap();
+        assert_eq!(app.send(refresh(refresh_token)).await.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_password_change_sends_notification_email() {
+        use crate::jobs::{Job, PASSWORD_CHANGED_SUBJECT};
+        use crate::schema::users;
+        use diesel::prelude::*;
+
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let email = format!("notified-{}@example.com", uuid::Uuid::new_v4());
+        diesel::insert_into(users::table)
+            .values((
+                users::email.eq(&email),
+                users::canonical_email.eq(&email),
+                users::password_hash.eq(password::hash_password("Original1").unwrap()),
+                users::name.eq("Notified"),
+                users::email_verified.eq(true),
+            ))
+            .execute(&mut pool.get().unwrap())
+            .unwrap();
+
+        let app = TestApp::builder()
+            .db_pool(pool)
+            .mail_from("security@example.com")
+            .build()
+            .await;
+        let response = app
+            .post_json("/api/v1/auth/login", serde_json::json!({ "email": email, "password": "Original1" }))
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json);
+        let token = response.json["access_token"].as_str().unwrap();
+
+        let body = serde_json::json!({ "current_password": "Original1", "new_password": "Changed42" });
+        let response = app
+            .send(bearer_request("POST", "/api/v1/auth/change-password", token, body))
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json);
+
+        let jobs = app.jobs().await;
+        assert!(
+            jobs.iter().any(|job| matches!(
+                job,
+                Job::SendEmail { from, to, subject, .. }
+                    if from == "security@example.com" && *to == email && subject == PASSWORD_CHANGED_SUBJECT
+            )),
+            "{jobs:?}"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_login_reports_actions_and_roles_from_user_record() {
+        use crate::schema::users;
+        use diesel::prelude::*;
+
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let email = format!("unverified-{}@example.com", uuid::Uuid::new_v4());
+        diesel::insert_into(users::table)
+            .values((
+                users::email.eq(&email),
+                users::canonical_email.eq(&email),
+                users::password_hash.eq(password::hash_password("Unverified1").unwrap()),
+                users::name.eq("Unverified"),
+            ))
+            .execute(&mut pool.get().unwrap())
+            .unwrap();
+
+        let app = TestApp::builder()
+            .db_pool(pool)
+            .admin_email(&email)
+            .build()
+            .await;
+        let response = app
+            .post_json("/api/v1/auth/login", serde_json::json!({ "email": email, "password": "Unverified1" }))
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json);
+        assert_eq!(response.json["required_actions"], serde_json::json!(["verify_email"]));
+
+        // Roles are granted after the password check, from the stored email
+        let token = response.json["access_token"].as_str().unwrap();
+        let claims = crate::api::jwt::validate_access_token_checked(token, app.clock.as_ref(), &app.state.revocations)
+            .await
+            .unwrap();
+        assert_eq!(claims.roles, ["admin"]);
+    }
+
+    #[test]
+    fn test_required_actions_omitted_when_empty() {
+        let mut response = LoginResponse::failure("x");
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("required_actions").is_none());
+
+        response.required_actions = AccountStatus::default().required_actions();
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["required_actions"], serde_json::json!(["verify_email"]));
+    }
+
+    async fn post_logout(uri: &str, accept: Option<&str>) -> axum::response::Response {
+        use tower::ServiceExt;
+
+        let app = axum::Router::new()
+            .route("/auth/logout", axum::routing::post(logout))
+            .with_state(AppState::new(crate::config::AppConfig::default(), None));
+        let mut request = axum::http::Request::builder().method("POST").uri(uri);
+        if let Some(accept) = accept {
+            request = request.header(header::ACCEPT, accept);
+        }
+        app.oneshot(request.body(axum::body::Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_logout_defaults_to_json_body() {
+        let response = post_logout("/auth/logout", Some("application/json")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get_all(header::SET_COOKIE).iter().count(), 2);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_logout_no_content_query_returns_204() {
+        let response = post_logout("/auth/logout?no_content=true", None).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers().get_all(header::SET_COOKIE).iter().count(), 2);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_logout_malformed_query_still_logs_out() {
+        let response = post_logout("/auth/logout?no_content=maybe", Some("application/json")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get_all(header::SET_COOKIE).iter().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_logout_wildcard_accept_returns_204() {
+        let response = post_logout("/auth/logout", Some("*/*")).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers().get_all(header::SET_COOKIE).iter().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_logout_revokes_presented_tokens() {
+        let app = TestApp::builder().build().await;
+        let web = generate_token_pair(app.clock.as_ref(), 41, "logout@example.com", &[]).unwrap();
+        let native = generate_token_pair(app.clock.as_ref(), 41, "logout@example.com", &[]).unwrap();
+        let activity = |token: &str| bearer_request("GET", "/api/v1/me/activity", token, serde_json::Value::Null);
+        let refresh = |token: &str| {
+            bearer_request("POST", "/api/v1/auth/refresh", "", serde_json::json!({ "refresh_token": token }))
+        };
+        assert_eq!(app.send(activity(&web.access_token)).await.status, StatusCode::OK);
+
+        let mut web_logout = bearer_request("POST", "/api/v1/auth/logout?no_content=maybe", &web.access_token, serde_json::Value::Null);
+        web_logout.headers_mut().insert(
+            header::COOKIE,
+            format!("{REFRESH_TOKEN_COOKIE_NAME}={}", web.refresh_token).parse().unwrap(),
+        );
+        let native_body = serde_json::json!({ "refresh_token": native.refresh_token });
+        let requests = [
+            web_logout, // malformed query: still revokes
+            bearer_request("POST", "/api/v1/auth/logout", &native.access_token, native_body),
+        ];
+        for request in requests {
+            assert_eq!(app.send(request).await.status, StatusCode::OK);
+        }
+
+        for pair in [web, native] {
+            let response = app.send(activity(&pair.access_token)).await;
+            assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+            assert_eq!(response.json["error"], "Token revoked");
+            assert_eq!(app.send(refresh(&pair.refresh_token)).await.status, StatusCode::UNAUTHORIZED);
+        }
+        // Tokens that weren't presented still work
+        let other = generate_token_pair(app.clock.as_ref(), 41, "logout@example.com", &[]).unwrap();
+        assert_eq!(app.send(refresh(&other.refresh_token)).await.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_logout_revokes_tokens_auth_user_refuses() {
+        let app = TestApp::builder().build().await;
+        let pending = generate_token_pair_with(app.clock.as_ref(), 42, "pending@example.com", &[], true).unwrap();
+        let scoped = crate::api::jwt::generate_scoped_token(
+            app.clock.as_ref(),
+            42,
+            "pending@example.com",
+            &[crate::api::scopes::PROFILE_READ.to_string()],
+            chrono::Duration::hours(1),
+        )
+        .unwrap();
+
+        for token in [pending.access_token, scoped] {
+            let logout = bearer_request("POST", "/api/v1/auth/logout", &token, serde_json::Value::Null);
+            assert_eq!(app.send(logout).await.status, StatusCode::OK);
+
+            let err = crate::api::jwt::validate_token_checked(&token, app.clock.as_ref(), &app.state.revocations)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, ApiError::Unauthorized(msg) if msg == "Token revoked"));
+        }
+    }
+
+    fn login_test_app() -> axum::Router {
+        let config = crate::config::AppConfig {
+            demo_auth: true,
+            ..Default::default()
+        };
+        axum::Router::new()
+            .route("/auth/login", axum::routing::post(login))
+            .with_state(AppState::new(config, None))
+    }
+
+    async fn post_login(app: &axum::Router, email: &str, ip: &str) -> StatusCode {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-forwarded-for", ip)
+            .body(axum::body::Body::from(format!(
+                r#"{{"email":"{email}","password":"whatever1"}}"#
+            )))
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    async fn post_login_as(content_type: &str, body: &str) -> StatusCode {
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/v1/auth/login")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        TestApp::builder().build().await.send(request).await.status
+    }
+
+    #[tokio::test]
+    async fn test_demo_login_is_flagged_in_response() {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-Client-Type", "native")
+            .body(axum::body::Body::from(r#"{"email":"demo@example.com","password":"anything1"}"#))
+            .unwrap();
+        let response = login_test_app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["demo"], true);
+    }
+
+    #[tokio::test]
+    async fn test_demo_login_never_grants_admin() {
+        use tower::ServiceExt;
+
+        let config = crate::config::AppConfig {
+            demo_auth: true,
+            admin_emails: vec!["ops@example.com".to_string()],
+            ..Default::default()
+        };
+        let state = AppState::new(config, None);
+        let app = axum::Router::new()
+            .route("/auth/login", axum::routing::post(login))
+            .with_state(state.clone());
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-Client-Type", "native")
+            .body(axum::body::Body::from(r#"{"email":"ops@example.com","password":"anything1"}"#))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let token = json["access_token"].as_str().unwrap();
+        let claims = crate::api::jwt::validate_access_token_checked(token, &crate::clock::SystemClock, &state.revocations)
+            .await
+            .unwrap();
+        assert!(claims.roles.is_empty(), "{:?}", claims.roles);
+    }
+
+    #[tokio::test]
+    async fn test_login_without_demo_auth_is_not_implemented() {
+        let app = TestApp::builder().config(crate::config::AppConfig::default()).build().await;
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/v1/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(r#"{"email":"demo@example.com","password":"anything1"}"#))
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::NOT_IMPLEMENTED);
+        assert!(response.headers.get(header::SET_COOKIE).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_login_accepts_json_and_form_bodies() {
+        let json = post_login_as("application/json", r#"{"email":"json@example.com","password":"secret123"}"#);
+        assert_eq!(json.await, StatusCode::OK);
+
+        let form = post_login_as(
+            "application/x-www-form-urlencoded",
+            "email=form%40example.com&password=secret123",
+        );
+        assert_eq!(form.await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_form_login_rejects_unknown_fields() {
+        let status = post_login_as(
+            "application/x-www-form-urlencoded",
+            "email=a%40b.com&password=secret123&passwrod=typo",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_login_unknown_field_returns_structured_400() {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(
+                r#"{"email":"a@b.com","password":"secret123","passwrod":"typo"}"#,
+            ))
+            .unwrap();
+        let response = login_test_app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["error"].as_str().unwrap().contains("unknown field `passwrod`"));
+    }
+
+    async fn post_login_body(body: String) -> (StatusCode, String) {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        let response = login_test_app().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        (status, json["error"].as_str().unwrap_or_default().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_login_rejects_over_length_email() {
+        let email = format!("{}@example.com", "a".repeat(MAX_EMAIL_LENGTH));
+        let (status, error) =
+            post_login_body(serde_json::json!({ "email": email, "password": "secret123" }).to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.contains("email"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_login_rejects_over_length_password() {
+        let password = "a1".repeat(MAX_PASSWORD_LENGTH);
+        let (status, error) =
+            post_login_body(serde_json::json!({ "email": "a@b.com", "password": password }).to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.contains("password"), "{error}");
+
+        // At the limit is still accepted by the extractor
+        let password = "a".repeat(MAX_PASSWORD_LENGTH);
+        let (status, _) =
+            post_login_body(serde_json::json!({ "email": "a@b.com", "password": password }).to_string()).await;
+        assert_ne!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_login_rate_limited_per_email_across_ips() {
+        let app = login_test_app();
+
+        // Attempts from many different IPs against the same account
+        for i in 0..5 {
+            let status = post_login(&app, "victim@example.com", &format!("10.0.0.{i}")).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let status = post_login(&app, "victim@example.com", "10.0.0.99").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+        // Other accounts are unaffected
+        let status = post_login(&app, "other@example.com", "10.0.0.1").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    async fn post_refresh(app: &axum::Router, refresh_token: &str, peer: &str) -> StatusCode {
+        use tower::ServiceExt;
+
+        let addr: std::net::SocketAddr = format!("{peer}:40000").parse().unwrap();
+        let mut request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/auth/refresh")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(format!(
+                r#"{{"refresh_token":"{refresh_token}"}}"#
+            )))
+            .unwrap();
+        request.extensions_mut().insert(axum::extract::ConnectInfo(addr));
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    fn strict_pinning_app() -> axum::Router {
+        use super::super::ip_pinning::{IpPinningMode, RefreshIpTracker};
+
+        let mut state = AppState::new(crate::config::AppConfig::default(), None);
+        state.refresh_ip_tracker = std::sync::Arc::new(RefreshIpTracker::new(IpPinningMode::Strict));
+        axum::Router::new()
+            .route("/auth/refresh", axum::routing::post(refresh))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_refresh_from_same_ip_passes_in_strict_mode() {
+        let app = strict_pinning_app();
+        let pair = generate_token_pair(&SystemClock, 7, "pin@example.com", &[]).unwrap();
+
+        assert_eq!(post_refresh(&app, &pair.refresh_token, "10.0.0.1").await, StatusCode::OK);
+        assert_eq!(post_refresh(&app, &pair.refresh_token, "10.0.0.1").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_from_changed_ip_rejected_in_strict_mode() {
+        let app = strict_pinning_app();
+        let pair = generate_token_pair(&SystemClock, 7, "pin@example.com", &[]).unwrap();
+
+        assert_eq!(post_refresh(&app, &pair.refresh_token, "10.0.0.1").await, StatusCode::OK);
+        assert_eq!(
+            post_refresh(&app, &pair.refresh_token, "203.0.113.9").await,
+            StatusCode::UNAUTHORIZED
+        );
+        // The original IP keeps working
+        assert_eq!(post_refresh(&app, &pair.refresh_token, "10.0.0.1").await, StatusCode::OK);
+    }
+
+    fn rotation_app(grace: std::time::Duration) -> axum::Router {
+        use super::super::refresh_rotation::RefreshRotations;
+
+        let mut state = AppState::new(crate::config::AppConfig::default(), None);
+        state.refresh_rotations = std::sync::Arc::new(RefreshRotations::new(state.kv_store.clone(), grace));
+        axum::Router::new()
+            .route("/auth/refresh", axum::routing::post(refresh))
+            .with_state(state)
+    }
+
+    async fn refresh_native(app: &axum::Router, refresh_token: &str) -> (StatusCode, serde_json::Value) {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/auth/refresh")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-Client-Type", "native")
+            .body(axum::body::Body::from(format!(r#"{{"refresh_token":"{refresh_token}"}}"#)))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_requires_reauthentication_after_max_rotations() {
+        use super::super::refresh_rotation::RefreshRotations;
+
+        let mut state = AppState::new(crate::config::AppConfig::default(), None);
+        state.refresh_rotations = std::sync::Arc::new(
+            RefreshRotations::new(state.kv_store.clone(), std::time::Duration::ZERO).with_max_rotations(Some(2)),
+        );
+        let app = axum::Router::new()
+            .route("/auth/refresh", axum::routing::post(refresh))
+            .with_state(state);
+
+        let mut refresh_token = generate_token_pair(&SystemClock, 7, "cap@example.com", &[]).unwrap().refresh_token;
+        for _ in 0..2 {
+            let (status, body) = refresh_native(&app, &refresh_token).await;
+            assert_eq!(status, StatusCode::OK);
+            refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+        }
+
+        let (status, body) = refresh_native(&app, &refresh_token).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["reauthenticate"], true);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_uses_valid_cookie_when_duplicates_present() {
+        let app = TestApp::builder().build().await;
+        let pair = generate_token_pair(app.clock.as_ref(), 7, "dup@example.com", &[]).unwrap();
+        let cookies = format!(
+            "{name}=stale.garbage.token; {name}={token}",
+            name = REFRESH_TOKEN_COOKIE_NAME,
+            token = pair.refresh_token
+        );
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/v1/auth/refresh")
+            .header(header::COOKIE, cookies)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_reports_the_issued_access_token_lifetime() {
+        let app = TestApp::builder().build().await;
+        let lifetime = |claims: Claims| claims.exp - claims.iat;
+
+        // Native: in the body
+        let pair = generate_token_pair(app.clock.as_ref(), 7, "lifetime@example.com", &[]).unwrap();
+        let body = serde_json::json!({ "refresh_token": pair.refresh_token });
+        let mut request = bearer_request("POST", "/api/v1/auth/refresh", "", body);
+        request.headers_mut().insert("X-Client-Type", HeaderValue::from_static("native"));
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let access_token = response.json["access_token"].as_str().unwrap();
+        let claims = validate_access_token_checked(access_token, app.clock.as_ref(), &app.state.revocations)
+            .await
+            .unwrap();
+        assert_eq!(response.json["expires_in"], lifetime(claims));
+
+        // Web: in the body and as the access cookie's Max-Age
+        let pair = generate_token_pair(app.clock.as_ref(), 7, "lifetime@example.com", &[]).unwrap();
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/v1/auth/refresh")
+            .header(header::COOKIE, format!("{REFRESH_TOKEN_COOKIE_NAME}={}", pair.refresh_token))
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let access_cookie = response
+            .headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| parse_set_cookie(v.to_str().unwrap()))
+            .find(|cookie| cookie.name == ACCESS_TOKEN_COOKIE_NAME)
+            .unwrap();
+        let claims = validate_access_token_checked(&access_cookie.value, app.clock.as_ref(), &app.state.revocations)
+            .await
+            .unwrap();
+        let expected = lifetime(claims);
+        assert_eq!(response.json["expires_in"], expected);
+        assert_eq!(access_cookie.max_age(), Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_with_access_token_names_the_mistake() {
+        let app = rotation_app(std::time::Duration::from_secs(10));
+        let pair = generate_token_pair(&SystemClock, 7, "mixup@example.com", &[]).unwrap();
+
+        let (status, body) = refresh_native(&app, &pair.access_token).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body, serde_json::json!({
+            "success": false,
+            "message": "Expected refresh token, got access token"
+        }));
+
+        // Garbage still gets the generic message
+        let (status, body) = refresh_native(&app, "not-a-token").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["message"], "Invalid or expired refresh token");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refreshes_both_succeed_with_same_tokens() {
+        let app = rotation_app(std::time::Duration::from_secs(10));
+        let pair = generate_token_pair(&SystemClock, 7, "race@example.com", &[]).unwrap();
+
+        let (first, second) = tokio::join!(
+            refresh_native(&app, &pair.refresh_token),
+            refresh_native(&app, &pair.refresh_token)
+        );
+
+        assert_eq!(first.0, StatusCode::OK);
+        assert_eq!(second.0, StatusCode::OK);
+        assert_eq!(first.1["refresh_token"], second.1["refresh_token"]);
+        assert_ne!(first.1["refresh_token"], pair.refresh_token.as_str());
+
+        // The rotated token continues the session
+        let rotated = first.1["refresh_token"].as_str().unwrap();
+        assert_eq!(refresh_native(&app, rotated).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rotated_token_reuse_after_grace_rejected() {
+        let app = rotation_app(std::time::Duration::ZERO);
+        let pair = generate_token_pair(&SystemClock, 7, "reuse@example.com", &[]).unwrap();
+
+        assert_eq!(refresh_native(&app, &pair.refresh_token).await.0, StatusCode::OK);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert_eq!(refresh_native(&app, &pair.refresh_token).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_huge_cookie_header_finds_token_early() {
+        let mut cookies = format!("{}=tok; ", ACCESS_TOKEN_COOKIE_NAME);
+        cookies.push_str(&(0..10_000).map(|i| format!("junk{i}=x")).collect::<Vec<_>>().join("; "));
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_str(&cookies).unwrap());
+
+        let started = std::time::Instant::now();
+        assert_eq!(extract_token_from_request(&headers).as_deref(), Some("tok"));
+        assert!(started.elapsed() < std::time::Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_cookie_lookup_ignores_pairs_past_cap_and_malformed_segments() {
+        let mut cookies = ";;garbage; =; novalue=; ".to_string();
+        cookies.push_str(&(0..10_000).map(|i| format!("junk{i}=x")).collect::<Vec<_>>().join("; "));
+        cookies.push_str(&format!("; {}=late", ACCESS_TOKEN_COOKIE_NAME));
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_str(&cookies).unwrap());
+
+        assert_eq!(extract_token_from_request(&headers), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_str(&format!("garbage; {}=tok", REFRESH_TOKEN_COOKIE_NAME)).unwrap(),
+        );
+        assert_eq!(extract_refresh_tokens_from_cookie(&headers), vec!["tok".to_string()]);
+    }
 }
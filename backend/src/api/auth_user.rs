@@ -0,0 +1,201 @@
+// ==============================================================================
+// AUTHENTICATED USER EXTRACTOR
+// ==============================================================================
+//
+// `AuthUser` resolves the caller from the access token (Bearer header for
+// native clients, `access_token` cookie for web) and rejects with 401 when
+// the token is missing or invalid. Scoped third-party tokens get 403 unless
+// the route is guarded by `scopes::require_scope` (see `scopes`). Tokens
+// revoked at logout (`state.revocations`) are rejected with 401.
+//
+// Accounts flagged `must_change_password` get tokens carrying `pwd_change`;
+// `AuthUser` rejects those with 403 `PASSWORD_CHANGE_REQUIRED`, so every
+// protected endpoint is blocked until the password is changed. The one
+// exception is the change-password endpoint, which extracts
+// `PasswordChangeUser` instead.
+//
+// USAGE:
+// ```rust
+// async fn me(user: AuthUser) -> impl IntoResponse { ... }          // required
+// async fn version(user: Option<AuthUser>) -> impl IntoResponse { ... } // optional
+// ```
+//
+// ==============================================================================
+
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum::http::request::Parts;
+
+use crate::AppState;
+use super::auth::extract_token_from_request;
+use super::jwt::{validate_access_token_checked, Claims};
+use super::scopes::ScopeGranted;
+use super::ApiError;
+
+/// Role name granting administrative access.
+pub const ADMIN_ROLE: &str = "admin";
+
+/// The authenticated caller, resolved from a valid access token.
+#[allow(dead_code)] // Identity fields are read by protected handlers as they are added
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: i64,
+    pub email: String,
+    pub roles: Vec<String>,
+    pub claims: Claims,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        self.roles.iter().any(|r| r == ADMIN_ROLE)
+    }
+
+    async fn from_parts(parts: &Parts, state: &AppState) -> Result<Self, ApiError> {
+        let user = Self::from_parts_pending_password_change(parts, state).await?;
+        if user.claims.pwd_change {
+            return Err(ApiError::PasswordChangeRequired);
+        }
+        Ok(user)
+    }
+
+    /// `from_parts` without the password-change requirement.
+    async fn from_parts_pending_password_change(parts: &Parts, state: &AppState) -> Result<Self, ApiError> {
+        let token = extract_token_from_request(&parts.headers)
+            .ok_or_else(|| ApiError::Unauthorized("Authentication required".to_string()))?;
+
+        let claims = validate_access_token_checked(&token, state.clock.as_ref(), &state.revocations).await?;
+        if claims.is_scoped() && parts.extensions.get::<ScopeGranted>().is_none() {
+            return Err(ApiError::Forbidden("Scoped tokens cannot access this endpoint".to_string()));
+        }
+
+        Ok(Self {
+            user_id: claims.user_id()?,
+            email: claims.email.clone(),
+            roles: claims.roles.clone(),
+            claims,
+        })
+    }
+}
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        Self::from_parts(parts, state).await
+    }
+}
+
+/// An authenticated caller that may still have to change their password.
+///
+/// Only for the change-password endpoint; everything else uses `AuthUser`.
+#[derive(Debug, Clone)]
+pub struct PasswordChangeUser(pub AuthUser);
+
+impl FromRequestParts<AppState> for PasswordChangeUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        AuthUser::from_parts_pending_password_change(parts, state).await.map(Self)
+    }
+}
+
+/// `Option<AuthUser>` never rejects: a missing OR invalid token yields `None`,
+/// so public endpoints stay reachable with a stale cookie.
+impl OptionalFromRequestParts<AppState> for AuthUser {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Option<Self>, Self::Rejection> {
+        Ok(Self::from_parts(parts, state).await.ok())
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::{routing::get, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use crate::clock::{MockClock, SystemClock};
+    use crate::config::AppConfig;
+
+    fn state() -> AppState {
+        AppState::new(AppConfig::default(), None)
+    }
+
+    #[tokio::test]
+    async fn test_protected_route_401_carries_www_authenticate() {
+        let app = Router::new()
+            .route("/me", get(|user: AuthUser| async move { user.email }))
+            .with_state(state());
+
+        for bearer in [None, Some("Bearer not-a-token")] {
+            let mut request = Request::builder().uri("/me");
+            if let Some(value) = bearer {
+                request = request.header(header::AUTHORIZATION, value);
+            }
+            let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                response.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+                super::super::BEARER_CHALLENGE
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pending_password_change_blocks_protected_routes() {
+        let app = Router::new()
+            .route("/me", get(|user: AuthUser| async move { user.email }))
+            .route("/change-password", get(|PasswordChangeUser(user): PasswordChangeUser| async move { user.email }))
+            .with_state(state());
+        let pair = crate::api::jwt::generate_token_pair_with(&SystemClock, 5, "new@example.com", &[], true).unwrap();
+        let request = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", pair.access_token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/me")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "PASSWORD_CHANGE_REQUIRED");
+
+        let response = app.oneshot(request("/change-password")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_token_expiry_follows_state_clock() {
+        let clock = Arc::new(MockClock::starting_now());
+        let mut state = state();
+        state.clock = clock.clone();
+        let app = Router::new()
+            .route("/me", get(|user: AuthUser| async move { user.email }))
+            .with_state(state);
+        let pair = crate::api::jwt::generate_token_pair_with(clock.as_ref(), 5, "user@example.com", &[], false).unwrap();
+        let request = || {
+            Request::builder()
+                .uri("/me")
+                .header(header::AUTHORIZATION, format!("Bearer {}", pair.access_token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Expired by the state's clock, even though no wall time has passed
+        clock.advance(chrono::Duration::hours(1));
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
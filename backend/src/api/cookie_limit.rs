@@ -0,0 +1,134 @@
+// ==============================================================================
+// SET-COOKIE COUNT GUARD
+// ==============================================================================
+//
+// Logs a warning when a response carries more than `MAX_SET_COOKIES`
+// (default 4) `Set-Cookie` headers. The busiest responses set three cookies
+// (access + refresh on login/refresh, plus the CSRF cookie), so going over
+// the limit usually means a cookie is being set twice on one path.
+//
+// - Warning only: the response is passed through unchanged
+// - The warning names the method, path, count and cookie names (never values)
+// - `MAX_SET_COOKIES=0` disables the check
+//
+// ==============================================================================
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::AppState;
+
+/// Default for `MAX_SET_COOKIES`.
+pub const DEFAULT_MAX_SET_COOKIES: usize = 4;
+
+/// Warn about responses setting more than `config.max_set_cookies` cookies.
+pub async fn set_cookie_limit_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(max) = state.config.max_set_cookies else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    let names = set_cookie_names(response.headers());
+    if names.len() > max {
+        tracing::warn!(
+            %method,
+            %path,
+            count = names.len(),
+            max,
+            cookies = %names.join(","),
+            "Response sets more cookies than expected (possible redundant Set-Cookie)"
+        );
+    }
+    response
+}
+
+/// Names of the cookies set by `headers`, in header order.
+fn set_cookie_names(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|value| {
+            let value = value.to_str().unwrap_or_default();
+            value.split_once('=').map_or(value, |(name, _)| name).trim().to_string()
+        })
+        .collect()
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::cookies::CookieJar;
+    use crate::test_support::TestApp;
+    use axum::routing::get;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Captures formatted log output for assertions.
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Respond to `/cookies` with `count` cookies and return the log output.
+    async fn get_cookies(max: usize, count: usize) -> String {
+        let config = crate::config::AppConfig {
+            max_set_cookies: Some(max),
+            ..Default::default()
+        };
+        let app = TestApp::builder()
+            .config(config)
+            .route(
+                "/cookies",
+                get(move || async move {
+                    (0..count).fold(CookieJar::new(), |jar, i| jar.add(format!("c{i}=secret{i}; Path=/")))
+                }),
+            )
+            .build()
+            .await;
+
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let response = app.get("/cookies").await;
+        assert_eq!(response.headers.get_all(header::SET_COOKIE).iter().count(), count);
+        let output = String::from_utf8_lossy(&logs.0.lock().unwrap()).into_owned();
+        output
+    }
+
+    #[tokio::test]
+    async fn test_too_many_cookies_logs_warning() {
+        let output = get_cookies(2, 3).await;
+        assert!(output.contains("sets more cookies than expected"), "{output}");
+        assert!(output.contains("count=3") && output.contains("c0,c1,c2"), "{output}");
+        assert!(!output.contains("secret"), "cookie values must not be logged: {output}");
+    }
+
+    #[tokio::test]
+    async fn test_cookies_within_limit_are_quiet() {
+        let output = get_cookies(3, 3).await;
+        assert!(!output.contains("sets more cookies than expected"), "{output}");
+    }
+}
@@ -0,0 +1,235 @@
+// ==============================================================================
+// SET-COOKIE HELPER
+// ==============================================================================
+//
+// `CookieJar` accumulates `Set-Cookie` values for a response:
+//
+// ```rust
+// (StatusCode::OK, CookieJar::new().add(access).add(refresh), ApiJson(body))
+// ```
+//
+// - Each cookie becomes its own `Set-Cookie` header (appended, never overwritten)
+// - Values are validated as header values; if any cookie contains bytes that
+//   aren't allowed in a header, the whole response becomes a 500 instead of
+//   panicking or silently dropping the cookie
+//
+// PARSING:
+// - `cookie_pairs` splits a request `Cookie` header into trimmed `(name, value)`
+//   pairs; cookie lookups (auth tokens, CSRF) go through it
+// - `parse_set_cookie` (tests only) turns a `Set-Cookie` value into a
+//   `ParsedCookie`, so tests assert on attributes rather than substrings
+//
+// ==============================================================================
+
+use axum::http::header::{self, HeaderValue, InvalidHeaderValue};
+use axum::response::{IntoResponse, IntoResponseParts, Response, ResponseParts};
+
+use super::ApiError;
+
+/// Set of `Set-Cookie` headers to attach to a response.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Vec<Result<HeaderValue, InvalidHeaderValue>>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a serialized cookie (`name=value; Path=/; ...`).
+    pub fn add(mut self, cookie: impl AsRef<str>) -> Self {
+        self.cookies.push(HeaderValue::from_str(cookie.as_ref()));
+        self
+    }
+
+    /// Validated header values, in insertion order.
+    pub fn header_values(self) -> Result<Vec<HeaderValue>, ApiError> {
+        self.cookies
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .map_err(|e| ApiError::internal("Failed to set cookie", e.to_string()))
+    }
+}
+
+impl IntoResponseParts for CookieJar {
+    type Error = ApiError;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        for value in self.header_values()? {
+            res.headers_mut().append(header::SET_COOKIE, value);
+        }
+        Ok(res)
+    }
+}
+
+/// A jar on its own is an empty-bodied response, e.g. `(StatusCode::NO_CONTENT, jar)`.
+impl IntoResponse for CookieJar {
+    fn into_response(self) -> Response {
+        (self, ()).into_response()
+    }
+}
+
+// ==============================================================================
+// PARSING
+// ==============================================================================
+
+/// `(name, value)` pairs of a `Cookie` header value (`a=1; b=2`), trimmed,
+/// in order. Segments without `=` are skipped; values may be empty.
+pub fn cookie_pairs(header: &str) -> impl Iterator<Item = (&str, &str)> {
+    header
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.trim(), value.trim()))
+}
+
+/// A `Set-Cookie` value split into its parts.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedCookie {
+    pub name: String,
+    pub value: String,
+    /// Attributes in order; flags such as `HttpOnly` have no value.
+    pub attributes: Vec<(String, Option<String>)>,
+}
+
+#[cfg(test)]
+impl ParsedCookie {
+    /// Attribute `name` (case-insensitive): `Some(None)` for a flag,
+    /// `Some(Some(value))` for `name=value`, `None` when absent.
+    pub fn attribute(&self, name: &str) -> Option<Option<&str>> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_deref())
+    }
+
+    /// Value of attribute `name`, if present with a value.
+    pub fn attribute_value(&self, name: &str) -> Option<&str> {
+        self.attribute(name).flatten()
+    }
+
+    pub fn http_only(&self) -> bool {
+        self.attribute("HttpOnly").is_some()
+    }
+
+    pub fn secure(&self) -> bool {
+        self.attribute("Secure").is_some()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.attribute_value("Path")
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.attribute_value("Domain")
+    }
+
+    pub fn same_site(&self) -> Option<&str> {
+        self.attribute_value("SameSite")
+    }
+
+    pub fn max_age(&self) -> Option<i64> {
+        self.attribute_value("Max-Age").and_then(|v| v.parse().ok())
+    }
+}
+
+/// Parse a `Set-Cookie` value (`name=value; Attr; Attr=value`).
+///
+/// A first segment without `=` yields a cookie with that name and an empty
+/// value; empty attribute segments are skipped.
+#[cfg(test)]
+pub fn parse_set_cookie(set_cookie: &str) -> ParsedCookie {
+    let mut segments = set_cookie.split(';');
+    let first = segments.next().unwrap_or_default();
+    let (name, value) = first.split_once('=').unwrap_or((first, ""));
+    let attributes = segments
+        .map(str::trim)
+        .filter(|attr| !attr.is_empty())
+        .map(|attr| match attr.split_once('=') {
+            Some((key, value)) => (key.trim().to_string(), Some(value.trim().to_string())),
+            None => (attr.to_string(), None),
+        })
+        .collect();
+
+    ParsedCookie {
+        name: name.trim().to_string(),
+        value: value.trim().to_string(),
+        attributes,
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_access_and_refresh_produce_two_set_cookie_headers() {
+        let jar = CookieJar::new()
+            .add("access_token=a; Path=/; HttpOnly")
+            .add("refresh_token=r; Path=/api/v1/auth; HttpOnly");
+
+        let response = (StatusCode::OK, jar, "ok").into_response();
+        let cookies: Vec<_> = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            cookies,
+            ["access_token=a; Path=/; HttpOnly", "refresh_token=r; Path=/api/v1/auth; HttpOnly"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_cookie_bytes_fail_safely() {
+        let jar = CookieJar::new().add("access_token=a").add("bad=\r\nInjected: yes");
+
+        let response = (StatusCode::OK, jar, "ok").into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+    }
+
+    #[test]
+    fn test_parse_full_set_cookie() {
+        let cookie = parse_set_cookie(
+            "refresh_token=abc.def=; HttpOnly; SameSite=Strict; Path=/api/v1/auth; Domain=example.com; Max-Age=604800; Secure",
+        );
+
+        assert_eq!(cookie.name, "refresh_token");
+        assert_eq!(cookie.value, "abc.def=");
+        assert!(cookie.http_only());
+        assert!(cookie.secure());
+        assert_eq!(cookie.same_site(), Some("Strict"));
+        assert_eq!(cookie.path(), Some("/api/v1/auth"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.max_age(), Some(604800));
+        assert_eq!(cookie.attribute("httponly"), Some(None));
+        assert_eq!(cookie.attributes.len(), 6);
+    }
+
+    #[test]
+    fn test_parse_minimal_set_cookie() {
+        let cookie = parse_set_cookie("csrf_token=; Max-Age=0");
+        assert_eq!(cookie.name, "csrf_token");
+        assert_eq!(cookie.value, "");
+        assert_eq!(cookie.max_age(), Some(0));
+        assert!(!cookie.http_only() && !cookie.secure());
+        assert_eq!(cookie.path(), None);
+    }
+
+    #[test]
+    fn test_cookie_pairs_trims_and_skips_malformed() {
+        let pairs: Vec<_> = cookie_pairs(" a=1;junk; b = 2 ;c=").collect();
+        assert_eq!(pairs, [("a", "1"), ("b", "2"), ("c", "")]);
+    }
+}
@@ -0,0 +1,125 @@
+// ==============================================================================
+// CORS POLICY
+// ==============================================================================
+//
+// The CORS policy in one place: `layer` builds the `CorsLayer` applied in
+// `app.rs`, and `GET /api/v1/debug/cors` reports the same values, so the
+// preview can't drift from what the server enforces.
+//
+// GET /api/v1/debug/cors (development only; 404 in production)
+// Returns the effective policy as JSON. `allowed_origins` lists the
+// `ALLOWED_ORIGINS` entries actually in effect (exact match, as browsers
+// send them); `ignored_origins` lists entries dropped as malformed.
+//
+// ==============================================================================
+
+use axum::extract::State;
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use serde::Serialize;
+use tower_http::cors::CorsLayer;
+
+use super::ip_rate_limit::EXPOSED_HEADERS;
+use super::json::ApiJson;
+use super::ApiError;
+use crate::config::validate_origin;
+use crate::AppState;
+
+/// Methods cross-origin requests may use.
+pub const ALLOWED_METHODS: [Method; 4] = [Method::GET, Method::POST, Method::PUT, Method::DELETE];
+
+/// Request headers cross-origin requests may send.
+pub const ALLOWED_HEADERS: [HeaderName; 4] = [
+    header::CONTENT_TYPE,
+    header::AUTHORIZATION,
+    header::ACCEPT,
+    HeaderName::from_static("x-client-type"),
+];
+
+/// Whether cross-origin requests may carry cookies (required for cookie auth).
+pub const ALLOW_CREDENTIALS: bool = true;
+
+/// The CORS layer for `origins` (from `AppConfig::cors_origins`).
+pub fn layer(origins: Vec<HeaderValue>) -> CorsLayer {
+    CorsLayer::new()
+        .allow_methods(ALLOWED_METHODS)
+        .allow_headers(ALLOWED_HEADERS)
+        .expose_headers(EXPOSED_HEADERS)
+        .allow_origin(origins)
+        .allow_credentials(ALLOW_CREDENTIALS)
+}
+
+#[derive(Debug, Serialize)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>,
+    pub ignored_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub exposed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// Preflight cache lifetime in seconds; `null` when no
+    /// `Access-Control-Max-Age` is sent (browsers apply their own default).
+    pub max_age_seconds: Option<u64>,
+}
+
+/// GET /debug/cors - the effective CORS policy (development only).
+pub async fn debug_cors(State(state): State<AppState>) -> Result<ApiJson<CorsPolicy>, ApiError> {
+    if state.config.is_production() {
+        return Err(ApiError::NotFound("Not found".to_string()));
+    }
+
+    let (allowed_origins, ignored_origins) = state
+        .config
+        .allowed_origins
+        .iter()
+        .cloned()
+        .partition(|origin| validate_origin(origin).is_ok() && HeaderValue::from_str(origin).is_ok());
+
+    Ok(ApiJson(CorsPolicy {
+        allowed_origins,
+        ignored_origins,
+        allowed_methods: ALLOWED_METHODS.iter().map(Method::to_string).collect(),
+        allowed_headers: ALLOWED_HEADERS.iter().map(HeaderName::to_string).collect(),
+        exposed_headers: EXPOSED_HEADERS.iter().map(HeaderName::to_string).collect(),
+        allow_credentials: ALLOW_CREDENTIALS,
+        max_age_seconds: None,
+    }))
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::test_support::TestApp;
+    use axum::http::StatusCode;
+
+    async fn get_policy(environment: &str) -> (StatusCode, serde_json::Value) {
+        let config = AppConfig {
+            environment: environment.to_string(),
+            allowed_origins: vec!["https://app.example.com".to_string(), "app.example.com/".to_string()],
+            ..AppConfig::default()
+        };
+        let response = TestApp::builder().config(config).build().await.get("/api/v1/debug/cors").await;
+        (response.status, response.json)
+    }
+
+    #[tokio::test]
+    async fn test_reports_configured_origins_in_development() {
+        let (status, json) = get_policy("development").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["allowed_origins"], serde_json::json!(["https://app.example.com"]));
+        assert_eq!(json["ignored_origins"], serde_json::json!(["app.example.com/"]));
+        assert_eq!(json["allowed_methods"], serde_json::json!(["GET", "POST", "PUT", "DELETE"]));
+        assert_eq!(json["allow_credentials"], true);
+        assert!(json["allowed_headers"].as_array().unwrap().contains(&"x-client-type".into()));
+    }
+
+    #[tokio::test]
+    async fn test_not_found_in_production() {
+        let (status, json) = get_policy("production").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(json.get("allowed_origins").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+// ==============================================================================
+// REJECTED CORS REQUEST LOGGING
+// ==============================================================================
+//
+// When a browser blocks a response because of CORS, the server never hears
+// about it: the request "works in Postman but not the browser". This
+// middleware logs requests whose `Origin` is not in `ALLOWED_ORIGINS`,
+// including preflights, so the mismatch shows up in the server logs.
+//
+// - Logged at debug under the `cors` target: enable with `RUST_LOG=cors=debug`
+// - CORS behaviour itself is unchanged (`CorsLayer` in `app.rs`)
+// - Layer it OUTSIDE the `CorsLayer`, which answers preflights itself
+// - Same-origin requests that send `Origin` (e.g. form POSTs) are logged
+//   too, unless that origin is listed
+//
+// ==============================================================================
+
+use axum::extract::{Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::AppState;
+
+/// Log requests from origins CORS will not allow, then pass them on.
+pub async fn cors_reject_log_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if let Some(origin) = request.headers().get(header::ORIGIN) {
+        let origin = origin.to_str().unwrap_or("<non-ascii>");
+        if !state.config.is_allowed_origin(origin) {
+            tracing::debug!(
+                target: "cors",
+                origin,
+                method = %request.method(),
+                path = request.uri().path(),
+                "CORS: origin not in ALLOWED_ORIGINS; the browser will block the response"
+            );
+        }
+    }
+    next.run(request).await
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    /// Captures formatted log output for assertions.
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn logs_for_origin(origin: &str) -> String {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = crate::config::AppConfig {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            ..Default::default()
+        };
+        let state = AppState::new(config, None);
+        let app = Router::new()
+            .route("/ping", get(|| async { "pong" }))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), cors_reject_log_middleware))
+            .with_state(state);
+        let request = Request::builder()
+            .uri("/ping")
+            .header(header::ORIGIN, origin)
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        let output = String::from_utf8_lossy(&logs.0.lock().unwrap()).into_owned();
+        output
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_origin_is_logged() {
+        let output = logs_for_origin("https://evil.example.net").await;
+        assert!(output.contains("origin not in ALLOWED_ORIGINS"), "{output}");
+        assert!(output.contains("https://evil.example.net"), "{output}");
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origin_is_not_logged() {
+        let output = logs_for_origin("https://app.example.com").await;
+        assert!(!output.contains("ALLOWED_ORIGINS"), "{output}");
+    }
+}
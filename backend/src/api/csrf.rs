@@ -17,26 +17,219 @@
 // - AND attacker can't set custom headers on cross-origin requests
 // - So attacker can't provide the matching X-CSRF-Token header
 //
+// OPTIONAL STATEFUL MODE (`CSRF_MODE=stateful`):
+// - Issued tokens are also recorded server-side in a `CsrfStore`, bound to
+//   the authenticated user with an expiry. Anonymous clients are bound to a
+//   random per-client id, handed out as an HttpOnly `csrf_session` cookie
+//   the first time they fetch a token
+// - The middleware validates the header token against the store, so tokens
+//   can be revoked server-side (e.g. on logout)
+// - Records live in the shared `KeyValueStore` (`STORE_BACKEND`), so with
+//   Redis they survive restarts and work across replicas
+// - At most `MAX_CSRF_TOKENS_PER_SESSION` (default 10) tokens are live per
+//   session; issuing more evicts the oldest, so a client can't grow the
+//   store without bound
+// - Default remains the stateless double-submit pattern
+//
+// NATIVE CLIENTS:
+// - Requests with `X-Client-Type: native` use Bearer tokens and skip CSRF
+// - Anyone can set that header, so with `REQUIRE_CLIENT_ATTESTATION=true`
+//   the bypass additionally requires `X-Client-Attestation` to match
+//   `CLIENT_ATTESTATION_SECRET`; without it the request is treated as a
+//   web request and CSRF is enforced
+//
 // ==============================================================================
 
 use axum::{
-    extract::Request,
-    http::{header, HeaderMap, HeaderValue, StatusCode},
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use rand::Rng;
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+use crate::store::{self, KeyValueStore, MemoryStore, StoreError};
+use crate::AppState;
+use super::auth::extract_token_from_request;
+use super::cookies::{cookie_pairs, CookieJar};
+use super::json::ApiJson;
+use super::jwt::validate_access_token_checked;
+use super::security::constant_time_eq;
+use super::ApiError;
 
 /// Cookie name for CSRF token
 const CSRF_COOKIE_NAME: &str = "csrf_token";
 
+/// Cookie carrying an anonymous client's stateful CSRF session id
+const CSRF_SESSION_COOKIE_NAME: &str = "csrf_session";
+
 /// Header name for CSRF token
 const CSRF_HEADER_NAME: &str = "x-csrf-token";
 
+/// Header native clients attest themselves with (`REQUIRE_CLIENT_ATTESTATION`)
+const CLIENT_ATTESTATION_HEADER: &str = "x-client-attestation";
+
 /// CSRF token length in bytes (32 bytes = 256 bits)
 const CSRF_TOKEN_LENGTH: usize = 32;
 
+/// Lifetime of a stateful CSRF token.
+const CSRF_TOKEN_TTL: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Default cap on live stateful tokens per session (`MAX_CSRF_TOKENS_PER_SESSION`).
+pub const DEFAULT_MAX_CSRF_TOKENS_PER_SESSION: usize = 10;
+
+/// How CSRF tokens are validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsrfMode {
+    /// Stateless double-submit cookie (default).
+    DoubleSubmit,
+    /// Tokens recorded and validated server-side.
+    Stateful,
+}
+
+impl CsrfMode {
+    /// Read `CSRF_MODE` (`double_submit` | `stateful`). Defaults to double-submit.
+    pub fn from_env() -> Self {
+        match env::var("CSRF_MODE").map(|v| v.to_lowercase()) {
+            Ok(v) if v == "stateful" => CsrfMode::Stateful,
+            _ => CsrfMode::DoubleSubmit,
+        }
+    }
+}
+
+/// Server-side record of issued CSRF tokens (stateful mode only).
+///
+/// Tokens are bound to a session key (see `session_key`) and expire after
+/// `CSRF_TOKEN_TTL`. Records live in the shared `KeyValueStore`:
+/// - `csrf:<token>` holds `<issued_at_ms>:<session>`
+/// - `csrf-revoked:<session>` holds the time of the session's last logout;
+///   tokens issued before it are rejected (no key scan needed to revoke)
+/// - `csrf-session:<session>` lists the session's live tokens, oldest first,
+///   to enforce the per-session cap. It is updated with
+///   `KeyValueStore::push_capped`, so concurrent issues for one session
+///   (across replicas) never exceed the cap or evict a token twice
+#[derive(Debug)]
+pub struct CsrfStore {
+    kv: Arc<dyn KeyValueStore>,
+    clock: Arc<dyn Clock>,
+    max_per_session: usize,
+}
+
+impl Default for CsrfStore {
+    fn default() -> Self {
+        Self::new(Arc::new(MemoryStore::default()), Arc::new(SystemClock))
+    }
+}
+
+impl CsrfStore {
+    pub fn new(kv: Arc<dyn KeyValueStore>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            kv,
+            clock,
+            max_per_session: DEFAULT_MAX_CSRF_TOKENS_PER_SESSION,
+        }
+    }
+
+    /// Keep at most `max` live tokens per session (minimum 1).
+    pub fn with_max_per_session(mut self, max: usize) -> Self {
+        self.max_per_session = max.max(1);
+        self
+    }
+
+    /// Read `MAX_CSRF_TOKENS_PER_SESSION` (default 10).
+    pub fn max_per_session_from_env() -> usize {
+        env::var("MAX_CSRF_TOKENS_PER_SESSION")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|max| *max > 0)
+            .unwrap_or(DEFAULT_MAX_CSRF_TOKENS_PER_SESSION)
+    }
+
+    /// Issue and record a new token for `session`, evicting the session's
+    /// oldest tokens beyond the cap.
+    pub async fn issue(&self, session: &str) -> Result<String, StoreError> {
+        let token = generate_csrf_token();
+        let record = format!("{}:{session}", self.clock.now().timestamp_millis());
+        let (session, max) = (session.to_string(), self.max_per_session);
+        let issued = token.clone();
+        store::call(&self.kv, move |kv| {
+            kv.set(&format!("csrf:{issued}"), &record, CSRF_TOKEN_TTL)?;
+            // Every listed token expires within the TTL of the newest one
+            let evicted = kv.push_capped(&format!("csrf-session:{session}"), &issued, max, CSRF_TOKEN_TTL)?;
+            for evicted in evicted {
+                kv.delete(&format!("csrf:{evicted}"))?;
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(token)
+    }
+
+    /// True if `token` was issued to `session` and hasn't expired or been revoked.
+    ///
+    /// Fails closed: a store error rejects the token.
+    pub async fn validate(&self, session: &str, token: &str) -> bool {
+        let (session, token) = (session.to_string(), token.to_string());
+        let lookup = store::call(&self.kv, move |kv| {
+            let Some(record) = kv.get(&format!("csrf:{token}"))? else {
+                return Ok(false);
+            };
+            let Some((issued_at, bound)) = record.split_once(':') else {
+                return Ok(false);
+            };
+            if !constant_time_eq(bound, &session) {
+                return Ok(false);
+            }
+            let revoked_at = kv.get(&format!("csrf-revoked:{session}"))?;
+            Ok(match (issued_at.parse::<i64>(), revoked_at.and_then(|v| v.parse::<i64>().ok())) {
+                (Ok(issued), Some(revoked)) => issued > revoked,
+                (Ok(_), None) => true,
+                (Err(_), _) => false,
+            })
+        });
+        lookup.await.unwrap_or_else(|err| {
+            tracing::error!("CSRF store lookup failed: {err}");
+            false
+        })
+    }
+
+    /// Revoke every token bound to `session` (called on logout).
+    pub async fn invalidate_session(&self, session: &str) {
+        let key = format!("csrf-revoked:{session}");
+        let now = self.clock.now().timestamp_millis().to_string();
+        // Outlives every token issued before now, so they all stay revoked
+        if let Err(err) = store::call(&self.kv, move |kv| kv.set(&key, &now, CSRF_TOKEN_TTL)).await {
+            tracing::error!("Failed to revoke CSRF tokens: {err}");
+        }
+    }
+}
+
+/// Session key a stateful CSRF token is bound to.
+///
+/// The authenticated user's id when a valid access token is present,
+/// otherwise `anon:<id>` from the `csrf_session` cookie. `None` for an
+/// anonymous client that hasn't been given a session id yet.
+pub async fn session_key(headers: &HeaderMap, state: &AppState) -> Option<String> {
+    if let Some(token) = extract_token_from_request(headers) {
+        if let Ok(claims) = validate_access_token_checked(&token, state.clock.as_ref(), &state.revocations).await {
+            return Some(claims.sub);
+        }
+    }
+    anonymous_session_id(headers).map(|id| format!("anon:{id}"))
+}
+
+/// The `csrf_session` cookie, if it has the shape `generate_csrf_token` produces.
+fn anonymous_session_id(headers: &HeaderMap) -> Option<&str> {
+    cookie_pairs(headers.get(header::COOKIE)?.to_str().ok()?)
+        .find(|(name, _)| *name == CSRF_SESSION_COOKIE_NAME)
+        .map(|(_, value)| value)
+        .filter(|id| id.len() == CSRF_TOKEN_LENGTH * 2 && id.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
 /// Generate a cryptographically secure random CSRF token
 pub fn generate_csrf_token() -> String {
     let mut rng = rand::thread_rng();
@@ -44,21 +237,31 @@ pub fn generate_csrf_token() -> String {
     hex::encode(token)
 }
 
+/// `; Secure` in production, so CSRF cookies never travel over plain HTTP.
+fn secure_flag(production: bool) -> &'static str {
+    if production { "; Secure" } else { "" }
+}
+
 /// Build CSRF cookie value
-pub fn build_csrf_cookie(token: &str) -> String {
-    let is_production = env::var("ENVIRONMENT")
-        .map(|v| v.to_lowercase() == "production" || v.to_lowercase() == "prod")
-        .unwrap_or(false);
-    
-    let secure_flag = if is_production { "; Secure" } else { "" };
-    
+pub fn build_csrf_cookie(token: &str, production: bool) -> String {
     // Note: This cookie is NOT HttpOnly because JavaScript needs to read it
     // to include in the X-CSRF-Token header
     format!(
         "{}={}; SameSite=Lax; Path=/{}",
         CSRF_COOKIE_NAME,
         token,
-        secure_flag
+        secure_flag(production)
+    )
+}
+
+/// Build the anonymous CSRF session cookie (stateful mode). Only the server
+/// reads it, so unlike the token cookie it is HttpOnly.
+fn build_csrf_session_cookie(id: &str, production: bool) -> String {
+    format!(
+        "{}={}; HttpOnly; SameSite=Lax; Path=/{}",
+        CSRF_SESSION_COOKIE_NAME,
+        id,
+        secure_flag(production)
     )
 }
 
@@ -72,7 +275,11 @@ pub fn build_csrf_cookie(token: &str) -> String {
 /// 2. Extract CSRF token from X-CSRF-Token header
 /// 3. Compare them (constant-time comparison)
 /// 4. Reject if they don't match
+///
+/// In stateful mode, step 1-3 are replaced by looking the header token up
+/// in the `CsrfStore` for the caller's session.
 pub async fn csrf_middleware(
+    State(state): State<AppState>,
     headers: HeaderMap,
     request: Request,
     next: Next,
@@ -88,21 +295,40 @@ pub async fn csrf_middleware(
     }
     
     // Skip CSRF check for native clients (they use Bearer tokens, not cookies)
-    if let Some(client_type) = headers.get("x-client-type") {
-        if client_type.to_str().unwrap_or("").to_lowercase() == "native" {
+    if is_native_client(&headers) {
+        if is_attested(&headers, state.config.client_attestation_secret.as_deref()) {
             return next.run(request).await;
         }
+        tracing::debug!("Native client without valid attestation; enforcing CSRF");
     }
     
-    // Extract CSRF token from cookie
-    let cookie_token = extract_csrf_from_cookie(&headers);
-    
     // Extract CSRF token from header
     let header_token = headers
         .get(CSRF_HEADER_NAME)
         .and_then(|v| v.to_str().ok())
         .map(String::from);
     
+    // Stateful mode: the server-side record is the source of truth
+    if let Some(store) = &state.csrf_store {
+        let session = session_key(&headers, &state).await;
+        return match (header_token, session) {
+            (Some(token), Some(session)) if store.validate(&session, &token).await => next.run(request).await,
+            _ => {
+                tracing::warn!("CSRF validation failed: token not issued for this session");
+                (
+                    StatusCode::FORBIDDEN,
+                    ApiJson(serde_json::json!({
+                        "error": "CSRF token invalid"
+                    })),
+                )
+                    .into_response()
+            }
+        };
+    }
+    
+    // Extract CSRF token from cookie
+    let cookie_token = extract_csrf_from_cookie(&headers);
+    
     // Validate tokens match
     match (cookie_token, header_token) {
         (Some(cookie), Some(header)) if constant_time_eq(&cookie, &header) => {
@@ -115,7 +341,7 @@ pub async fn csrf_middleware(
             tracing::warn!("CSRF validation failed: no cookie token");
             (
                 StatusCode::FORBIDDEN,
-                axum::Json(serde_json::json!({
+                ApiJson(serde_json::json!({
                     "error": "CSRF token missing. Fetch /api/v1/csrf first."
                 })),
             )
@@ -126,7 +352,7 @@ pub async fn csrf_middleware(
             tracing::warn!("CSRF validation failed: token mismatch");
             (
                 StatusCode::FORBIDDEN,
-                axum::Json(serde_json::json!({
+                ApiJson(serde_json::json!({
                     "error": "CSRF token invalid"
                 })),
             )
@@ -135,33 +361,31 @@ pub async fn csrf_middleware(
     }
 }
 
-/// Extract CSRF token from cookie header
-fn extract_csrf_from_cookie(headers: &HeaderMap) -> Option<String> {
+/// Whether the request claims to come from a native client (`X-Client-Type: native`).
+fn is_native_client(headers: &HeaderMap) -> bool {
     headers
-        .get(header::COOKIE)?
-        .to_str()
-        .ok()?
-        .split(';')
-        .find_map(|cookie| {
-            let cookie = cookie.trim();
-            cookie
-                .strip_prefix(&format!("{}=", CSRF_COOKIE_NAME))
-                .filter(|v| !v.is_empty())
-                .map(String::from)
-        })
+        .get("x-client-type")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("native"))
 }
 
-/// Constant-time string comparison to prevent timing attacks
-fn constant_time_eq(a: &str, b: &str) -> bool {
-    if a.len() != b.len() {
-        return false;
-    }
-    
-    let mut result = 0u8;
-    for (x, y) in a.bytes().zip(b.bytes()) {
-        result |= x ^ y;
-    }
-    result == 0
+/// Whether a native client may skip CSRF: always when no attestation secret
+/// is configured, otherwise only with a matching `X-Client-Attestation`.
+fn is_attested(headers: &HeaderMap, secret: Option<&str>) -> bool {
+    let Some(secret) = secret else {
+        return true;
+    };
+    headers
+        .get(CLIENT_ATTESTATION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|presented| constant_time_eq(presented, secret))
+}
+
+/// Extract CSRF token from cookie header
+fn extract_csrf_from_cookie(headers: &HeaderMap) -> Option<String> {
+    cookie_pairs(headers.get(header::COOKIE)?.to_str().ok()?)
+        .find(|(name, value)| *name == CSRF_COOKIE_NAME && !value.is_empty())
+        .map(|(_, value)| value.to_string())
 }
 
 /// Handler to get a new CSRF token
@@ -171,14 +395,38 @@ fn constant_time_eq(a: &str, b: &str) -> bool {
 /// Returns a CSRF token in both:
 /// 1. Response body (for JavaScript to read)
 /// 2. Set-Cookie header (for browser to store)
-pub async fn get_csrf_token() -> Response {
-    let token = generate_csrf_token();
-    let cookie = build_csrf_cookie(&token);
+///
+/// In stateful mode the token is also recorded in the `CsrfStore`, and an
+/// anonymous client without a session id is given one (`csrf_session`).
+pub async fn get_csrf_token(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let mut jar = CookieJar::new();
+    let token = match &state.csrf_store {
+        Some(store) => {
+            let session = match session_key(&headers, &state).await {
+                Some(session) => session,
+                None => {
+                    let id = generate_csrf_token();
+                    jar = jar.add(build_csrf_session_cookie(&id, state.config.is_production()));
+                    format!("anon:{id}")
+                }
+            };
+            match store.issue(&session).await {
+                Ok(token) => token,
+                Err(err) => {
+                    tracing::error!("Failed to record CSRF token: {err}");
+                    return ApiError::ServiceUnavailable("CSRF tokens temporarily unavailable".to_string())
+                        .into_response();
+                }
+            }
+        }
+        None => generate_csrf_token(),
+    };
+    let cookie = build_csrf_cookie(&token, state.config.is_production());
     
     (
         StatusCode::OK,
-        [(header::SET_COOKIE, HeaderValue::from_str(&cookie).unwrap())],
-        axum::Json(serde_json::json!({
+        jar.add(cookie),
+        ApiJson(serde_json::json!({
             "csrf_token": token
         })),
     )
@@ -203,18 +451,241 @@ mod tests {
         assert_ne!(token1, token2);
     }
     
-    #[test]
-    fn test_constant_time_eq_same() {
-        assert!(constant_time_eq("abc123", "abc123"));
+    #[tokio::test]
+    async fn test_stateful_token_issue_and_validate() {
+        let store = CsrfStore::default();
+        let token = store.issue("42").await.unwrap();
+        
+        assert!(store.validate("42", &token).await);
+        // Bound to the issuing session
+        assert!(!store.validate("43", &token).await);
+        // Unknown tokens are rejected
+        assert!(!store.validate("42", &generate_csrf_token()).await);
     }
     
-    #[test]
-    fn test_constant_time_eq_different() {
-        assert!(!constant_time_eq("abc123", "abc124"));
+    #[tokio::test]
+    async fn test_tokens_beyond_session_cap_evict_oldest() {
+        let store = CsrfStore::default().with_max_per_session(3);
+        let mut tokens = Vec::new();
+        for _ in 0..5 {
+            tokens.push(store.issue("42").await.unwrap());
+        }
+
+        assert!(!store.validate("42", &tokens[0]).await);
+        assert!(!store.validate("42", &tokens[1]).await);
+        for newest in &tokens[2..] {
+            assert!(store.validate("42", newest).await);
+        }
+
+        // Other sessions have their own budget
+        let other = store.issue("7").await.unwrap();
+        assert!(store.validate("7", &other).await);
+        assert!(store.validate("42", &tokens[4]).await);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_issues_respect_session_cap() {
+        let store = Arc::new(CsrfStore::default().with_max_per_session(4));
+        let handles: Vec<_> = (0..100)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move { store.issue("42").await.unwrap() })
+            })
+            .collect();
+        let mut tokens = Vec::new();
+        for handle in handles {
+            tokens.push(handle.await.unwrap());
+        }
+
+        let mut live = 0;
+        for token in &tokens {
+            if store.validate("42", token).await {
+                live += 1;
+            }
+        }
+        assert_eq!(live, 4);
+    }
+
+    #[tokio::test]
+    async fn test_stateful_token_invalidated_on_session_logout() {
+        let store = CsrfStore::default();
+        let token = store.issue("42").await.unwrap();
+        let other = store.issue("7").await.unwrap();
+        
+        store.invalidate_session("42").await;
+        
+        assert!(!store.validate("42", &token).await);
+        assert!(store.validate("7", &other).await);
     }
     
-    #[test]
-    fn test_constant_time_eq_different_length() {
-        assert!(!constant_time_eq("abc", "abcd"));
+    #[tokio::test]
+    async fn test_stateful_middleware_validates_against_store() {
+        use axum::{body::Body, routing::post, Router};
+        use std::sync::Arc;
+        use tower::ServiceExt;
+        
+        let mut state = AppState::new(crate::config::AppConfig::default(), None);
+        let store = Arc::new(CsrfStore::default());
+        state.csrf_store = Some(store.clone());
+        
+        let app = Router::new()
+            .route("/thing", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), csrf_middleware))
+            .with_state(state);
+        
+        let post_with = |token: String| {
+            Request::builder()
+                .method("POST")
+                .uri("/thing")
+                .header(CSRF_HEADER_NAME, token)
+                .body(Body::empty())
+                .unwrap()
+        };
+        
+        // A token that matches a cookie but was never issued is rejected
+        let forged = generate_csrf_token();
+        let response = app.clone().oneshot(post_with(forged)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        
+        // An issued token passes until its session is invalidated
+        let token = store.issue("42").await.unwrap();
+        let access = crate::api::jwt::generate_access_token(&SystemClock, 42, "csrf@example.com", &[]).unwrap();
+        let post_as_user = |token: String| {
+            let mut request = post_with(token);
+            request
+                .headers_mut()
+                .insert(header::AUTHORIZATION, format!("Bearer {access}").parse().unwrap());
+            request
+        };
+        let response = app.clone().oneshot(post_as_user(token.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        store.invalidate_session("42").await;
+        let response = app.oneshot(post_as_user(token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_clients_get_separate_sessions() {
+        use axum::{body::Body, routing::{get, post}, Router};
+        use tower::ServiceExt;
+        use super::super::cookies::parse_set_cookie;
+
+        let mut state = AppState::new(crate::config::AppConfig::default(), None);
+        state.csrf_store = Some(Arc::new(CsrfStore::default().with_max_per_session(1)));
+        let app = Router::new()
+            .route("/csrf", get(get_csrf_token))
+            .route("/thing", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), csrf_middleware))
+            .with_state(state);
+
+        // Each anonymous client fetches a token and gets its own session cookie
+        let mut clients = Vec::new();
+        for _ in 0..2 {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri("/csrf").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let session = response
+                .headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .map(|v| parse_set_cookie(v.to_str().unwrap()))
+                .find(|cookie| cookie.name == CSRF_SESSION_COOKIE_NAME)
+                .unwrap();
+            assert!(session.http_only());
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            clients.push((session.value, json["csrf_token"].as_str().unwrap().to_string()));
+        }
+        assert_ne!(clients[0].0, clients[1].0);
+
+        let post_as = |session: &str, token: &str| {
+            let mut request = Request::builder().method("POST").uri("/thing").header(CSRF_HEADER_NAME, token);
+            if !session.is_empty() {
+                request = request.header(header::COOKIE, format!("{CSRF_SESSION_COOKIE_NAME}={session}"));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let status = |request: Request| {
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        // A client's token works only with its own session; issuing for one
+        // client (cap 1) doesn't evict the other's token
+        let ((a_session, a_token), (b_session, b_token)) = (&clients[0], &clients[1]);
+        assert_eq!(status(post_as(a_session, a_token)).await, StatusCode::OK);
+        assert_eq!(status(post_as(b_session, b_token)).await, StatusCode::OK);
+        assert_eq!(status(post_as(b_session, a_token)).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(post_as("", a_token)).await, StatusCode::FORBIDDEN);
+    }
+    
+    fn attestation_app(secret: Option<&str>) -> axum::Router {
+        use axum::routing::post;
+        
+        let config = crate::config::AppConfig {
+            client_attestation_secret: secret.map(String::from),
+            ..Default::default()
+        };
+        let state = AppState::new(config, None);
+        axum::Router::new()
+            .route("/thing", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), csrf_middleware))
+            .with_state(state)
+    }
+    
+    async fn post_native(app: axum::Router, attestation: Option<&str>) -> StatusCode {
+        use tower::ServiceExt;
+        
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/thing")
+            .header("X-Client-Type", "native");
+        if let Some(value) = attestation {
+            request = request.header(CLIENT_ATTESTATION_HEADER, value);
+        }
+        let request = request.body(axum::body::Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+    
+    #[tokio::test]
+    async fn test_native_client_skips_csrf_without_attestation_requirement() {
+        assert_eq!(post_native(attestation_app(None), None).await, StatusCode::OK);
+    }
+    
+    #[tokio::test]
+    async fn test_unattested_native_client_gets_csrf_enforced() {
+        let app = attestation_app(Some("s3cret"));
+        assert_eq!(post_native(app.clone(), None).await, StatusCode::FORBIDDEN);
+        assert_eq!(post_native(app, Some("wrong")).await, StatusCode::FORBIDDEN);
+    }
+    
+    #[tokio::test]
+    async fn test_attested_native_client_skips_csrf() {
+        assert_eq!(post_native(attestation_app(Some("s3cret")), Some("s3cret")).await, StatusCode::OK);
+    }
+    
+    #[tokio::test]
+    async fn test_stateful_tokens_shared_through_kv_store() {
+        use crate::clock::MockClock;
+        
+        // Two replicas sharing one backend see each other's tokens and logouts
+        let kv: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::default());
+        let clock = Arc::new(MockClock::starting_now());
+        let replica_a = CsrfStore::new(kv.clone(), clock.clone());
+        let replica_b = CsrfStore::new(kv, clock.clone());
+        
+        let old = replica_a.issue("42").await.unwrap();
+        assert!(replica_b.validate("42", &old).await);
+        
+        clock.advance(chrono::Duration::seconds(1));
+        replica_b.invalidate_session("42").await;
+        assert!(!replica_a.validate("42", &old).await);
+        
+        clock.advance(chrono::Duration::seconds(1));
+        let fresh = replica_a.issue("42").await.unwrap();
+        assert!(replica_b.validate("42", &fresh).await);
     }
 }
@@ -0,0 +1,168 @@
+// ==============================================================================
+// PER-REQUEST DATABASE CALL BUDGET
+// ==============================================================================
+//
+// Counts database calls made while handling one request and logs a warning
+// when a request makes more than `MAX_DB_CALLS_PER_REQUEST` (default 50), so
+// N+1 query patterns show up in the logs before they show up in latency.
+//
+// - Every `run_db` call counts as one call, as does each `Tx` round trip
+//   (begin, `run`, commit)
+// - The warning names the method and path; it is logged once per request
+// - `MAX_DB_CALLS_PER_REQUEST=0` disables counting
+// - `DB_CALL_BUDGET_STRICT=true` turns an over-budget request into a 500,
+//   to make N+1s impossible to miss in development. Ignored in production
+//
+// Like `server_timing`, the per-request `DbCallBudget` is placed in the
+// request extensions and in a task-local, so repository helpers can count
+// against it without access to the request.
+//
+// ==============================================================================
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::AppState;
+use super::ApiError;
+
+/// Default for `MAX_DB_CALLS_PER_REQUEST`.
+pub const DEFAULT_MAX_DB_CALLS_PER_REQUEST: u32 = 50;
+
+tokio::task_local! {
+    static CURRENT: Arc<DbCallBudget>;
+}
+
+/// Database calls made so far by one request.
+#[derive(Debug, Default)]
+pub struct DbCallBudget {
+    calls: AtomicU32,
+}
+
+impl DbCallBudget {
+    pub fn calls(&self) -> u32 {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+/// Count one database call against the current request, if it is budgeted.
+pub fn record_db_call() {
+    let _ = CURRENT.try_with(|budget| budget.calls.fetch_add(1, Ordering::Relaxed));
+}
+
+/// Count the request's database calls and flag requests over budget.
+pub async fn db_budget_middleware(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let Some(max) = state.config.max_db_calls_per_request else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let budget = Arc::new(DbCallBudget::default());
+    request.extensions_mut().insert(budget.clone());
+
+    let response = CURRENT.scope(budget.clone(), next.run(request)).await;
+
+    let calls = budget.calls();
+    if calls <= max {
+        return response;
+    }
+    tracing::warn!(%method, %path, calls, max, "Request exceeded database call budget (possible N+1 queries)");
+    if state.config.strict_db_call_budget {
+        return ApiError::internal(
+            "Database call budget exceeded",
+            format!("{method} {path} made {calls} database calls (max {max})"),
+        )
+        .into_response();
+    }
+    response
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    use crate::test_support::TestApp;
+
+    /// Captures formatted log output for assertions.
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn test_app(max: u32, strict: bool) -> TestApp {
+        let config = crate::config::AppConfig {
+            max_db_calls_per_request: Some(max),
+            strict_db_call_budget: strict,
+            ..Default::default()
+        };
+        TestApp::builder()
+            .config(config)
+            .route(
+                "/items",
+                get(|| async {
+                    // Stands in for a handler querying once per item (N+1)
+                    for _ in 0..5 {
+                        record_db_call();
+                    }
+                    "ok"
+                }),
+            )
+            .build()
+            .await
+    }
+
+    async fn get_items(app: TestApp) -> (StatusCode, String) {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let status = app.get("/items").await.status;
+        let output = String::from_utf8_lossy(&logs.0.lock().unwrap()).into_owned();
+        (status, output)
+    }
+
+    #[tokio::test]
+    async fn test_over_budget_request_logs_warning() {
+        let (status, output) = get_items(test_app(3, false).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(output.contains("exceeded database call budget"), "{output}");
+        assert!(output.contains("calls=5") && output.contains("/items"), "{output}");
+    }
+
+    #[tokio::test]
+    async fn test_within_budget_request_is_quiet() {
+        let (status, output) = get_items(test_app(5, false).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!output.contains("exceeded database call budget"), "{output}");
+    }
+
+    #[tokio::test]
+    async fn test_strict_budget_fails_request() {
+        let (status, _) = get_items(test_app(3, true).await).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,93 @@
+// ==============================================================================
+// CONDITIONAL GET (ETag / If-None-Match)
+// ==============================================================================
+//
+// Lets polling clients skip re-downloading unchanged resources:
+//
+// 1. Handler derives an ETag from the resource version (e.g. `updated_at`)
+// 2. Response carries `ETag: "<tag>"`
+// 3. Client re-sends it as `If-None-Match`; if it still matches, the server
+//    answers `304 Not Modified` with no body
+//
+// Comparison is weak (RFC 9110 §13.1.2): a `W/` prefix is ignored.
+//
+// ==============================================================================
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+/// Build a quoted strong entity tag from an opaque version string.
+pub fn entity_tag(version: &str) -> String {
+    format!("\"{version}\"")
+}
+
+/// True if the request's `If-None-Match` matches `etag`.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    let etag = strip_weak(etag);
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || strip_weak(candidate) == etag)
+}
+
+/// `304 Not Modified` when the client's copy is current, otherwise `body`
+/// rendered normally. Both carry the `ETag` header.
+pub fn conditional<T: IntoResponse>(headers: &HeaderMap, etag: String, body: T) -> Response {
+    let mut response = if if_none_match(headers, &etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        body.into_response()
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+fn strip_weak(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_with(if_none_match: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(if_none_match).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_if_none_match_variants() {
+        let etag = entity_tag("v1");
+        assert!(if_none_match(&headers_with("\"v1\""), &etag));
+        assert!(if_none_match(&headers_with("W/\"v1\""), &etag));
+        assert!(if_none_match(&headers_with("\"v0\", \"v1\""), &etag));
+        assert!(if_none_match(&headers_with("*"), &etag));
+        assert!(!if_none_match(&headers_with("\"v2\""), &etag));
+        assert!(!if_none_match(&HeaderMap::new(), &etag));
+    }
+
+    #[test]
+    fn test_conditional_returns_304_only_on_match() {
+        let etag = entity_tag("v1");
+
+        let fresh = conditional(&HeaderMap::new(), etag.clone(), "body");
+        assert_eq!(fresh.status(), StatusCode::OK);
+        assert_eq!(fresh.headers()[header::ETAG], "\"v1\"");
+
+        let cached = conditional(&headers_with("\"v1\""), etag, "body");
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(cached.headers()[header::ETAG], "\"v1\"");
+    }
+}
@@ -0,0 +1,101 @@
+// ==============================================================================
+// REQUEST HEADER SIZE GUARD
+// ==============================================================================
+//
+// Rejects requests whose combined header size exceeds `MAX_HEADER_BYTES`
+// with `431 Request Header Fields Too Large`.
+//
+// WHY:
+// - "Cookie bombing": a malicious subdomain or script can plant many large
+//   cookies so every request to the API carries a huge Cookie header
+// - Oversized headers waste CPU in every parser downstream (cookie/token
+//   extraction, CSRF, tracing)
+//
+// ==============================================================================
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::json::ApiJson;
+use crate::AppState;
+
+/// Reject requests with more than `config.max_header_bytes` of headers.
+pub async fn header_size_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let size = total_header_bytes(request.headers());
+
+    if size > state.config.max_header_bytes {
+        tracing::warn!(
+            "Rejecting request with {} header bytes (limit {})",
+            size,
+            state.config.max_header_bytes
+        );
+        return (
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            ApiJson(serde_json::json!({
+                "error": "Request headers too large"
+            })),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Approximate wire size of the headers: `name: value\r\n` per entry.
+fn total_header_bytes(headers: &HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 4)
+        .sum()
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header};
+
+    use crate::test_support::TestApp;
+
+    async fn test_app(max_header_bytes: usize) -> TestApp {
+        let config = crate::config::AppConfig {
+            max_header_bytes,
+            ..Default::default()
+        };
+        TestApp::builder().config(config).build().await
+    }
+
+    fn with_cookie(cookie: &str) -> Request {
+        Request::builder()
+            .uri("/health/live")
+            .header(header::COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_cookie_header_returns_431() {
+        let cookie = format!("bomb={}", "x".repeat(2048));
+        let response = test_app(1024).await.send(with_cookie(&cookie)).await;
+
+        assert_eq!(response.status, StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_normal_headers_pass() {
+        let response = test_app(1024).await.send(with_cookie("access_token=abc")).await;
+
+        assert_eq!(response.status, StatusCode::OK);
+    }
+}
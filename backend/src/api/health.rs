@@ -1,11 +1,101 @@
 use axum::extract::State;
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use axum::Json;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
-use crate::db;
+use crate::db::{self, DbCheckError, MigrationStatus};
 use crate::AppState;
+use super::auth_user::AuthUser;
+use super::json::ApiJson;
+use super::security::constant_time_eq;
+use super::ApiError;
+
+/// Header carrying the internal token when `PROTECT_HEALTH_DETAILS` is on.
+pub const HEALTH_TOKEN_HEADER: &str = "x-health-token";
+
+/// Caches the database readiness result for a short TTL.
+///
+/// Orchestrators often probe `/health/ready` every second on every replica;
+/// without a cache each probe costs a pool checkout plus `SELECT 1`. Probes
+/// within the TTL reuse the last result, so a DB failure is still detected
+/// within one TTL. The lock is held across the check, so concurrent probes
+/// wait for a single in-flight check instead of each running their own.
+#[derive(Debug)]
+pub struct HealthCache {
+    ttl: Duration,
+    last: tokio::sync::Mutex<Option<(Instant, Result<(), NotReadyReason>)>>,
+}
+
+impl HealthCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            last: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Return the cached result if fresh, otherwise run `check` and cache it.
+    pub async fn get_or_check<F, Fut>(&self, check: F) -> Result<(), NotReadyReason>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(), NotReadyReason>>,
+    {
+        let mut last = self.last.lock().await;
+
+        if let Some((checked_at, result)) = last.as_ref() {
+            if checked_at.elapsed() < self.ttl {
+                return *result;
+            }
+        }
+
+        let result = check().await;
+        *last = Some((Instant::now(), result));
+        result
+    }
+}
+
+/// Why the database check failed, as reported by `/health/ready`.
+///
+/// Tells operators whether to look at the pool (`pool_exhausted`) or the
+/// database itself (`query_failed`, `timeout`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotReadyReason {
+    /// No pooled connection could be acquired
+    PoolExhausted,
+    /// `SELECT 1` failed on an acquired connection
+    QueryFailed,
+    /// The check didn't finish within `HEALTH_CHECK_TIMEOUT_MS`
+    Timeout,
+}
+
+/// Run the blocking database `check` on the blocking pool, giving up after `timeout`.
+async fn check_database_within<F>(timeout: Duration, check: F) -> Result<(), NotReadyReason>
+where
+    F: FnOnce() -> Result<(), DbCheckError> + Send + 'static,
+{
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(timeout, tokio::task::spawn_blocking(check)).await;
+    super::server_timing::record_db(started.elapsed());
+
+    let error = match outcome {
+        Ok(Ok(Ok(()))) => return Ok(()),
+        Ok(Ok(Err(error))) => error,
+        Ok(Err(panicked)) => DbCheckError::Query(format!("database health check panicked: {panicked}")),
+        Err(_) => {
+            tracing::warn!(timeout_ms = timeout.as_millis() as u64, "Database readiness check timed out");
+            return Err(NotReadyReason::Timeout);
+        }
+    };
+    tracing::warn!(error = %error, "Database readiness check failed");
+    match error {
+        DbCheckError::Acquire(_) => Err(NotReadyReason::PoolExhausted),
+        DbCheckError::Query(_) => Err(NotReadyReason::QueryFailed),
+    }
+}
 
 #[derive(Debug, Serialize)]
 struct LiveResponse {
@@ -13,71 +103,142 @@ struct LiveResponse {
 }
 
 pub async fn live() -> impl IntoResponse {
-    (StatusCode::OK, Json(LiveResponse { status: "ok" }))
+    (StatusCode::OK, ApiJson(LiveResponse { status: "ok" }))
 }
 
 #[derive(Debug, Serialize)]
 struct ReadyResponse {
     status: &'static str,
     database: &'static str,
+    /// Set when `database` is `down`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<NotReadyReason>,
+}
+
+#[derive(Debug, Serialize)]
+struct DrainingResponse {
+    status: &'static str,
 }
 
-pub async fn ready(State(state): State<AppState>) -> impl IntoResponse {
-    match &state.db_pool {
+/// GET /health/ready
+///
+/// Fails (503) as soon as shutdown begins, before the database is even
+/// checked, so load balancers stop routing here while in-flight requests drain.
+///
+/// A failed database check is `database: "down"` with a `reason`:
+/// `pool_exhausted`, `query_failed` or `timeout`.
+///
+/// With `PROTECT_HEALTH_DETAILS` the database status is internal: callers
+/// without the `X-Health-Token` get 401. `/health/live` stays public.
+pub async fn ready(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(expected) = state.config.health_token.as_deref() {
+        let presented = headers.get(HEALTH_TOKEN_HEADER).and_then(|v| v.to_str().ok());
+        if !presented.is_some_and(|token| constant_time_eq(token, expected)) {
+            return ApiError::Unauthorized("Health token required".to_string()).into_response();
+        }
+    }
+
+    if state.draining.load(Ordering::Relaxed) {
+        return (StatusCode::SERVICE_UNAVAILABLE, ApiJson(DrainingResponse { status: "draining" })).into_response();
+    }
+
+    let response = match &state.db_pool {
         Some(pool) => {
             let pool = pool.clone();
-            match tokio::task::spawn_blocking(move || db::check_database(&pool)).await {
-                Ok(Ok(())) => (
-                    StatusCode::OK,
-                    Json(ReadyResponse {
-                        status: "ready",
-                        database: "ok",
-                    }),
-                ),
-                Ok(Err(_)) | Err(_) => (
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    Json(ReadyResponse {
-                        status: "not_ready",
-                        database: "down",
-                    }),
-                ),
-            }
+            let check = || check_database_within(state.config.health_check_timeout, move || db::check_database(&pool));
+            database_response(state.health_cache.get_or_check(check).await)
         }
         None if state.config.database_required => (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(ReadyResponse {
+            ApiJson(ReadyResponse {
                 status: "not_ready",
                 database: "missing",
+                reason: None,
             }),
         ),
         None => (
             StatusCode::OK,
-            Json(ReadyResponse {
+            ApiJson(ReadyResponse {
                 status: "ready",
                 database: "disabled",
+                reason: None,
             }),
         ),
+    };
+    response.into_response()
+}
+
+fn database_response(result: Result<(), NotReadyReason>) -> (StatusCode, ApiJson<ReadyResponse>) {
+    match result {
+        Ok(()) => (
+            StatusCode::OK,
+            ApiJson(ReadyResponse {
+                status: "ready",
+                database: "ok",
+                reason: None,
+            }),
+        ),
+        Err(reason) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiJson(ReadyResponse {
+                status: "not_ready",
+                database: "down",
+                reason: Some(reason),
+            }),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MigrationsResponse {
+    status: &'static str,
+    #[serde(flatten)]
+    migrations: MigrationStatus,
+}
+
+/// GET /health/migrations (admin only)
+///
+/// Reports applied vs pending migrations so operators can confirm the schema
+/// matches this binary. Pending migrations are a 503 unless `RUN_MIGRATIONS`
+/// is enabled (then they are expected to be applied on the next start).
+pub async fn migrations(State(state): State<AppState>, user: AuthUser) -> Result<Response, ApiError> {
+    if !user.is_admin() {
+        return Err(ApiError::Forbidden("Admin role required".to_string()));
     }
+
+    let pool = state
+        .db_pool
+        .clone()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Database not configured".to_string()))?;
+
+    let status = tokio::task::spawn_blocking(move || db::migration_status(&pool))
+        .await
+        .map_err(|e| ApiError::internal("Migration status check failed", e.to_string()))?
+        .map_err(|e| ApiError::internal("Migration status check failed", e))?;
+
+    Ok(migrations_response(status, state.config.run_migrations))
+}
+
+fn migrations_response(migrations: MigrationStatus, run_migrations: bool) -> Response {
+    let (code, status) = match (migrations.pending.is_empty(), run_migrations) {
+        (true, _) => (StatusCode::OK, "up_to_date"),
+        (false, true) => (StatusCode::OK, "pending"),
+        (false, false) => (StatusCode::SERVICE_UNAVAILABLE, "pending"),
+    };
+    (code, ApiJson(MigrationsResponse { status, migrations })).into_response()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{bearer_request, TestApp};
     use axum::{body::Body, http::Request, Router};
     use axum::routing::get;
     use tower::ServiceExt;
 
     fn create_test_app() -> Router {
-        let config = crate::config::AppConfig {
-            host: std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST),
-            port: 8000,
-            database_url: None,
-            database_required: false,
-        };
-        let state = crate::AppState {
-            config,
-            db_pool: None,
-        };
+        let config = crate::config::AppConfig::default();
+        let state = crate::AppState::new(config, None);
         Router::new()
             .route("/health/live", get(live))
             .route("/health/ready", get(ready))
@@ -115,18 +276,105 @@ mod tests {
         assert_eq!(json["database"], "disabled");
     }
 
+    #[tokio::test]
+    async fn test_health_ready_returns_503_while_draining() {
+        let state = crate::AppState::new(crate::config::AppConfig::default(), None);
+        let app = Router::new()
+            .route("/health/ready", get(ready))
+            .route("/health/live", get(live))
+            .with_state(state.clone());
+
+        state.draining.store(true, Ordering::Relaxed);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/health/ready").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["status"], "draining");
+
+        // Still alive: the orchestrator must not kill the process early
+        let response = app
+            .oneshot(Request::builder().uri("/health/live").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_health_cache_reuses_result_within_ttl() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = HealthCache::new(Duration::from_secs(60));
+        let checks = AtomicUsize::new(0);
+        let check = || async {
+            checks.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+
+        assert!(cache.get_or_check(check).await.is_ok());
+        assert!(cache.get_or_check(check).await.is_ok());
+        assert_eq!(checks.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_health_cache_rechecks_after_ttl() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = HealthCache::new(Duration::ZERO);
+        let checks = AtomicUsize::new(0);
+        let check = || async {
+            checks.fetch_add(1, Ordering::SeqCst);
+            Err(NotReadyReason::QueryFailed)
+        };
+
+        assert!(cache.get_or_check(check).await.is_err());
+        assert!(cache.get_or_check(check).await.is_err());
+        assert_eq!(checks.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_database_failures_map_to_not_ready_reasons() {
+        let timeout = Duration::from_millis(50);
+
+        let result = check_database_within(timeout, || Err(DbCheckError::Acquire("timed out waiting for connection".into())));
+        assert_eq!(result.await, Err(NotReadyReason::PoolExhausted));
+
+        let result = check_database_within(timeout, || Err(DbCheckError::Query("relation does not exist".into())));
+        assert_eq!(result.await, Err(NotReadyReason::QueryFailed));
+
+        let result = check_database_within(timeout, || {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(())
+        });
+        assert_eq!(result.await, Err(NotReadyReason::Timeout));
+
+        assert_eq!(check_database_within(timeout, || Ok(())).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_reason_is_reported_in_json() {
+        let (status, ApiJson(body)) = database_response(Err(NotReadyReason::PoolExhausted));
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let json = serde_json::to_value(body).unwrap();
+        assert_eq!(json["database"], "down");
+        assert_eq!(json["reason"], "pool_exhausted");
+
+        let (status, ApiJson(body)) = database_response(Ok(()));
+        assert_eq!(status, StatusCode::OK);
+        assert!(serde_json::to_value(body).unwrap().get("reason").is_none());
+    }
+
     #[tokio::test]
     async fn test_health_ready_with_required_db_missing_returns_503() {
         let config = crate::config::AppConfig {
-            host: std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST),
-            port: 8000,
-            database_url: None,
             database_required: true,
+            ..Default::default()
         };
-        let state = crate::AppState {
-            config,
-            db_pool: None,
-        };
+        let state = crate::AppState::new(config, None);
         let app = Router::new()
             .route("/health/ready", get(ready))
             .with_state(state);
@@ -143,4 +391,59 @@ mod tests {
         assert_eq!(json["status"], "not_ready");
         assert_eq!(json["database"], "missing");
     }
+
+    #[tokio::test]
+    async fn test_protected_ready_requires_health_token() {
+        let config = crate::config::AppConfig {
+            health_token: Some("internal".to_string()),
+            ..Default::default()
+        };
+        let app = TestApp::builder().config(config).build().await;
+        let ready_with = |token: Option<&str>| {
+            let mut request = Request::builder().uri("/health/ready");
+            if let Some(token) = token {
+                request = request.header(HEALTH_TOKEN_HEADER, token);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        assert_eq!(app.send(ready_with(None)).await.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(app.send(ready_with(Some("wrong"))).await.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(app.send(ready_with(Some("internal"))).await.status, StatusCode::OK);
+
+        // Liveness stays public for the load balancer
+        assert_eq!(app.get("/health/live").await.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_pending_migrations_are_reported_and_503() {
+        let pending = MigrationStatus {
+            applied: Vec::new(),
+            pending: vec!["2024_01_01_000001_create_users".to_string()],
+        };
+
+        let response = migrations_response(pending.clone(), false);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["status"], "pending");
+        assert_eq!(json["pending"], serde_json::json!(["2024_01_01_000001_create_users"]));
+
+        // Expected to be applied on startup when RUN_MIGRATIONS is on
+        assert_eq!(migrations_response(pending, true).status(), StatusCode::OK);
+        assert_eq!(
+            migrations_response(MigrationStatus::default(), false).status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrations_endpoint_is_admin_only() {
+        let app = TestApp::builder().build().await;
+        let token = crate::api::jwt::generate_access_token(app.clock.as_ref(), 7, "user@example.com", &[]).unwrap();
+
+        let request = bearer_request("GET", "/health/migrations", &token, serde_json::Value::Null);
+        assert_eq!(app.send(request).await.status, StatusCode::FORBIDDEN);
+        assert_eq!(app.get("/health/migrations").await.status, StatusCode::UNAUTHORIZED);
+    }
 }
@@ -0,0 +1,113 @@
+// ==============================================================================
+// HTTPS REDIRECT
+// ==============================================================================
+//
+// With `FORCE_HTTPS=true`, plain HTTP requests get `308 Permanent Redirect`
+// to the same URL over https. 308 (unlike 301) preserves the method and
+// body, so a redirected POST stays a POST.
+//
+// A request counts as HTTPS when:
+// - the first `X-Forwarded-Proto` value is `https` (TLS terminated by a proxy), or
+// - the request URI itself carries the `https` scheme
+//
+// Health checks (`/health/*`) are never redirected: load balancers and
+// orchestrators probe them over plain HTTP.
+//
+// ==============================================================================
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::ApiError;
+use crate::AppState;
+
+/// Redirect plain HTTP to HTTPS when `config.force_https` is set.
+pub async fn force_https_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if !state.config.force_https
+        || request.uri().path().starts_with("/health/")
+        || is_https(request.uri(), request.headers())
+    {
+        return next.run(request).await;
+    }
+
+    match https_location(request.uri(), request.headers()) {
+        Some(location) => (StatusCode::PERMANENT_REDIRECT, [(header::LOCATION, location)]).into_response(),
+        None => ApiError::BadRequest("HTTPS required".to_string()).into_response(),
+    }
+}
+
+fn is_https(uri: &Uri, headers: &HeaderMap) -> bool {
+    let forwarded = headers
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|proto| proto.trim().eq_ignore_ascii_case("https"));
+
+    forwarded.unwrap_or(false) || uri.scheme_str() == Some("https")
+}
+
+/// `https://<host><path?query>` for the request, if the host is known.
+fn https_location(uri: &Uri, headers: &HeaderMap) -> Option<HeaderValue> {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| uri.authority().map(|a| a.as_str()))?;
+    let path = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+
+    HeaderValue::from_str(&format!("https://{host}{path}")).ok()
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    use crate::test_support::{TestApp, TestResponse};
+
+    async fn test_app(force_https: bool) -> TestApp {
+        let config = crate::config::AppConfig {
+            force_https,
+            ..Default::default()
+        };
+        TestApp::builder().config(config).build().await
+    }
+
+    async fn send(app: TestApp, uri: &str, forwarded_proto: Option<&str>) -> TestResponse {
+        let mut request = Request::builder().uri(uri).header(header::HOST, "api.example.com");
+        if let Some(proto) = forwarded_proto {
+            request = request.header("x-forwarded-proto", proto);
+        }
+        app.send(request.body(Body::empty()).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn test_http_request_redirects_to_https() {
+        let response = send(test_app(true).await, "/api/v1/version?x=1", Some("http")).await;
+
+        assert_eq!(response.status, StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers[header::LOCATION],
+            "https://api.example.com/api/v1/version?x=1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_https_passes_through() {
+        let response = send(test_app(true).await, "/api/v1/version", Some("https")).await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_health_and_disabled_mode_not_redirected() {
+        assert_eq!(send(test_app(true).await, "/health/live", None).await.status, StatusCode::OK);
+        assert_eq!(send(test_app(false).await, "/api/v1/version", None).await.status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,207 @@
+// ==============================================================================
+// TOKEN INTROSPECTION (RFC 7662 STYLE)
+// ==============================================================================
+//
+// POST /api/v1/auth/introspect
+//
+// Lets trusted internal services ask "is this token still good, and whose is
+// it?" without holding the JWT secret. Callers authenticate with a shared
+// secret in `X-Introspection-Secret`, set via `INTROSPECTION_SECRET` (or
+// `INTROSPECTION_SECRET_FILE`).
+//
+// SECURITY:
+// - Disabled (404) unless a secret is configured
+// - The secret is compared in constant time (see `security`)
+// - Any invalid, expired or revoked token is just `{"active": false}`;
+//   the reason is never disclosed
+//
+// ==============================================================================
+
+use axum::extract::State;
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+use super::json::ApiJson;
+use super::jwt::{validate_token_checked, Claims};
+use super::security::constant_time_eq;
+use super::ApiError;
+
+/// Header carrying the shared introspection secret.
+pub const INTROSPECTION_SECRET_HEADER: &str = "x-introspection-secret";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IntrospectRequest {
+    pub token: String,
+}
+
+/// Introspection result; everything but `active` is omitted for inactive tokens.
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+impl From<Claims> for IntrospectResponse {
+    fn from(claims: Claims) -> Self {
+        Self {
+            active: true,
+            token_type: Some(claims.token_type.as_str()),
+            sub: Some(claims.sub),
+            email: Some(claims.email),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            jti: Some(claims.jti),
+            roles: claims.roles,
+        }
+    }
+}
+
+/// True if the request carries the expected shared secret.
+pub fn secret_matches(headers: &HeaderMap, expected: &str) -> bool {
+    headers
+        .get(INTROSPECTION_SECRET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|provided| constant_time_eq(provided, expected))
+}
+
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<IntrospectRequest>,
+) -> Result<ApiJson<IntrospectResponse>, ApiError> {
+    let Some(expected) = state.config.introspection_secret.as_deref() else {
+        return Err(ApiError::NotFound("Not found".to_string()));
+    };
+
+    if !secret_matches(&headers, expected) {
+        tracing::warn!("Token introspection rejected: bad shared secret");
+        return Err(ApiError::Unauthorized("Invalid introspection secret".to_string()));
+    }
+
+    let claims = validate_token_checked(&request.token, state.clock.as_ref(), &state.revocations).await;
+    Ok(ApiJson(match claims {
+        Ok(claims) => claims.into(),
+        // Can't tell whether the token is revoked: fail, don't vouch for it
+        Err(err @ ApiError::ServiceUnavailable(_)) => return Err(err),
+        Err(_) => IntrospectResponse::default(),
+    }))
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::api::jwt::generate_access_token;
+    use crate::config::AppConfig;
+    use axum::body::Body;
+    use axum::http::{header, HeaderValue, Request, StatusCode};
+    use tower::ServiceExt;
+
+    const SECRET: &str = "introspection-test-secret";
+
+    async fn post(secret: Option<&str>, configured: bool, token: &str) -> (StatusCode, serde_json::Value) {
+        let config = AppConfig {
+            introspection_secret: configured.then(|| SECRET.to_string()),
+            ..AppConfig::default()
+        };
+        post_to(AppState::new(config, None), secret, token).await
+    }
+
+    async fn post_to(state: AppState, secret: Option<&str>, token: &str) -> (StatusCode, serde_json::Value) {
+        let app = axum::Router::new()
+            .route("/auth/introspect", axum::routing::post(introspect))
+            .with_state(state);
+
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/auth/introspect")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = secret {
+            request = request.header(INTROSPECTION_SECRET_HEADER, secret);
+        }
+        let body = serde_json::json!({ "token": token }).to_string();
+        let response = app.oneshot(request.body(Body::from(body)).unwrap()).await.unwrap();
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[test]
+    fn test_secret_check() {
+        let mut headers = HeaderMap::new();
+        assert!(!secret_matches(&headers, SECRET));
+
+        headers.insert(INTROSPECTION_SECRET_HEADER, HeaderValue::from_static(SECRET));
+        assert!(secret_matches(&headers, SECRET));
+
+        // Same length, last byte differs
+        headers.insert(
+            INTROSPECTION_SECRET_HEADER,
+            HeaderValue::from_static("introspection-test-secreT"),
+        );
+        assert!(!secret_matches(&headers, SECRET));
+    }
+
+    #[tokio::test]
+    async fn test_introspect_requires_secret() {
+        let (status, _) = post(None, true, "x").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = post(Some("wrong"), true, "x").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = post(Some(SECRET), false, "x").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_introspect_reports_active_and_inactive_tokens() {
+        let token = generate_access_token(&SystemClock, 42, "ops@example.com", &["admin".to_string()]).unwrap();
+        let (status, json) = post(Some(SECRET), true, &token).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["active"], true);
+        assert_eq!(json["sub"], "42");
+        assert_eq!(json["token_type"], "access");
+        assert_eq!(json["roles"], serde_json::json!(["admin"]));
+
+        let (status, json) = post(Some(SECRET), true, "not-a-jwt").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json, serde_json::json!({ "active": false }));
+    }
+
+    #[tokio::test]
+    async fn test_introspect_reports_revoked_tokens_inactive() {
+        let config = AppConfig {
+            introspection_secret: Some(SECRET.to_string()),
+            ..AppConfig::default()
+        };
+        let state = AppState::new(config, None);
+        let token = generate_access_token(&SystemClock, 42, "ops@example.com", &[]).unwrap();
+        let claims = validate_token_checked(&token, &SystemClock, &state.revocations).await.unwrap();
+        crate::api::jwt::revoke_token(&state.revocations, &claims, claims.iat).await.unwrap();
+
+        let (status, json) = post_to(state, Some(SECRET), &token).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json, serde_json::json!({ "active": false }));
+    }
+}
@@ -0,0 +1,177 @@
+// ==============================================================================
+// REFRESH TOKEN IP PINNING
+// ==============================================================================
+//
+// Token theft often shows up as a refresh token suddenly being used from a
+// new IP address. This module records the client IPs seen per refresh
+// session and flags the session when too many distinct IPs show up within
+// a sliding window.
+//
+// MODES (`REFRESH_IP_PINNING`):
+// - off    (default) no tracking at all
+// - warn   flagged refreshes succeed but emit an audit log entry
+// - strict flagged refreshes are rejected; the user must log in again
+//
+// NOTES:
+// - The session key is the refresh token's `jti`, which is stable for the
+//   lifetime of a login.
+// - The client IP is the TCP peer address (same source as the governor
+//   rate limiters). `X-Forwarded-For` is NOT trusted here.
+// - Mobile clients legitimately hop between Wi-Fi and cellular; `strict`
+//   is best suited to web-only deployments.
+//
+// ==============================================================================
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Sliding window in which distinct IPs are counted.
+const IP_CHANGE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Distinct IPs a session may use within `IP_CHANGE_WINDOW` before it is flagged.
+const MAX_DISTINCT_IPS_PER_WINDOW: usize = 1;
+
+/// What to do when a refresh session changes IP beyond the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpPinningMode {
+    /// No tracking (default).
+    Off,
+    /// Allow, but write an audit log entry.
+    Warn,
+    /// Reject the refresh and require re-authentication.
+    Strict,
+}
+
+impl IpPinningMode {
+    /// Read `REFRESH_IP_PINNING` (`strict` | `warn` | `off`). Defaults to off.
+    pub fn from_env() -> Self {
+        match env::var("REFRESH_IP_PINNING").map(|v| v.to_lowercase()) {
+            Ok(v) if v == "strict" => IpPinningMode::Strict,
+            Ok(v) if v == "warn" => IpPinningMode::Warn,
+            _ => IpPinningMode::Off,
+        }
+    }
+}
+
+/// Outcome of checking a refresh against the session's IP history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinningDecision {
+    Allow,
+    Warn,
+    Reject,
+}
+
+/// Per-session record of recently seen client IPs.
+#[derive(Debug)]
+pub struct RefreshIpTracker {
+    mode: IpPinningMode,
+    sessions: Mutex<HashMap<String, Vec<(IpAddr, Instant)>>>,
+}
+
+impl RefreshIpTracker {
+    pub fn new(mode: IpPinningMode) -> Self {
+        Self {
+            mode,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a refresh for `session` from `ip` and decide whether to allow it.
+    ///
+    /// In strict mode a rejected IP is not recorded, so the session stays
+    /// pinned to the addresses it was legitimately used from.
+    pub fn check(&self, session: &str, ip: IpAddr) -> PinningDecision {
+        if self.mode == IpPinningMode::Off {
+            return PinningDecision::Allow;
+        }
+
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Opportunistic cleanup keeps the map bounded by active sessions
+        sessions.retain(|_, seen| {
+            seen.retain(|(_, at)| now.duration_since(*at) < IP_CHANGE_WINDOW);
+            !seen.is_empty()
+        });
+
+        let seen = sessions.entry(session.to_string()).or_default();
+        if let Some(entry) = seen.iter_mut().find(|(known, _)| *known == ip) {
+            entry.1 = now;
+            return PinningDecision::Allow;
+        }
+
+        if seen.len() < MAX_DISTINCT_IPS_PER_WINDOW {
+            seen.push((ip, now));
+            return PinningDecision::Allow;
+        }
+
+        match self.mode {
+            IpPinningMode::Strict => PinningDecision::Reject,
+            _ => {
+                seen.push((ip, now));
+                PinningDecision::Warn
+            }
+        }
+    }
+}
+
+impl Default for RefreshIpTracker {
+    fn default() -> Self {
+        Self::new(IpPinningMode::Off)
+    }
+}
+
+/// The TCP peer IP, when the server was started with connect info.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip()),
+        ))
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_off_mode_never_flags() {
+        let tracker = RefreshIpTracker::new(IpPinningMode::Off);
+        assert_eq!(tracker.check("s", ip("10.0.0.1")), PinningDecision::Allow);
+        assert_eq!(tracker.check("s", ip("10.0.0.2")), PinningDecision::Allow);
+    }
+
+    #[test]
+    fn test_warn_mode_flags_but_allows_new_ip() {
+        let tracker = RefreshIpTracker::new(IpPinningMode::Warn);
+        assert_eq!(tracker.check("s", ip("10.0.0.1")), PinningDecision::Allow);
+        assert_eq!(tracker.check("s", ip("10.0.0.2")), PinningDecision::Warn);
+        // Other sessions are tracked independently
+        assert_eq!(tracker.check("t", ip("10.0.0.2")), PinningDecision::Allow);
+    }
+}
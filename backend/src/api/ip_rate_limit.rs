@@ -0,0 +1,240 @@
+// ==============================================================================
+// GENERAL (PER-IP) RATE LIMITING
+// ==============================================================================
+//
+// Token bucket per client IP applied to every request. Unlike the
+// tower_governor layer it replaces, the bucket state is surfaced to clients:
+//
+// - Every response carries `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+//   `X-RateLimit-Reset` (seconds until the bucket is full again)
+// - `GET /api/v1/ratelimit` returns the same numbers as JSON
+// - Rejected requests get `429` with `Retry-After`
+//
+// NOTES:
+// - The client IP is the TCP peer address; `X-Forwarded-For` is NOT trusted
+// - The quota is built exactly like the previous `GovernorConfigBuilder`
+//   (`per_second(n)` = one element replenished every `n` seconds)
+// - The stricter per-IP auth governor in `app.rs` is unchanged
+//
+// ==============================================================================
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use governor::clock::{Clock, DefaultClock};
+use governor::middleware::StateInformationMiddleware;
+use governor::{Quota, RateLimiter};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use super::json::ApiJson;
+use crate::AppState;
+
+/// Number of checks between sweeps of idle buckets (bounds memory).
+const SWEEP_INTERVAL: u64 = 1024;
+
+const X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+const X_RATELIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Headers browsers may read cross-origin (CORS `Access-Control-Expose-Headers`).
+pub const EXPOSED_HEADERS: [HeaderName; 3] = [X_RATELIMIT_LIMIT, X_RATELIMIT_REMAINING, X_RATELIMIT_RESET];
+
+type KeyedLimiter = RateLimiter<
+    IpAddr,
+    governor::state::keyed::DefaultKeyedStateStore<IpAddr>,
+    DefaultClock,
+    StateInformationMiddleware,
+>;
+
+/// The caller's standing in the general bucket after the current request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimitStatus {
+    /// Burst size: requests allowed back to back from a full bucket.
+    pub limit: u32,
+    /// Requests left before the caller is throttled.
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset: u64,
+}
+
+impl RateLimitStatus {
+    fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert(X_RATELIMIT_LIMIT, HeaderValue::from(self.limit));
+        headers.insert(X_RATELIMIT_REMAINING, HeaderValue::from(self.remaining));
+        headers.insert(X_RATELIMIT_RESET, HeaderValue::from(self.reset));
+    }
+}
+
+/// Token bucket keyed on the client IP.
+#[derive(Debug)]
+pub struct IpRateLimiter {
+    limiter: KeyedLimiter,
+    checks: AtomicU64,
+}
+
+impl IpRateLimiter {
+    /// One request replenished every `period`, bursts of up to `burst`.
+    pub fn new(period: Duration, burst: u32) -> Self {
+        let burst = NonZeroU32::new(burst).unwrap_or(NonZeroU32::MIN);
+        let quota = Quota::with_period(period)
+            .unwrap_or_else(|| Quota::per_second(NonZeroU32::MIN))
+            .allow_burst(burst);
+        Self {
+            limiter: RateLimiter::keyed(quota).with_middleware::<StateInformationMiddleware>(),
+            checks: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a request from `ip`.
+    ///
+    /// Returns the caller's updated status, or `Err((status, retry_after))`
+    /// when the bucket is exhausted.
+    pub fn check(&self, ip: IpAddr) -> Result<RateLimitStatus, (RateLimitStatus, Duration)> {
+        if self.checks.fetch_add(1, Ordering::Relaxed) % SWEEP_INTERVAL == SWEEP_INTERVAL - 1 {
+            self.limiter.retain_recent();
+        }
+
+        match self.limiter.check_key(&ip) {
+            Ok(snapshot) => {
+                let quota = snapshot.quota();
+                let limit = quota.burst_size().get();
+                let remaining = snapshot.remaining_burst_capacity();
+                let reset = quota.replenish_interval() * (limit - remaining);
+                Ok(RateLimitStatus {
+                    limit,
+                    remaining,
+                    reset: reset.as_secs(),
+                })
+            }
+            Err(not_until) => {
+                let limit = not_until.quota().burst_size().get();
+                let retry_after = not_until.wait_time_from(DefaultClock::default().now());
+                let reset = retry_after + not_until.quota().replenish_interval() * (limit - 1);
+                let status = RateLimitStatus {
+                    limit,
+                    remaining: 0,
+                    reset: reset.as_secs(),
+                };
+                Err((status, retry_after))
+            }
+        }
+    }
+}
+
+impl Default for IpRateLimiter {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_secs(crate::config::GENERAL_RATE_LIMIT_PER_SECOND),
+            crate::config::GENERAL_RATE_LIMIT_BURST,
+        )
+    }
+}
+
+/// Enforce the general per-IP limit and add `X-RateLimit-*` headers.
+pub async fn rate_limit_middleware(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let ip = peer.ip();
+    match state.general_limiter.check(ip) {
+        Ok(status) => {
+            request.extensions_mut().insert(status);
+            let mut response = next.run(request).await;
+            status.apply(response.headers_mut());
+            response
+        }
+        Err((status, retry_after)) => {
+            tracing::info!("General rate limit exceeded for {ip}");
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+                ApiJson(serde_json::json!({ "error": "Too many requests" })),
+            )
+                .into_response();
+            status.apply(response.headers_mut());
+            response
+        }
+    }
+}
+
+/// `GET /api/v1/ratelimit`: the caller's general quota after this request.
+pub async fn rate_limit_status(Extension(status): Extension<RateLimitStatus>) -> ApiJson<RateLimitStatus> {
+    ApiJson(status)
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_support::{TestApp, TestResponse};
+
+    async fn test_app(burst: u32) -> TestApp {
+        TestApp::builder()
+            .configure(move |state| {
+                state.general_limiter = std::sync::Arc::new(IpRateLimiter::new(Duration::from_secs(60), burst));
+            })
+            .build()
+            .await
+    }
+
+    fn header_u64(response: &TestResponse, name: &HeaderName) -> u64 {
+        response.headers[name].to_str().unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_remaining_header_decrements_across_requests() {
+        let app = test_app(3).await;
+
+        let first = app.get("/health/live").await;
+        let second = app.get("/health/live").await;
+        assert_eq!(header_u64(&first, &X_RATELIMIT_LIMIT), 3);
+        assert_eq!(header_u64(&first, &X_RATELIMIT_REMAINING), 2);
+        assert_eq!(header_u64(&second, &X_RATELIMIT_REMAINING), 1);
+        assert!(header_u64(&second, &X_RATELIMIT_RESET) > header_u64(&first, &X_RATELIMIT_RESET));
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_bucket_returns_429() {
+        let app = test_app(1).await;
+        assert_eq!(app.get("/health/live").await.status, StatusCode::OK);
+
+        let throttled = app.get("/health/live").await;
+        assert_eq!(throttled.status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(throttled.headers.contains_key(header::RETRY_AFTER));
+        assert_eq!(header_u64(&throttled, &X_RATELIMIT_REMAINING), 0);
+    }
+
+    #[tokio::test]
+    async fn test_status_endpoint_reports_quota() {
+        let app = test_app(5).await;
+        app.get("/health/live").await;
+
+        let response = app.get("/api/v1/ratelimit").await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.json["limit"], 5);
+        assert_eq!(response.json["remaining"], 3);
+        assert_eq!(response.json["reset"], 120);
+    }
+
+    #[test]
+    fn test_buckets_are_per_ip() {
+        let limiter = IpRateLimiter::new(Duration::from_secs(60), 1);
+        let a: IpAddr = "198.51.100.1".parse().unwrap();
+        let b: IpAddr = "198.51.100.2".parse().unwrap();
+        assert!(limiter.check(a).is_ok());
+        assert!(limiter.check(a).is_err());
+        assert!(limiter.check(b).is_ok());
+    }
+}
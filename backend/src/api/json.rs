@@ -0,0 +1,230 @@
+// ==============================================================================
+// JSON EXTRACTOR WITH API ERROR REJECTIONS (AND JSON RESPONSES)
+// ==============================================================================
+//
+// Axum's `Json<T>` rejects bad bodies with plain-text 400/415/422 responses.
+// `ApiJson<T>` wraps it so every body error becomes a `400` in the standard
+// `ApiError` JSON envelope, which clients can handle uniformly.
+//
+// Pair with `#[serde(deny_unknown_fields)]` on request types to turn typos
+// like `passwrod` into a clear 400 instead of a silently ignored key.
+//
+// Bodies must be exactly one JSON value: trailing non-whitespace after it
+// (`{"email":"a"}garbage`) is a 400, not silently ignored. axum's `Json`
+// enforces this via `Deserializer::end`; the tests below pin it in case
+// the extractor is ever swapped for a streaming parser.
+//
+// `bounded_string` caps string fields during deserialization, so an
+// oversized value is rejected before it is copied into a `String`.
+//
+// As a response, `ApiJson<T>` is what handlers return instead of axum's
+// `Json<T>`: with `PRETTY_JSON=true` (ignored in production) bodies are
+// indented for reading in a browser or curl; otherwise they stay compact.
+//
+// ==============================================================================
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, OptionalFromRequest, Request};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::Serialize;
+use std::fmt;
+use std::sync::OnceLock;
+
+use super::ApiError;
+
+static PRETTY_JSON: OnceLock<bool> = OnceLock::new();
+
+/// JSON body extractor that rejects with `ApiError::BadRequest`, and JSON
+/// response that honours `PRETTY_JSON`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match <Json<T> as FromRequest<S>>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => Err(bad_request(rejection)),
+        }
+    }
+}
+
+/// `Option<ApiJson<T>>` is `None` when the request has no `Content-Type`
+/// (i.e. no body was sent), and still rejects malformed JSON bodies.
+impl<T, S> OptionalFromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        if !req.headers().contains_key(header::CONTENT_TYPE) {
+            return Ok(None);
+        }
+        <Self as FromRequest<S>>::from_request(req, state).await.map(Some)
+    }
+}
+
+fn bad_request(rejection: JsonRejection) -> ApiError {
+    // Serde messages only name fields/types from our own request structs,
+    // never echo the submitted values, so they are safe to return.
+    ApiError::BadRequest(rejection.body_text())
+}
+
+/// Deserialize a string of at most `max` bytes, failing before allocating
+/// anything for longer input. Use from a `deserialize_with` helper.
+pub fn bounded_string<'de, D>(deserializer: D, max: usize) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct BoundedVisitor(usize);
+
+    impl Visitor<'_> for BoundedVisitor {
+        type Value = String;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a string of at most {} bytes", self.0)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<String, E> {
+            if value.len() > self.0 {
+                return Err(E::invalid_length(value.len(), &self));
+            }
+            Ok(value.to_string())
+        }
+    }
+
+    deserializer.deserialize_str(BoundedVisitor(max))
+}
+
+/// Whether responses are pretty-printed: `PRETTY_JSON=true`, never in production.
+pub fn resolve_pretty_json(requested: Option<&str>, production: bool) -> bool {
+    !production && requested.and_then(crate::config::parse_bool).unwrap_or(false)
+}
+
+/// Install the resolved setting (`AppConfig::pretty_json`) once at startup.
+pub fn init_pretty_json(pretty: bool) {
+    let _ = PRETTY_JSON.set(pretty);
+}
+
+/// Set by `init_pretty_json`; compact until then (tests).
+fn pretty_json() -> bool {
+    PRETTY_JSON.get().copied().unwrap_or(false)
+}
+
+/// Serialize `value` as a JSON response, indented when `pretty`.
+fn json_response<T: Serialize>(value: &T, pretty: bool) -> Response {
+    let body = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+
+    match body {
+        Ok(body) => (
+            [(header::CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+            body,
+        )
+            .into_response(),
+        // Same fallback as axum's `Json`
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [(header::CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"))],
+            err.to_string(),
+        )
+            .into_response(),
+    }
+}
+
+impl<T: Serialize> IntoResponse for ApiJson<T> {
+    fn into_response(self) -> Response {
+        json_response(&self.0, pretty_json())
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_of(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pretty_output_in_development() {
+        let pretty = resolve_pretty_json(Some("true"), false);
+        let response = json_response(&serde_json::json!({ "status": "ok" }), pretty);
+
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(body_of(response).await, "{\n  \"status\": \"ok\"\n}");
+    }
+
+    #[tokio::test]
+    async fn test_compact_output_in_production() {
+        let pretty = resolve_pretty_json(Some("true"), true);
+        assert!(!pretty);
+
+        let response = json_response(&serde_json::json!({ "status": "ok" }), pretty);
+        assert_eq!(body_of(response).await, r#"{"status":"ok"}"#);
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Credentials {
+        email: String,
+        password: String,
+    }
+
+    async fn post_login(body: &'static str) -> Response {
+        use tower::ServiceExt;
+
+        let app = axum::Router::new().route(
+            "/login",
+            axum::routing::post(|ApiJson(_): ApiJson<Credentials>| async { StatusCode::OK }),
+        );
+        app.oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_trailing_garbage_after_json_is_rejected() {
+        let response = post_login(r#"{"email":"a","password":"b"}garbage"#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_str(&body_of(response).await).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("trailing characters"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_trailing_whitespace_after_json_is_allowed() {
+        let response = post_login("{\"email\":\"a\",\"password\":\"b\"}\n  ").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_pretty_json_off_by_default() {
+        assert!(!resolve_pretty_json(None, false));
+        assert!(!resolve_pretty_json(Some("false"), false));
+    }
+}
@@ -0,0 +1,223 @@
+// ==============================================================================
+// JWKS (JSON WEB KEY SET)
+// ==============================================================================
+//
+// GET /.well-known/jwks.json
+//
+// When tokens are RS256 JWTs (`JWT_ALGORITHM=RS256`), publishes the RSA
+// public key from `JWT_PUBLIC_KEY_PATH` as a JWK so other services can verify
+// our tokens without sharing key files. The set comes from the same resolved
+// `jwt` keys that sign tokens, so the published `kid` always matches.
+//
+// Returns 404 when there is nothing to verify with: under HS256 (the secret
+// is symmetric), with `TOKEN_FORMAT=paseto` (tokens aren't JWTs), or when the
+// keys failed to load.
+//
+// SECURITY:
+// - Only the public key (`n`, `e`) is ever exposed
+// - The key id (`kid`) is `JWT_KEY_ID`, or derived from the key itself so it
+//   changes whenever the key is rotated
+//
+// ==============================================================================
+
+use super::json::ApiJson;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, Jwk, JwkSet, KeyAlgorithm, PublicKeyUse, RSAKeyParameters,
+    RSAKeyType,
+};
+
+use super::ApiError;
+
+/// DER object identifier of `rsaEncryption` (1.2.840.113549.1.1.1).
+const RSA_ENCRYPTION_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+/// `GET /.well-known/jwks.json`
+pub async fn jwks() -> Result<ApiJson<JwkSet>, ApiError> {
+    jwks_response(super::jwt::published_jwks())
+}
+
+fn jwks_response(set: Option<&JwkSet>) -> Result<ApiJson<JwkSet>, ApiError> {
+    set.cloned()
+        .map(ApiJson)
+        .ok_or_else(|| ApiError::NotFound("Not found".to_string()))
+}
+
+/// Convert an RSA public key (`-----BEGIN PUBLIC KEY-----`, SPKI) to a JWK.
+pub fn rsa_public_jwk(pem: &str, key_id: Option<&str>) -> Result<Jwk, String> {
+    let der = pem_body(pem, "PUBLIC KEY")?;
+    let (modulus, exponent) = parse_spki_rsa(&der).ok_or("not an RSA SubjectPublicKeyInfo key")?;
+
+    let kid = key_id.map(str::to_string).unwrap_or_else(|| derive_key_id(&der));
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(KeyAlgorithm::RS256),
+            key_id: Some(kid),
+            ..Default::default()
+        },
+        algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: URL_SAFE_NO_PAD.encode(modulus),
+            e: URL_SAFE_NO_PAD.encode(exponent),
+        }),
+    })
+}
+
+/// Stable key id: first 16 hex chars of BLAKE2b-256 over the DER key.
+fn derive_key_id(der: &[u8]) -> String {
+    use blake2::digest::consts::U32;
+    use blake2::{Blake2b, Digest};
+
+    let digest = Blake2b::<U32>::digest(der);
+    hex::encode(&digest[..8])
+}
+
+/// Decode the base64 body of a PEM block with the given label.
+fn pem_body(pem: &str, label: &str) -> Result<Vec<u8>, String> {
+    let begin = format!("-----BEGIN {label}-----");
+    let end = format!("-----END {label}-----");
+
+    let start = pem.find(&begin).ok_or_else(|| format!("missing `{begin}`"))? + begin.len();
+    let stop = pem[start..].find(&end).ok_or_else(|| format!("missing `{end}`"))? + start;
+
+    let body: String = pem[start..stop].split_whitespace().collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(body)
+        .map_err(|e| format!("invalid PEM base64: {e}"))
+}
+
+// ==============================================================================
+// MINIMAL DER PARSING
+// ==============================================================================
+//
+// Just enough ASN.1 to read
+//
+//   SubjectPublicKeyInfo ::= SEQUENCE {
+//       algorithm SEQUENCE { OID rsaEncryption, NULL },
+//       subjectPublicKey BIT STRING { SEQUENCE { INTEGER n, INTEGER e } }
+//   }
+//
+// ==============================================================================
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// Split one DER element off `input`, returning `(content, rest)` if its tag matches.
+fn der_element(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&actual, input) = input.split_first()?;
+    if actual != tag {
+        return None;
+    }
+
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > std::mem::size_of::<usize>() || input.len() < count {
+            return None;
+        }
+        let (bytes, rest) = input.split_at(count);
+        input = rest;
+        bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize)
+    };
+
+    (input.len() >= len).then(|| input.split_at(len))
+}
+
+/// Big-endian unsigned integer bytes, without DER's sign-padding zero.
+fn unsigned(bytes: &[u8]) -> &[u8] {
+    match bytes {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => bytes,
+    }
+}
+
+/// Extract `(modulus, exponent)` from an RSA SubjectPublicKeyInfo.
+fn parse_spki_rsa(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (spki, _) = der_element(der, TAG_SEQUENCE)?;
+    let (algorithm, rest) = der_element(spki, TAG_SEQUENCE)?;
+    let (oid, _) = der_element(algorithm, TAG_OID)?;
+    if oid != RSA_ENCRYPTION_OID {
+        return None;
+    }
+
+    let (bits, _) = der_element(rest, TAG_BIT_STRING)?;
+    // First byte of a BIT STRING is the unused-bit count; must be 0 here
+    let (&0, key) = bits.split_first()? else {
+        return None;
+    };
+
+    let (rsa_key, _) = der_element(key, TAG_SEQUENCE)?;
+    let (modulus, rest) = der_element(rsa_key, TAG_INTEGER)?;
+    let (exponent, _) = der_element(rest, TAG_INTEGER)?;
+    Some((unsigned(modulus), unsigned(exponent)))
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    const TEST_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAn+UCnE7ujYgiV8MYo6z2
+RJlgwoh3yfEpFV4ymReBu6EUiIXIwCLcU4BOtf2n+e8+LYgy//nDWekLq5R7+aQK
+Dz2GMqwLfKsgcWSlqxMG84aJXmopcQnaNei/JsSgUAbTiExCNBLMYZydsuFFmmu4
+RxrnPBYUbuXUv2ThDlT6/yJ55/3FfC9tCZP8JMdij5nFiM1Glg0UXc1qhBnD3qSD
+Uh7tdIcELT4R+jFGSGsg7TcFMzExFeIRootD6ACw8gBetZl/jsx8Mg4J2nci4AA0
+I2xtnnuCx/itHIJvjNAKs6u/FDvLNimcj9sAxZmlPHDgkhHsq/SX8l4t9pNdcHUP
+FQIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    #[test]
+    fn test_rs256_jwks_exposes_public_key() {
+        let set = JwkSet {
+            keys: vec![rsa_public_jwk(TEST_PUBLIC_KEY, Some("key-1")).unwrap()],
+        };
+        let json = serde_json::to_value(&set).unwrap();
+        let key = &json["keys"][0];
+
+        assert_eq!(key["kid"], "key-1");
+        assert_eq!(key["kty"], "RSA");
+        assert_eq!(key["alg"], "RS256");
+        assert_eq!(key["e"], "AQAB");
+
+        let n = URL_SAFE_NO_PAD.decode(key["n"].as_str().unwrap()).unwrap();
+        assert_eq!(n.len(), 256);
+        assert_eq!(&n[..4], &[0x9f, 0xe5, 0x02, 0x9c]);
+        assert!(key.get("d").is_none());
+
+        // Other services can build a verification key straight from it
+        assert!(jsonwebtoken::DecodingKey::from_jwk(&set.keys[0]).is_ok());
+    }
+
+    #[test]
+    fn test_derived_kid_is_stable() {
+        let a = rsa_public_jwk(TEST_PUBLIC_KEY, None).unwrap();
+        let b = rsa_public_jwk(TEST_PUBLIC_KEY, None).unwrap();
+        assert_eq!(a.common.key_id, b.common.key_id);
+        assert_eq!(a.common.key_id.unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_no_key_set_is_not_found() {
+        let response = jwks_response(None).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_invalid_public_key_is_an_error() {
+        assert!(rsa_public_jwk("-----BEGIN PUBLIC KEY-----\nAAAA\n-----END PUBLIC KEY-----", None).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+// ==============================================================================
+// LOCALE NEGOTIATION
+// ==============================================================================
+//
+// Central place to decide which language a response should be rendered in.
+//
+// USAGE:
+// ```rust
+// async fn handler(AcceptLanguage(locale): AcceptLanguage) -> impl IntoResponse {
+//     // locale is one of SUPPORTED_LOCALES, e.g. "es"
+// }
+// ```
+//
+// The negotiated locale is also stored in the request extensions so code that
+// runs later (e.g. an error renderer) can read it without re-parsing.
+//
+// ==============================================================================
+
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderMap};
+use std::convert::Infallible;
+
+/// Locales the API can respond in. The first entry is the fallback.
+pub const SUPPORTED_LOCALES: &[&str] = &["en", "es"];
+
+/// Fallback when nothing in `Accept-Language` is supported.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// The client's best-matching supported locale.
+#[allow(dead_code)] // No handler renders localized text yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptLanguage(pub &'static str);
+
+impl<S> FromRequestParts<S> for AcceptLanguage
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(locale) = parts.extensions.get::<AcceptLanguage>() {
+            return Ok(*locale);
+        }
+
+        let locale = AcceptLanguage(negotiate(&parts.headers));
+        parts.extensions.insert(locale);
+        Ok(locale)
+    }
+}
+
+/// Pick the best supported locale from the `Accept-Language` header.
+pub fn negotiate(headers: &HeaderMap) -> &'static str {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .map(best_match)
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Parse a quality-weighted list (`es;q=0.9, en;q=0.8`) and return the
+/// highest-weighted supported locale. Region subtags (`en-US`) match their
+/// primary language. Entries with `q=0` are explicitly not acceptable.
+fn best_match(header_value: &str) -> &'static str {
+    let mut candidates: Vec<(&str, f32)> = header_value
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            if tag.is_empty() {
+                return None;
+            }
+
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            Some((tag, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .collect();
+
+    // Stable sort keeps header order for equal weights
+    candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    candidates
+        .iter()
+        .find_map(|(tag, _)| {
+            let primary = tag.split('-').next().unwrap_or(tag);
+            SUPPORTED_LOCALES
+                .iter()
+                .find(|supported| supported.eq_ignore_ascii_case(primary))
+                .copied()
+        })
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderValue, Request};
+
+    #[test]
+    fn test_quality_weights_select_spanish() {
+        assert_eq!(best_match("en;q=0.8, es;q=0.9"), "es");
+    }
+
+    #[test]
+    fn test_region_subtag_matches_primary_language() {
+        assert_eq!(best_match("es-MX, en;q=0.5"), "es");
+    }
+
+    #[test]
+    fn test_unsupported_falls_back_to_english() {
+        assert_eq!(best_match("ja, zh-CN;q=0.7"), "en");
+        assert_eq!(best_match(""), "en");
+    }
+
+    #[test]
+    fn test_zero_quality_is_not_acceptable() {
+        assert_eq!(best_match("es;q=0, en;q=0.1"), "en");
+    }
+
+    #[tokio::test]
+    async fn test_extractor_stores_locale_in_extensions() {
+        let (mut parts, _) = Request::builder()
+            .header(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en;q=0.8, es;q=0.9"))
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let AcceptLanguage(locale) = AcceptLanguage::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(locale, "es");
+        assert_eq!(parts.extensions.get::<AcceptLanguage>(), Some(&AcceptLanguage("es")));
+    }
+}
@@ -1,20 +1,68 @@
+mod admin;
+pub mod audit;
 mod auth;
+pub mod auth_user;
+pub mod cookie_limit;
+pub mod cookies;
+pub mod cors;
+mod cors_log;
 pub mod csrf;
+pub mod db_budget;
+pub mod etag;
+mod header_limit;
 mod health;
+mod https_redirect;
+mod introspect;
+pub mod ip_rate_limit;
+pub mod ip_pinning;
+pub mod json;
+mod jwks;
 pub mod jwt;
+pub mod locale;
+pub mod panic;
 pub mod password;
+pub mod overload;
+mod paseto;
+pub mod rate_limit;
+mod read_only;
+pub mod refresh_rotation;
+pub mod request_id;
+pub mod scopes;
+pub mod security;
+pub mod server_timing;
+pub mod sessions;
+pub mod tx;
+pub mod upload;
+pub mod response;
+mod version;
 
 #[allow(unused_imports)] // Will be used by auth middleware
 pub use auth::{login, logout, refresh, extract_token_from_request};
-pub use health::{live, ready};
+pub use cookie_limit::set_cookie_limit_middleware;
+pub use cors_log::cors_reject_log_middleware;
+pub use db_budget::db_budget_middleware;
+pub use header_limit::header_size_middleware;
+pub use https_redirect::force_https_middleware;
+pub use introspect::introspect;
+pub use ip_rate_limit::rate_limit_middleware;
+pub use health::{live, migrations, ready, HealthCache};
+pub use jwks::jwks;
+pub use read_only::read_only_middleware;
+pub use request_id::request_id_middleware;
+pub use server_timing::server_timing_middleware;
 
-use axum::http::StatusCode;
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
-use axum::Json;
+use json::ApiJson;
 use serde::Serialize;
 #[allow(unused_imports)]
 use axum::routing::get;
 use axum::Router;
+use std::panic::Location;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tower::Layer;
+use tower_http::normalize_path::{NormalizePath, NormalizePathLayer};
 
 use crate::AppState;
 
@@ -30,7 +78,7 @@ use crate::AppState;
 /// - audits easier (HIPAA / PHI handling)
 /// - future maintenance safer
 #[allow(dead_code)] // Used by repository stubs - will be active when user routes are added
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum ApiError {
     #[error("bad request")]
     BadRequest(String),
@@ -41,6 +89,11 @@ pub enum ApiError {
     #[error("forbidden")]
     Forbidden(String),
 
+    /// 403 with `code: "PASSWORD_CHANGE_REQUIRED"`: the account must change
+    /// its password (`POST /auth/change-password`) before anything else.
+    #[error("password change required")]
+    PasswordChangeRequired,
+
     #[error("not found")]
     NotFound(String),
 
@@ -50,25 +103,106 @@ pub enum ApiError {
     #[error("service unavailable")]
     ServiceUnavailable(String),
 
+    /// Transient unavailability (e.g. the database refusing connections);
+    /// the response tells clients when to retry via `Retry-After`.
+    #[error("service unavailable")]
+    ServiceUnavailableRetry { message: String, retry_after: Duration },
+
+    #[error("unsupported media type")]
+    UnsupportedMediaType(String),
+
     #[error("internal error")]
     InternalError(String),
+
+    /// Internal error carrying a diagnostic `detail` and the source location
+    /// it was raised at. Only `message` reaches clients unless
+    /// `ERROR_VERBOSITY=verbose` (never in production). Build with `ApiError::internal`.
+    #[error("internal error")]
+    InternalWithDetail {
+        message: String,
+        detail: String,
+        location: &'static Location<'static>,
+    },
 }
 
+/// Error body. Successful responses use `response::ApiResponse`.
 #[derive(Debug, Serialize)]
 struct ApiErrorBody {
     error: String,
+    /// Machine-readable reason, for errors clients must act on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    /// Internal diagnostics; verbose mode only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    /// `file:line` where the error was raised; verbose mode only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+}
+
+/// `code` of `ApiError::PasswordChangeRequired` responses.
+pub const PASSWORD_CHANGE_REQUIRED: &str = "PASSWORD_CHANGE_REQUIRED";
+
+/// `WWW-Authenticate` challenge sent with 401 responses (RFC 6750), so HTTP
+/// client libraries know to refresh or re-acquire the bearer token.
+pub const BEARER_CHALLENGE: &str = r#"Bearer realm="api", error="invalid_token""#;
+
+/// How much error detail responses include (`ERROR_VERBOSITY`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorVerbosity {
+    /// Public messages only (default, and forced in production).
+    Safe,
+    /// Also internal detail and source location (local debugging).
+    Verbose,
+}
+
+static ERROR_VERBOSITY: OnceLock<ErrorVerbosity> = OnceLock::new();
+
+impl ErrorVerbosity {
+    /// `verbose` is only honoured outside production.
+    pub fn resolve(requested: Option<&str>, production: bool) -> Self {
+        match requested.map(|v| v.trim().to_lowercase()) {
+            Some(v) if v == "verbose" && !production => ErrorVerbosity::Verbose,
+            _ => ErrorVerbosity::Safe,
+        }
+    }
+
+    /// Install the resolved setting (`AppConfig::error_verbosity`) once at startup.
+    pub fn init(verbosity: Self) {
+        let _ = ERROR_VERBOSITY.set(verbosity);
+    }
+
+    /// Set by `init`; `Safe` until then (tests).
+    pub fn current() -> Self {
+        ERROR_VERBOSITY.get().copied().unwrap_or(ErrorVerbosity::Safe)
+    }
 }
 
 impl ApiError {
+    /// Internal error with a client-safe `message` and a diagnostic `detail`.
+    #[track_caller]
+    pub fn internal(message: impl Into<String>, detail: impl Into<String>) -> Self {
+        ApiError::InternalWithDetail {
+            message: message.into(),
+            detail: detail.into(),
+            location: Location::caller(),
+        }
+    }
+
     fn status_code(&self) -> StatusCode {
         match self {
             ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
             ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
-            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Forbidden(_) | ApiError::PasswordChangeRequired => StatusCode::FORBIDDEN,
             ApiError::NotFound(_) => StatusCode::NOT_FOUND,
             ApiError::Conflict(_) => StatusCode::CONFLICT,
-            ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
-            ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::ServiceUnavailable(_) | ApiError::ServiceUnavailableRetry { .. } => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ApiError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::InternalError(_) | ApiError::InternalWithDetail { .. } => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
         }
     }
 
@@ -84,22 +218,64 @@ impl ApiError {
             | ApiError::NotFound(msg)
             | ApiError::Conflict(msg)
             | ApiError::ServiceUnavailable(msg)
-            | ApiError::InternalError(msg) => msg.clone(),
+            | ApiError::ServiceUnavailableRetry { message: msg, .. }
+            | ApiError::UnsupportedMediaType(msg)
+            | ApiError::InternalError(msg)
+            | ApiError::InternalWithDetail { message: msg, .. } => msg.clone(),
+            ApiError::PasswordChangeRequired => "Password change required".to_string(),
+        }
+    }
+
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            ApiError::PasswordChangeRequired => Some(PASSWORD_CHANGE_REQUIRED),
+            _ => None,
+        }
+    }
+
+    /// Render the response at an explicit verbosity.
+    fn into_response_with(self, verbosity: ErrorVerbosity) -> Response {
+        let status = self.status_code();
+        let retry_after = match &self {
+            ApiError::ServiceUnavailableRetry { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        };
+        let mut body = ApiErrorBody {
+            error: self.public_message(),
+            code: self.code(),
+            detail: None,
+            location: None,
+        };
+
+        if let ApiError::InternalWithDetail { detail, location, .. } = self {
+            if verbosity == ErrorVerbosity::Verbose {
+                body.detail = Some(detail);
+                body.location = Some(location.to_string());
+            }
+        }
+
+        let mut response = (status, ApiJson(body)).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static(BEARER_CHALLENGE));
+        }
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after.as_secs().max(1)));
         }
+        response
     }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let status = self.status_code();
-        (status, Json(ApiErrorBody {
-            error: self.public_message(),
-        }))
-            .into_response()
+        self.into_response_with(ErrorVerbosity::current())
     }
 }
 
-pub fn routes() -> Router<AppState> {
+pub fn routes(state: AppState) -> Router<AppState> {
     use axum::routing::{get, post};
     use axum::middleware;
     
@@ -109,10 +285,108 @@ pub fn routes() -> Router<AppState> {
         // ==========================================================================
         .route("/csrf", get(csrf::get_csrf_token))
         // ==========================================================================
+        // VERSION (minimal publicly, detailed for admins)
+        // ==========================================================================
+        .route("/version", get(version::version))
+        // ==========================================================================
+        // RATE LIMIT STATUS (general per-IP bucket)
+        // ==========================================================================
+        .route("/ratelimit", get(ip_rate_limit::rate_limit_status))
+        // ==========================================================================
+        // CORS POLICY PREVIEW (development only; 404 in production)
+        // ==========================================================================
+        .route("/debug/cors", get(cors::debug_cors))
+        // ==========================================================================
+        // ADMIN (requires the admin role)
+        // ==========================================================================
+        .route("/admin/revoke-before", post(admin::revoke_before))
+        .route("/admin/users", get(admin::list_users))
+        .route("/admin/read-only", axum::routing::put(admin::set_read_only))
+        .route("/admin/users/{id}/security", get(admin::user_security))
+        .route("/admin/users/import", post(admin::import_users))
+        // ==========================================================================
+        // CHANGE PASSWORD (CSRF-protected, unlike login/refresh)
+        // ==========================================================================
+        .route("/auth/change-password", post(auth::change_password))
+        // ==========================================================================
+        // FEATURE ROUTES
+        // ==========================================================================
+        .merge(crate::features::users::api::routes(state.clone()))
+        // ==========================================================================
         // CSRF PROTECTION MIDDLEWARE
         // ==========================================================================
         // Apply CSRF validation to all state-changing requests
-        .layer(middleware::from_fn(csrf::csrf_middleware))
-    // Add feature routes here, e.g.:
-    // .nest("/users", users::routes())
+        .layer(middleware::from_fn_with_state(state, csrf::csrf_middleware))
+}
+
+/// Trim trailing slashes before routing, so `/api/v1/version/` reaches the
+/// same handler as `/api/v1/version` instead of 404ing.
+///
+/// Must wrap the finished `Router` from the outside: layers added with
+/// `Router::layer` only run after a route has already been matched.
+pub fn normalize_trailing_slash(router: Router) -> NormalizePath<Router> {
+    NormalizePathLayer::trim_trailing_slash().layer(router)
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn status_for(uri: &str) -> StatusCode {
+        let app = Router::new()
+            .route("/api/v1/me", axum::routing::get(|| async { "me" }))
+            .route("/health/live", axum::routing::get(|| async { "ok" }));
+        normalize_trailing_slash(app)
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    async fn error_json(error: ApiError, verbosity: ErrorVerbosity) -> serde_json::Value {
+        let response = error.into_response_with(verbosity);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verbose_errors_include_detail_in_development() {
+        let verbosity = ErrorVerbosity::resolve(Some("verbose"), false);
+        let json = error_json(ApiError::internal("Token generation failed", "bad key"), verbosity).await;
+
+        assert_eq!(json["error"], "Token generation failed");
+        assert_eq!(json["detail"], "bad key");
+        assert!(json["location"].as_str().unwrap().contains("mod.rs"));
+    }
+
+    #[tokio::test]
+    async fn test_verbose_errors_suppressed_in_production() {
+        let verbosity = ErrorVerbosity::resolve(Some("verbose"), true);
+        assert_eq!(verbosity, ErrorVerbosity::Safe);
+
+        let json = error_json(ApiError::internal("Token generation failed", "bad key"), verbosity).await;
+        assert_eq!(json, serde_json::json!({ "error": "Token generation failed" }));
+    }
+
+    #[tokio::test]
+    async fn test_slashed_and_unslashed_paths_reach_same_handler() {
+        assert_eq!(status_for("/api/v1/me").await, StatusCode::OK);
+        assert_eq!(status_for("/api/v1/me/").await, StatusCode::OK);
+        assert_eq!(status_for("/api/v1/me//").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_health_routes_unaffected() {
+        assert_eq!(status_for("/health/live").await, StatusCode::OK);
+        assert_eq!(status_for("/health/live/").await, StatusCode::OK);
+        assert_eq!(status_for("/health/missing").await, StatusCode::NOT_FOUND);
+    }
 }
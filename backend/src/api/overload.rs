@@ -0,0 +1,106 @@
+// ==============================================================================
+// CONCURRENCY LIMIT / LOAD SHEDDING
+// ==============================================================================
+//
+// At most `config::MAX_CONCURRENT_REQUESTS` requests are processed at once.
+//
+// - Default: requests beyond the limit wait for a free slot (queueing)
+// - `SHED_ON_OVERLOAD=true`: they are rejected immediately with 503, which
+//   keeps tail latency bounded and lets load balancers/clients retry
+//   elsewhere instead of piling up behind a saturated instance
+//
+// ==============================================================================
+
+use axum::error_handling::HandleErrorLayer;
+use axum::response::{IntoResponse, Response};
+use axum::{BoxError, Router};
+use tower::limit::ConcurrencyLimitLayer;
+use tower::util::Either;
+use tower::ServiceBuilder;
+
+use super::ApiError;
+
+/// Limit `router` to `limit` in-flight requests, shedding the excess when
+/// `shed` is set and queueing it otherwise.
+pub fn limit_concurrency<S>(router: Router<S>, limit: usize, shed: bool) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    // Load shedding must wrap the limiter in the same layer: it turns "not
+    // ready" from the limiter into an immediate error instead of waiting
+    let layer = if shed {
+        Either::Left(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(overloaded))
+                .load_shed()
+                .concurrency_limit(limit),
+        )
+    } else {
+        Either::Right(ConcurrencyLimitLayer::new(limit))
+    };
+
+    router.layer(layer)
+}
+
+async fn overloaded(_: BoxError) -> Response {
+    ApiError::ServiceUnavailable("Server is overloaded, try again later".to_string()).into_response()
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use std::time::{Duration, Instant};
+    use tower::ServiceExt;
+
+    fn slow_app(shed: bool) -> Router {
+        let app = Router::new().route(
+            "/slow",
+            axum::routing::get(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "done"
+            }),
+        );
+        // `with_state` materializes the routes once, as in `main`; otherwise
+        // each request would get a fresh limiter
+        limit_concurrency(app, 1, shed).with_state(())
+    }
+
+    fn request() -> Request<Body> {
+        Request::builder().uri("/slow").body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_shedding_returns_503_promptly_beyond_limit() {
+        let app = slow_app(true);
+
+        let busy = tokio::spawn(app.clone().oneshot(request()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        assert_eq!(busy.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_without_shedding_excess_requests_wait() {
+        let app = slow_app(false);
+
+        let busy = tokio::spawn(app.clone().oneshot(request()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(busy.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,79 @@
+// ==============================================================================
+// PANIC RECOVERY
+// ==============================================================================
+//
+// Without this, a panicking handler drops the connection and the client
+// sees a reset instead of an HTTP response. `CatchPanicLayer` (installed in
+// `app.rs`) turns the panic into the standard `ApiError` 500 body instead.
+//
+// - The panic message is logged, never returned: it may contain internals
+// - Each panic gets a random reference id, logged and returned in the
+//   message, so a user report can be matched to the log line
+// - The server keeps running; only the one request fails
+//
+// ==============================================================================
+
+use axum::response::{IntoResponse, Response};
+use std::any::Any;
+
+use super::ApiError;
+
+/// `CatchPanicLayer::custom` handler: log the panic and answer 500.
+pub fn handle_panic(err: Box<dyn Any + Send + 'static>) -> Response {
+    let message = err
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| err.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic payload");
+    let reference = uuid::Uuid::new_v4();
+
+    tracing::error!(%reference, panic = message, "Request handler panicked");
+
+    ApiError::InternalError(format!("Internal server error (reference {reference})")).into_response()
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+    use tower_http::catch_panic::CatchPanicLayer;
+
+    async fn boom() -> &'static str {
+        panic!("secret internal state")
+    }
+
+    #[tokio::test]
+    async fn test_panicking_route_returns_clean_500() {
+        let app = Router::new()
+            .route("/boom", get(boom))
+            .route("/ok", get(|| async { "ok" }))
+            .layer(CatchPanicLayer::custom(handle_panic));
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/boom").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let error = json["error"].as_str().unwrap();
+        assert!(error.starts_with("Internal server error (reference "));
+        assert!(!error.contains("secret"));
+
+        // The service is still usable afterwards
+        let response = app
+            .oneshot(Request::builder().uri("/ok").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -9,16 +9,144 @@
 // - Argon2id is resistant to GPU attacks and side-channel attacks
 // - Uses random salt per password (stored in the hash string)
 // - Memory-hard: requires significant RAM, defeating parallel attacks
+// - Verification refuses stored hashes whose cost parameters exceed safe
+//   bounds, so a crafted PHC string can't pin a CPU or exhaust memory
+//
+// UNICODE NORMALIZATION:
+// Passwords are normalized to NFC before hashing and verification, so the
+// same visible password typed as composed (`é`, U+00E9) or decomposed
+// (`e` + U+0301) input matches. ONE-TIME BEHAVIOR CHANGE: hashes created
+// before this were over the raw bytes. Verification falls back to the raw
+// input, so users who keep typing the same form still get in; a user whose
+// input method changed form since the hash was made needs a password reset.
+//
+// DENYLIST:
+// `PASSWORD_DENYLIST_PATH` names a local file of banned passwords (one per
+// line, `#` comments), e.g. the company or product name and a top-100 list.
+// Matching is case-insensitive; `PASSWORD_DENYLIST_MATCH=substring` also
+// rejects passwords that merely contain an entry (default: `exact`).
+//
+// PEPPER:
+// `PASSWORD_PEPPER` (or `PASSWORD_PEPPER_FILE`) is a server-side secret fed
+// to Argon2 as its secret input for every new hash, so a leaked database
+// alone can't be cracked offline. Hashes made before a pepper was configured
+// still verify (verification falls back to no pepper). Losing or changing
+// the pepper locks out every account hashed with it.
+//
+// BULK HASHING:
+// `hash_passwords` hashes a batch on the blocking pool, at most
+// `BULK_HASH_CONCURRENCY` at a time (default: available CPUs), so a large
+// import uses the spare cores without starving every other request of them.
 //
 // ==============================================================================
 
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, Params, Version,
 };
+use std::borrow::Cow;
+use std::env;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
 use super::ApiError;
 
+// ==============================================================================
+// VERIFICATION COST BOUNDS
+// ==============================================================================
+
+/// Upper bounds on the Argon2 parameters accepted from a stored hash.
+///
+/// Configured via `ARGON2_MAX_MEMORY_KIB`, `ARGON2_MAX_ITERATIONS` and
+/// `ARGON2_MAX_PARALLELISM`. Defaults leave ample headroom above the
+/// parameters `hash_password` uses (19 MiB, t=2, p=1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashCostBounds {
+    pub max_memory_kib: u64,
+    pub max_iterations: u64,
+    pub max_parallelism: u64,
+}
+
+impl Default for HashCostBounds {
+    fn default() -> Self {
+        Self {
+            max_memory_kib: 256 * 1024, // 256 MiB
+            max_iterations: 10,
+            max_parallelism: 16,
+        }
+    }
+}
+
+impl HashCostBounds {
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: u64| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_memory_kib: read("ARGON2_MAX_MEMORY_KIB", defaults.max_memory_kib),
+            max_iterations: read("ARGON2_MAX_ITERATIONS", defaults.max_iterations),
+            max_parallelism: read("ARGON2_MAX_PARALLELISM", defaults.max_parallelism),
+        }
+    }
+
+    /// Bounds resolved once from the environment.
+    fn current() -> Self {
+        static BOUNDS: OnceLock<HashCostBounds> = OnceLock::new();
+        *BOUNDS.get_or_init(Self::from_env)
+    }
+
+    /// Reject hashes whose `m`/`t`/`p` parameters exceed the bounds (or don't parse).
+    fn check(&self, hash: &PasswordHash) -> Result<(), String> {
+        for (param, max) in [
+            ("m", self.max_memory_kib),
+            ("t", self.max_iterations),
+            ("p", self.max_parallelism),
+        ] {
+            if let Some(value) = hash.params.get_str(param) {
+                match value.parse::<u64>() {
+                    Ok(n) if n <= max => {}
+                    _ => return Err(format!("argon2 parameter {param}={value} exceeds limit {max}")),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// ==============================================================================
+// PEPPER
+// ==============================================================================
+
+/// Pepper installed by `init_pepper`; `None` until then (tests) or when unset.
+static PEPPER: OnceLock<Option<String>> = OnceLock::new();
+
+/// Install the pepper (`AppConfig::password_pepper`) at startup, before any
+/// password is hashed or verified.
+pub fn init_pepper(pepper: Option<String>) {
+    PEPPER.get_or_init(|| pepper);
+}
+
+fn pepper() -> Option<&'static [u8]> {
+    PEPPER.get_or_init(|| None).as_deref().map(str::as_bytes)
+}
+
+/// Argon2id with the default parameters, keyed with `pepper` when given.
+fn argon2(pepper: Option<&[u8]>) -> Result<Argon2<'_>, ApiError> {
+    match pepper {
+        None => Ok(Argon2::default()),
+        Some(pepper) => Argon2::new_with_secret(pepper, Algorithm::Argon2id, Version::V0x13, Params::default())
+            .map_err(|e| ApiError::internal("Password hashing failed", e.to_string())),
+    }
+}
+
 // ==============================================================================
 // PASSWORD HASHING
 // ==============================================================================
@@ -38,17 +166,23 @@ use super::ApiError;
 /// // hash looks like: $argon2id$v=19$m=19456,t=2,p=1$salt$hash
 /// ```
 pub fn hash_password(password: &str) -> Result<String, ApiError> {
+    hash_password_with(password, pepper())
+}
+
+fn hash_password_with(password: &str, pepper: Option<&[u8]>) -> Result<String, ApiError> {
+    let password = normalize_password(password);
+
     // Validate password before hashing
-    validate_password_strength(password)?;
+    validate_password_strength(&password)?;
     
     let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default(); // Uses recommended params
+    let argon2 = argon2(pepper)?; // Uses recommended params
     
     let password_hash = argon2
         .hash_password(password.as_bytes(), &salt)
         .map_err(|e| {
             tracing::error!("Password hashing failed: {}", e);
-            ApiError::InternalError("Password hashing failed".to_string())
+            ApiError::internal("Password hashing failed", e.to_string())
         })?;
     
     Ok(password_hash.to_string())
@@ -68,20 +202,138 @@ pub fn hash_password(password: &str) -> Result<String, ApiError> {
 /// # Timing Safety
 /// This function uses constant-time comparison to prevent timing attacks.
 pub fn verify_password(password: &str, hash: &str) -> Result<bool, ApiError> {
+    verify_password_with(password, hash, pepper())
+}
+
+fn verify_password_with(password: &str, hash: &str, pepper: Option<&[u8]>) -> Result<bool, ApiError> {
     let parsed_hash = PasswordHash::new(hash)
         .map_err(|e| {
             tracing::error!("Failed to parse password hash: {}", e);
-            ApiError::InternalError("Password verification failed".to_string())
+            ApiError::internal("Password verification failed", e.to_string())
         })?;
+
+    // Refuse to run attacker-influenced cost parameters
+    HashCostBounds::current().check(&parsed_hash).map_err(|detail| {
+        tracing::error!("Refusing to verify password hash: {}", detail);
+        ApiError::internal("Password verification failed", detail)
+    })?;
     
-    let argon2 = Argon2::default();
-    
-    match argon2.verify_password(password.as_bytes(), &parsed_hash) {
+    let normalized = normalize_password(password);
+    if verify_peppered(normalized.as_bytes(), &parsed_hash, pepper)? {
+        return Ok(true);
+    }
+
+    // Hashes made before NFC normalization were over the raw input
+    match normalized {
+        Cow::Owned(_) => verify_peppered(password.as_bytes(), &parsed_hash, pepper),
+        Cow::Borrowed(_) => Ok(false),
+    }
+}
+
+/// Verify with `pepper`, then without it: hashes made before the pepper
+/// was configured didn't use one.
+fn verify_peppered(password: &[u8], parsed_hash: &PasswordHash<'_>, pepper: Option<&[u8]>) -> Result<bool, ApiError> {
+    if pepper.is_some() && verify_bytes(password, parsed_hash, pepper)? {
+        return Ok(true);
+    }
+    verify_bytes(password, parsed_hash, None)
+}
+
+fn verify_bytes(password: &[u8], parsed_hash: &PasswordHash<'_>, pepper: Option<&[u8]>) -> Result<bool, ApiError> {
+    match argon2(pepper)?.verify_password(password, parsed_hash) {
         Ok(()) => Ok(true),
         Err(argon2::password_hash::Error::Password) => Ok(false), // Wrong password
         Err(e) => {
             tracing::error!("Password verification error: {}", e);
-            Err(ApiError::InternalError("Password verification failed".to_string()))
+            Err(ApiError::internal("Password verification failed", e.to_string()))
+        }
+    }
+}
+
+// ==============================================================================
+// BULK HASHING
+// ==============================================================================
+
+static BULK_HASH_CONCURRENCY: OnceLock<usize> = OnceLock::new();
+
+/// Passwords hashed in parallel by `hash_passwords` (`BULK_HASH_CONCURRENCY`,
+/// default: available CPUs; at least 1).
+pub fn bulk_hash_concurrency() -> usize {
+    *BULK_HASH_CONCURRENCY.get_or_init(|| {
+        env::var("BULK_HASH_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+    })
+}
+
+/// Hash a batch of passwords (e.g. for an import), returning hashes in input
+/// order. Fails on the first password that is rejected or fails to hash.
+pub async fn hash_passwords(passwords: Vec<String>) -> Result<Vec<String>, ApiError> {
+    hash_all_with(passwords, bulk_hash_concurrency(), hash_password).await
+}
+
+/// Like `hash_passwords`, but with a result per password, so one weak
+/// password doesn't fail the batch (best-effort imports).
+pub async fn hash_passwords_each(passwords: Vec<String>) -> Result<Vec<Result<String, ApiError>>, ApiError> {
+    hash_each_with(passwords, bulk_hash_concurrency(), hash_password).await
+}
+
+/// Run `hash` over `passwords`, failing on the first error.
+async fn hash_all_with<F>(passwords: Vec<String>, concurrency: usize, hash: F) -> Result<Vec<String>, ApiError>
+where
+    F: Fn(&str) -> Result<String, ApiError> + Send + Sync + 'static,
+{
+    hash_each_with(passwords, concurrency, hash).await?.into_iter().collect()
+}
+
+/// Run `hash` over `passwords` on the blocking pool, `concurrency` at a time.
+/// The outer error is for the pool itself failing (e.g. a panicked hash).
+async fn hash_each_with<F>(
+    passwords: Vec<String>,
+    concurrency: usize,
+    hash: F,
+) -> Result<Vec<Result<String, ApiError>>, ApiError>
+where
+    F: Fn(&str) -> Result<String, ApiError> + Send + Sync + 'static,
+{
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let hash = Arc::new(hash);
+    let mut tasks = JoinSet::new();
+
+    for (index, password) in passwords.into_iter().enumerate() {
+        let permit = permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| ApiError::internal("Password hashing failed", e.to_string()))?;
+        let hash = hash.clone();
+        tasks.spawn_blocking(move || {
+            let _permit = permit;
+            (index, hash(&password))
+        });
+    }
+
+    let mut hashes = vec![Ok(String::new()); tasks.len()];
+    while let Some(joined) = tasks.join_next().await {
+        let (index, result) = joined.map_err(|e| ApiError::internal("Password hashing failed", e.to_string()))?;
+        hashes[index] = result;
+    }
+    Ok(hashes)
+}
+
+/// NFC form of `password`; borrowed when it already is NFC (the common case).
+pub fn normalize_password(password: &str) -> Cow<'_, str> {
+    match is_nfc_quick(password.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(password),
+        _ => {
+            let normalized: String = password.nfc().collect();
+            if normalized == password {
+                Cow::Borrowed(password)
+            } else {
+                Cow::Owned(normalized)
+            }
         }
     }
 }
@@ -94,7 +346,91 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, ApiError> {
 const MIN_PASSWORD_LENGTH: usize = 8;
 
 /// Maximum password length (prevent DoS via huge passwords)
-const MAX_PASSWORD_LENGTH: usize = 128;
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// How denylist entries are compared with a password (both lowercased).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DenylistMatch {
+    /// The whole password equals an entry.
+    #[default]
+    Exact,
+    /// The password contains an entry anywhere.
+    Substring,
+}
+
+/// Banned passwords loaded from `PASSWORD_DENYLIST_PATH`.
+#[derive(Debug, Clone, Default)]
+pub struct PasswordDenylist {
+    entries: Vec<String>,
+    matching: DenylistMatch,
+}
+
+static DENYLIST: OnceLock<PasswordDenylist> = OnceLock::new();
+
+impl PasswordDenylist {
+    /// Entries from denylist file `contents`: one per line, blank lines and
+    /// `#` comments skipped.
+    pub fn parse(contents: &str, matching: DenylistMatch) -> Self {
+        let entries = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_lowercase)
+            .collect();
+        Self { entries, matching }
+    }
+
+    /// Read `PASSWORD_DENYLIST_PATH` / `PASSWORD_DENYLIST_MATCH`; empty when no path is set.
+    fn from_env() -> Result<Self, String> {
+        let matching = match env::var("PASSWORD_DENYLIST_MATCH").as_deref().map(str::trim) {
+            Err(_) | Ok("") | Ok("exact") => DenylistMatch::Exact,
+            Ok("substring") => DenylistMatch::Substring,
+            Ok(other) => {
+                return Err(format!("PASSWORD_DENYLIST_MATCH must be exact or substring (got {other:?})"));
+            }
+        };
+        let Ok(path) = env::var("PASSWORD_DENYLIST_PATH") else {
+            return Ok(Self::default());
+        };
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("PASSWORD_DENYLIST_PATH: failed to read {path}: {e}"))?;
+        Ok(Self::parse(&contents, matching))
+    }
+
+    /// Denylist resolved once from the environment. Startup calls
+    /// `init_denylist` first, so load errors surface there.
+    fn current() -> &'static Self {
+        DENYLIST.get_or_init(|| {
+            Self::from_env().unwrap_or_else(|err| {
+                tracing::error!("Password denylist disabled: {err}");
+                Self::default()
+            })
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether `password` is banned.
+    pub fn is_denied(&self, password: &str) -> bool {
+        if self.entries.is_empty() {
+            return false;
+        }
+        let password = password.to_lowercase();
+        match self.matching {
+            DenylistMatch::Exact => self.entries.contains(&password),
+            DenylistMatch::Substring => self.entries.iter().any(|entry| password.contains(entry.as_str())),
+        }
+    }
+}
+
+/// Load the password denylist at startup, failing on an unreadable file or
+/// bad `PASSWORD_DENYLIST_MATCH`. Returns the number of entries.
+pub fn init_denylist() -> Result<usize, String> {
+    let denylist = PasswordDenylist::from_env()?;
+    Ok(DENYLIST.get_or_init(|| denylist).len())
+}
 
 /// Validate password strength.
 /// 
@@ -102,11 +438,16 @@ const MAX_PASSWORD_LENGTH: usize = 128;
 /// - Minimum 8 characters (NIST recommendation)
 /// - Maximum 128 characters (prevent DoS)
 /// - Must contain at least one letter and one number
+/// - Must not be on the configured denylist (`PASSWORD_DENYLIST_PATH`)
 /// 
 /// # Note
 /// NIST SP 800-63B recommends against complexity rules (special chars, etc.)
 /// in favor of length and checking against common password lists.
 pub fn validate_password_strength(password: &str) -> Result<(), ApiError> {
+    validate_password_against(password, PasswordDenylist::current())
+}
+
+fn validate_password_against(password: &str, denylist: &PasswordDenylist) -> Result<(), ApiError> {
     if password.len() < MIN_PASSWORD_LENGTH {
         return Err(ApiError::BadRequest(format!(
             "Password must be at least {} characters",
@@ -130,6 +471,12 @@ pub fn validate_password_strength(password: &str) -> Result<(), ApiError> {
         ));
     }
     
+    if denylist.is_denied(password) {
+        return Err(ApiError::BadRequest(
+            "This password is too common or easy to guess. Please choose another".to_string()
+        ));
+    }
+    
     Ok(())
 }
 
@@ -177,15 +524,144 @@ mod tests {
         assert!(result.is_err());
     }
     
+    #[test]
+    fn test_denylisted_password_rejected_exact() {
+        let denylist = PasswordDenylist::parse("# banned\nAcme2024\n\npassword1\n", DenylistMatch::Exact);
+        assert_eq!(denylist.len(), 2);
+        
+        let err = validate_password_against("ACME2024", &denylist).unwrap_err();
+        assert!(matches!(&err, ApiError::BadRequest(msg) if msg.contains("too common")), "{err:?}");
+        // Similar but not listed
+        assert!(validate_password_against("Acme2024!", &denylist).is_ok());
+    }
+    
+    #[test]
+    fn test_denylisted_password_rejected_substring() {
+        let denylist = PasswordDenylist::parse("acme", DenylistMatch::Substring);
+        
+        assert!(validate_password_against("MyAcmeLogin9", &denylist).is_err());
+        assert!(validate_password_against("MyAcneLogin9", &denylist).is_ok());
+    }
+    
     #[test]
     fn test_password_no_digit() {
         let result = hash_password("NoDigitsHere");
         assert!(result.is_err());
     }
     
+    #[test]
+    fn test_hash_with_absurd_parameters_rejected_without_running() {
+        let hash = hash_password("SecurePass123").unwrap();
+        let hostile = hash.replacen("m=19456", "m=4000000000", 1);
+        assert_ne!(hash, hostile);
+
+        let started = std::time::Instant::now();
+        assert!(verify_password("SecurePass123", &hostile).is_err());
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_hash_cost_bounds_check_each_parameter() {
+        let bounds = HashCostBounds::default();
+        let phc = |params: &str| format!("$argon2id$v=19${params}$c29tZXNhbHQ$aGFzaGhhc2hoYXNoaGFzaA");
+
+        let ok = phc("m=19456,t=2,p=1");
+        assert!(bounds.check(&PasswordHash::new(&ok).unwrap()).is_ok());
+
+        for params in ["m=19456,t=1000,p=1", "m=19456,t=2,p=255"] {
+            let hash = phc(params);
+            assert!(bounds.check(&PasswordHash::new(&hash).unwrap()).is_err(), "{params}");
+        }
+    }
+
     #[test]
     fn test_valid_password_accepted() {
         let result = validate_password_strength("ValidPass1");
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_nfc_and_nfd_passwords_verify_against_each_other() {
+        let composed = "Caf\u{e9}Secret1";
+        let decomposed = "Cafe\u{301}Secret1";
+        assert_ne!(composed, decomposed);
+
+        let hash = hash_password(composed).unwrap();
+        assert!(verify_password(decomposed, &hash).unwrap());
+
+        let hash = hash_password(decomposed).unwrap();
+        assert!(verify_password(composed, &hash).unwrap());
+        assert!(!verify_password("CafeSecret1", &hash).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_raw_nfd_hash_still_verifies() {
+        let decomposed = "Cafe\u{301}Secret1";
+        let salt = SaltString::generate(&mut OsRng);
+        let legacy = Argon2::default()
+            .hash_password(decomposed.as_bytes(), &salt)
+            .unwrap()
+            .to_string();
+
+        assert!(verify_password(decomposed, &legacy).unwrap());
+    }
+
+    #[test]
+    fn test_peppered_hash_needs_the_pepper() {
+        let pepper = Some(b"server-side-pepper".as_slice());
+        let hash = hash_password_with("PepperedPass1", pepper).unwrap();
+
+        assert!(verify_password_with("PepperedPass1", &hash, pepper).unwrap());
+        assert!(!verify_password_with("WrongPass1", &hash, pepper).unwrap());
+        // The database alone isn't enough
+        assert!(!verify_password_with("PepperedPass1", &hash, None).unwrap());
+        assert!(!verify_password_with("PepperedPass1", &hash, Some(b"other-pepper".as_slice())).unwrap());
+    }
+
+    #[test]
+    fn test_hash_from_before_the_pepper_still_verifies() {
+        let hash = hash_password_with("PlainPass1", None).unwrap();
+        let pepper = Some(b"server-side-pepper".as_slice());
+
+        assert!(verify_password_with("PlainPass1", &hash, pepper).unwrap());
+        assert!(!verify_password_with("WrongPass1", &hash, pepper).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_hash_passwords_all_verify() {
+        let passwords: Vec<String> = (0..4).map(|i| format!("ImportPass{i}x")).collect();
+        let hashes = hash_all_with(passwords.clone(), 2, hash_password).await.unwrap();
+
+        assert_eq!(hashes.len(), passwords.len());
+        for (password, hash) in passwords.iter().zip(&hashes) {
+            assert!(verify_password(password, hash).unwrap(), "{password}");
+        }
+        assert!(hash_all_with(vec!["short".to_string()], 2, hash_password).await.is_err());
+    }
+
+    /// Peak number of hashes running at once for a batch of 8.
+    async fn peak_hash_concurrency(concurrency: usize) -> usize {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (running_in, peak_in) = (running.clone(), peak.clone());
+        let slow_hash = move |password: &str| {
+            let now = running_in.fetch_add(1, Ordering::SeqCst) + 1;
+            peak_in.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            running_in.fetch_sub(1, Ordering::SeqCst);
+            Ok(format!("hash:{password}"))
+        };
+
+        let passwords: Vec<String> = (0..8).map(|i| i.to_string()).collect();
+        let hashes = hash_all_with(passwords, concurrency, slow_hash).await.unwrap();
+        assert_eq!(hashes[7], "hash:7", "hashes keep input order");
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_bulk_hash_parallelism_scales_with_concurrency() {
+        assert_eq!(peak_hash_concurrency(1).await, 1);
+        assert_eq!(peak_hash_concurrency(4).await, 4);
+    }
 }
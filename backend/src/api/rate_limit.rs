@@ -0,0 +1,227 @@
+// ==============================================================================
+// PER-ACCOUNT LOGIN RATE LIMITING
+// ==============================================================================
+//
+// The auth governor in `app.rs` limits requests per client IP. That stops a
+// single attacker IP from hammering many accounts, but NOT a distributed
+// attack (botnet) spraying guesses at one account from thousands of IPs.
+//
+// This module adds a second, independent limit keyed on the normalized
+// email address, checked inside `login` BEFORE any password verification
+// work is done.
+//
+// The same limiter type also throttles expensive per-account endpoints
+// such as the data export.
+//
+// Refused attempts are counted per email while the account is throttled, so
+// support can see whether an account is currently locked out and how hard it
+// is being hit (`GET /admin/users/{id}/security`).
+//
+// STORAGE:
+// Counts live in the shared `KeyValueStore` (`STORE_BACKEND`), so with Redis
+// the limit holds across replicas and restarts. Each account gets a counter
+// per fixed one-minute window (`<name>:<email>:<minute>`); attempts beyond
+// the limit are refused until the window ends. A client can therefore make
+// up to twice the limit across a window boundary.
+//
+// If the store is unreachable the limiter fails open (and logs): the per-IP
+// governor still applies, and logins shouldn't depend on the cache.
+//
+// ==============================================================================
+
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::store::{self, KeyValueStore};
+
+/// Login attempts allowed per account per minute.
+const LOGIN_ATTEMPTS_PER_EMAIL_PER_MINUTE: u32 = 5;
+
+/// Data exports (`GET /me/export`) allowed per account per minute.
+pub const EXPORTS_PER_EMAIL_PER_MINUTE: u32 = 1;
+
+/// Length of one counting window.
+const WINDOW_SECONDS: i64 = 60;
+
+/// An email whose limit is currently exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttled {
+    /// Attempts refused in the current window.
+    pub refused: u32,
+    /// Time until the next attempt will be allowed.
+    pub retry_after: Duration,
+}
+
+/// Per-minute attempt counter keyed on the (normalized) email.
+#[derive(Debug)]
+pub struct EmailRateLimiter {
+    kv: Arc<dyn KeyValueStore>,
+    /// Key prefix, so limiters sharing a store count separately.
+    name: &'static str,
+    per_minute: u32,
+}
+
+impl EmailRateLimiter {
+    /// Create a limiter named `name` allowing `per_minute` attempts per email.
+    pub fn new(kv: Arc<dyn KeyValueStore>, name: &'static str, per_minute: u32) -> Self {
+        Self {
+            kv,
+            name,
+            per_minute: per_minute.max(1),
+        }
+    }
+
+    /// The login limiter (`LOGIN_ATTEMPTS_PER_EMAIL_PER_MINUTE`).
+    pub fn login(kv: Arc<dyn KeyValueStore>) -> Self {
+        Self::new(kv, "login-rate", LOGIN_ATTEMPTS_PER_EMAIL_PER_MINUTE)
+    }
+
+    /// The data export limiter (`EXPORTS_PER_EMAIL_PER_MINUTE`).
+    pub fn export(kv: Arc<dyn KeyValueStore>) -> Self {
+        Self::new(kv, "export-rate", EXPORTS_PER_EMAIL_PER_MINUTE)
+    }
+
+    /// Record an attempt for `email` at `now` (from the app clock).
+    ///
+    /// Returns `Err(retry_after)` when the account's limit is exhausted.
+    pub async fn check(&self, email: &str, now: DateTime<Utc>) -> Result<(), Duration> {
+        let key = self.key(email, now);
+        let count = match store::call(&self.kv, move |kv| kv.increment(&key, window()))
+            .await
+        {
+            Ok(count) => count,
+            Err(err) => {
+                tracing::error!("Per-account rate limit unavailable: {err}");
+                return Ok(());
+            }
+        };
+        if count <= i64::from(self.per_minute) {
+            return Ok(());
+        }
+        Err(until_next_window(now))
+    }
+
+    /// Whether `email` is currently throttled, and how many attempts were refused.
+    pub async fn throttled(&self, email: &str, now: DateTime<Utc>) -> Option<Throttled> {
+        let key = self.key(email, now);
+        let count = store::call(&self.kv, move |kv| kv.get(&key))
+            .await
+            .inspect_err(|err| tracing::error!("Per-account rate limit unavailable: {err}"))
+            .ok()??
+            .parse::<i64>()
+            .ok()?;
+        let refused = count - i64::from(self.per_minute);
+        (refused > 0).then(|| Throttled {
+            refused: u32::try_from(refused).unwrap_or(u32::MAX),
+            retry_after: until_next_window(now),
+        })
+    }
+
+    fn key(&self, email: &str, now: DateTime<Utc>) -> String {
+        let window = now.timestamp().div_euclid(WINDOW_SECONDS);
+        format!("{}:{}:{window}", self.name, normalize(email))
+    }
+}
+
+/// TTL of a window's counter.
+fn window() -> Duration {
+    Duration::from_secs(WINDOW_SECONDS as u64)
+}
+
+/// Time from `now` until the next window starts.
+fn until_next_window(now: DateTime<Utc>) -> Duration {
+    let window_ms = WINDOW_SECONDS * 1000;
+    let elapsed = now.timestamp_millis().rem_euclid(window_ms);
+    Duration::from_millis((window_ms - elapsed) as u64)
+}
+
+/// Case/whitespace variations of an address must share one bucket.
+fn normalize(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    fn limiter(per_minute: u32) -> EmailRateLimiter {
+        EmailRateLimiter::new(Arc::new(MemoryStore::default()), "test", per_minute)
+    }
+
+    /// Start of a window, so a test never straddles two.
+    fn window_start() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_040, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_allows_attempts_up_to_limit() {
+        let limiter = limiter(3);
+        let now = window_start();
+        for _ in 0..3 {
+            assert!(limiter.check("user@example.com", now).await.is_ok());
+        }
+        assert!(limiter.check("user@example.com", now).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_limit_is_per_email() {
+        let limiter = limiter(1);
+        let now = window_start();
+        assert!(limiter.check("a@example.com", now).await.is_ok());
+        assert!(limiter.check("a@example.com", now).await.is_err());
+        assert!(limiter.check("b@example.com", now).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_email_is_normalized() {
+        let limiter = limiter(1);
+        let now = window_start();
+        assert!(limiter.check("User@Example.com", now).await.is_ok());
+        assert!(limiter.check("  user@example.com ", now).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refused_attempts_are_reported_while_throttled() {
+        let limiter = limiter(1);
+        let now = window_start() + chrono::Duration::seconds(15);
+        assert!(limiter.check("user@example.com", now).await.is_ok());
+        assert_eq!(limiter.throttled("user@example.com", now).await, None);
+
+        assert!(limiter.check("user@example.com", now).await.is_err());
+        assert_eq!(limiter.check("USER@example.com", now).await, Err(Duration::from_secs(45)));
+        let throttled = limiter.throttled("user@example.com", now).await.unwrap();
+        assert_eq!(throttled.refused, 2);
+        assert_eq!(throttled.retry_after, Duration::from_secs(45));
+        assert_eq!(limiter.throttled("other@example.com", now).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_limit_resets_with_the_next_window() {
+        let limiter = limiter(1);
+        let now = window_start();
+        assert!(limiter.check("user@example.com", now).await.is_ok());
+        assert!(limiter.check("user@example.com", now).await.is_err());
+
+        let next = now + chrono::Duration::seconds(WINDOW_SECONDS);
+        assert!(limiter.check("user@example.com", next).await.is_ok());
+        assert_eq!(limiter.throttled("user@example.com", next).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_limiters_sharing_a_store_count_separately() {
+        let kv: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::default());
+        let login = EmailRateLimiter::login(kv.clone());
+        let export = EmailRateLimiter::export(kv);
+        let now = window_start();
+
+        assert!(export.check("user@example.com", now).await.is_ok());
+        assert!(export.check("user@example.com", now).await.is_err());
+        assert!(login.check("user@example.com", now).await.is_ok());
+    }
+}
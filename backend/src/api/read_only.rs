@@ -0,0 +1,82 @@
+// ==============================================================================
+// READ-ONLY MODE
+// ==============================================================================
+//
+// For maintenance or incident mitigation: reads keep working, writes are
+// refused with `503` and a clear message instead of failing half-way.
+//
+// - Starts from `READ_ONLY_MODE` (default off)
+// - Toggled at runtime by admins: `PUT /api/v1/admin/read-only {"enabled": true}`
+// - Applies to POST/PUT/PATCH/DELETE under `/api/v1`
+//
+// EXEMPT (must work while read-only):
+// - `POST /auth/logout` (users can always end their session)
+// - `PUT /admin/read-only` (otherwise the mode could never be turned off)
+//
+// The flag is per-process; with several replicas, toggle each one.
+//
+// ==============================================================================
+
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::atomic::Ordering;
+
+use super::ApiError;
+use crate::AppState;
+
+/// Paths (relative to `/api/v1`) that accept writes even in read-only mode.
+const EXEMPT_PATHS: &[&str] = &["/auth/logout", "/admin/read-only"];
+
+fn is_write(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE)
+}
+
+/// Reject writes while `state.read_only` is set. Layer on the `/api/v1` router.
+pub async fn read_only_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if state.read_only.load(Ordering::Relaxed)
+        && is_write(request.method())
+        && !EXEMPT_PATHS.contains(&request.uri().path())
+    {
+        return ApiError::ServiceUnavailable(
+            "The API is in read-only mode for maintenance; changes are temporarily disabled".to_string(),
+        )
+        .into_response();
+    }
+    next.run(request).await
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::config::AppConfig;
+    use crate::test_support::TestApp;
+
+    async fn test_app(read_only: bool) -> TestApp {
+        TestApp::builder().config(AppConfig { read_only, ..Default::default() }).build().await
+    }
+
+    #[tokio::test]
+    async fn test_reads_pass_and_writes_are_rejected() {
+        let app = test_app(true).await;
+        assert_eq!(app.get("/api/v1/version").await.status, StatusCode::OK);
+        let response = app.post_json("/api/v1/auth/change-password", json!({})).await;
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(app.post_json("/api/v1/auth/logout", json!({})).await.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_writes_pass_when_disabled() {
+        let app = test_app(false).await;
+        // Reaches the handler, which wants a token
+        let response = app.post_json("/api/v1/auth/change-password", json!({})).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+}
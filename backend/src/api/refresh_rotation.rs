@@ -0,0 +1,338 @@
+// ==============================================================================
+// REFRESH TOKEN ROTATION
+// ==============================================================================
+//
+// Every successful refresh returns a NEW refresh token and retires the one
+// that was presented (keyed by its `jti`). Presenting a retired token again
+// means it was copied, so the refresh is rejected.
+//
+// GRACE WINDOW (`REFRESH_GRACE_SECONDS`, default 10):
+// Clients often fire two refreshes at once (e.g. on app resume). Without a
+// grace window the second one would present the token the first just
+// retired and log the user out. Within the window, a retired token instead
+// gets the SAME tokens the first refresh issued, so concurrent refreshes are
+// idempotent and all callers end up with one consistent token pair.
+//
+// ROTATION CAP (`MAX_REFRESH_ROTATIONS`, default unlimited):
+// Each login starts a token family; every rotation within it is counted.
+// Once a family has rotated the configured number of times, the next
+// refresh is refused and the user must log in again, bounding how long one
+// login can be extended. Grace-window repeats don't count.
+//
+// STORAGE:
+// Retired tokens and rotation counts live in the shared `KeyValueStore`
+// (`STORE_BACKEND`), so with Redis reuse detection holds across replicas:
+// - `refresh-retired:<jti>`: when the token was rotated. Claimed with
+//   `set_if_absent`, so exactly one concurrent refresh rotates a token
+// - `refresh-issued:<jti>`: the tokens that rotation issued, kept for the
+//   grace window so repeats can be answered with them
+// - `refresh-rotations:<family>`: rotations so far (only with a cap)
+//
+// NOTES:
+// - Entries expire once the retired token would have expired anyway
+// - A repeat that arrives while the first refresh is still issuing waits
+//   briefly for its tokens
+// - If the store is unreachable, refreshes fail with 503 rather than
+//   skipping reuse detection
+//
+// ==============================================================================
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::store::{self, KeyValueStore, StoreError};
+use super::ApiError;
+
+/// Default window in which a just-rotated refresh token is still honoured.
+pub const DEFAULT_GRACE: Duration = Duration::from_secs(10);
+
+/// How long a repeat waits for the first rotation's tokens, and how often it looks.
+const REPEAT_WAIT: Duration = Duration::from_secs(2);
+const REPEAT_POLL: Duration = Duration::from_millis(20);
+
+/// Tokens issued by a rotation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotatedTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Lifetime of `access_token` in seconds (`TokenPair::expires_in`).
+    #[serde(default)]
+    pub expires_in: i64,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum RotationError {
+    /// The token was already rotated and the grace window has passed.
+    #[error("refresh token already used")]
+    Reused,
+    /// The token's family has reached `MAX_REFRESH_ROTATIONS`.
+    #[error("refresh rotation limit reached")]
+    LimitReached,
+}
+
+/// Tracks retired refresh tokens and rotations per token family.
+#[derive(Debug)]
+pub struct RefreshRotations {
+    kv: Arc<dyn KeyValueStore>,
+    grace: Duration,
+    max_rotations: Option<u32>,
+}
+
+impl RefreshRotations {
+    pub fn new(kv: Arc<dyn KeyValueStore>, grace: Duration) -> Self {
+        Self {
+            kv,
+            grace,
+            max_rotations: None,
+        }
+    }
+
+    /// Refuse refreshes once a family has rotated `max` times (`None`: unlimited).
+    pub fn with_max_rotations(mut self, max: Option<u32>) -> Self {
+        self.max_rotations = max;
+        self
+    }
+
+    /// Read the grace window from `REFRESH_GRACE_SECONDS` (0 disables it)
+    /// and the cap from `MAX_REFRESH_ROTATIONS` (unset or 0: unlimited).
+    pub fn from_env(kv: Arc<dyn KeyValueStore>) -> Self {
+        let grace = env::var("REFRESH_GRACE_SECONDS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_GRACE);
+        let max_rotations = env::var("MAX_REFRESH_ROTATIONS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .filter(|max| *max > 0);
+        Self::new(kv, grace).with_max_rotations(max_rotations)
+    }
+
+    /// Rotate the refresh token `jti` of `family` (expiring at `exp`, Unix
+    /// seconds) at time `now` (from the app clock).
+    ///
+    /// The first call runs `issue` and remembers its tokens. Repeat calls
+    /// within the grace window return those same tokens; later ones fail
+    /// with `RotationError::Reused`. Only the caller that claims `jti` runs
+    /// `issue`, so concurrent callers (on any replica) can't both rotate the
+    /// same token. Once `family` has rotated `max_rotations` times, fails
+    /// with `RotationError::LimitReached` instead of issuing.
+    pub async fn rotate<F>(
+        &self,
+        jti: &str,
+        family: &str,
+        exp: i64,
+        now: DateTime<Utc>,
+        issue: F,
+    ) -> Result<RotatedTokens, ApiError>
+    where
+        F: FnOnce() -> Result<RotatedTokens, ApiError>,
+    {
+        // The claim is useless once the token has expired anyway
+        let remaining = u64::try_from(exp.saturating_sub(now.timestamp())).unwrap_or(0).max(1);
+        let key = retired_key(jti);
+        let retired_at = now.timestamp_millis().to_string();
+        let claimed = store::call(&self.kv, move |kv| {
+            kv.set_if_absent(&key, &retired_at, Duration::from_secs(remaining))
+        })
+        .await
+        .map_err(unavailable)?;
+        if !claimed {
+            return self.repeat(jti, now).await;
+        }
+
+        if let Some(max) = self.max_rotations {
+            let key = format!("refresh-rotations:{family}");
+            // Outlives every token of the family issued so far
+            let family_ttl = Duration::from_secs((super::jwt::REFRESH_TOKEN_DURATION_DAYS * 24 * 60 * 60) as u64);
+            let rotations = store::call(&self.kv, move |kv| kv.increment(&key, family_ttl))
+                .await
+                .map_err(unavailable)?;
+            if rotations > i64::from(max) {
+                return Err(ApiError::Unauthorized(RotationError::LimitReached.to_string()));
+            }
+        }
+
+        let issued = match issue() {
+            Ok(issued) => issued,
+            Err(err) => {
+                // Not rotated after all, so the token stays usable
+                let key = retired_key(jti);
+                if let Err(store_err) = store::call(&self.kv, move |kv| kv.delete(&key)).await {
+                    tracing::error!("Failed to release refresh token claim: {store_err}");
+                }
+                return Err(err);
+            }
+        };
+
+        if !self.grace.is_zero() {
+            let key = issued_key(jti);
+            let json = serde_json::to_string(&issued)
+                .map_err(|e| ApiError::internal("Failed to record rotation", e.to_string()))?;
+            let grace = self.grace;
+            store::call(&self.kv, move |kv| kv.set(&key, &json, grace))
+                .await
+                .map_err(unavailable)?;
+        }
+        Ok(issued)
+    }
+
+    /// Answer a repeat of an already rotated `jti`: the same tokens within
+    /// the grace window, `Reused` after it.
+    async fn repeat(&self, jti: &str, now: DateTime<Utc>) -> Result<RotatedTokens, ApiError> {
+        let reused = || ApiError::Unauthorized(RotationError::Reused.to_string());
+        let mut waited = Duration::ZERO;
+        loop {
+            let (retired, issued) = (retired_key(jti), issued_key(jti));
+            let (retired_at, issued) = store::call(&self.kv, move |kv| Ok((kv.get(&retired)?, kv.get(&issued)?)))
+                .await
+                .map_err(unavailable)?;
+
+            let Some(retired_at) = retired_at.and_then(|at| at.parse::<i64>().ok()) else {
+                return Err(reused());
+            };
+            let elapsed = Duration::from_millis(u64::try_from(now.timestamp_millis() - retired_at).unwrap_or(0));
+            if elapsed > self.grace {
+                return Err(reused());
+            }
+            if let Some(tokens) = issued.and_then(|json| serde_json::from_str(&json).ok()) {
+                return Ok(tokens);
+            }
+            // Claimed but not yet issued: the first refresh is still running
+            if waited >= REPEAT_WAIT {
+                return Err(reused());
+            }
+            tokio::time::sleep(REPEAT_POLL).await;
+            waited += REPEAT_POLL;
+        }
+    }
+}
+
+fn retired_key(jti: &str) -> String {
+    format!("refresh-retired:{jti}")
+}
+
+fn issued_key(jti: &str) -> String {
+    format!("refresh-issued:{jti}")
+}
+
+fn unavailable(err: StoreError) -> ApiError {
+    tracing::error!("Refresh rotation store unavailable: {err}");
+    ApiError::ServiceUnavailable("Sessions temporarily unavailable".to_string())
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+    use crate::store::MemoryStore;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn tokens(n: usize) -> RotatedTokens {
+        RotatedTokens {
+            access_token: format!("access-{n}"),
+            refresh_token: format!("refresh-{n}"),
+            expires_in: 900,
+        }
+    }
+
+    fn rotations(grace: Duration) -> RefreshRotations {
+        RefreshRotations::new(Arc::new(MemoryStore::default()), grace)
+    }
+
+    #[tokio::test]
+    async fn test_repeat_within_grace_returns_same_tokens() {
+        let rotations = rotations(Duration::from_secs(10));
+        let issued = AtomicUsize::new(0);
+        let issue = || Ok(tokens(issued.fetch_add(1, Ordering::SeqCst)));
+
+        let now = Utc::now();
+        let first = rotations.rotate("jti-1", "fam-1", i64::MAX, now, issue).await.unwrap();
+        let second = rotations.rotate("jti-1", "fam-1", i64::MAX, now, issue).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(issued.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reuse_after_grace_is_rejected() {
+        let clock = MockClock::starting_now();
+        let rotations = rotations(Duration::from_secs(10));
+        rotations.rotate("jti-1", "fam-1", i64::MAX, clock.now(), || Ok(tokens(0))).await.unwrap();
+
+        clock.advance(chrono::Duration::seconds(10));
+        assert!(rotations.rotate("jti-1", "fam-1", i64::MAX, clock.now(), || Ok(tokens(1))).await.is_ok());
+
+        clock.advance(chrono::Duration::seconds(1));
+        let reused = rotations.rotate("jti-1", "fam-1", i64::MAX, clock.now(), || Ok(tokens(1))).await;
+        assert!(matches!(reused, Err(ApiError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_with_the_token() {
+        let clock = Arc::new(MockClock::starting_now());
+        let kv: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::new(clock.clone()));
+        let rotations = RefreshRotations::new(kv.clone(), Duration::ZERO);
+        rotations.rotate("old", "fam-old", clock.unix() + 100, clock.now(), || Ok(tokens(0))).await.unwrap();
+        assert!(kv.get(&retired_key("old")).unwrap().is_some());
+
+        clock.advance(chrono::Duration::seconds(100));
+        assert!(kv.get(&retired_key("old")).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_issue_leaves_token_usable() {
+        let rotations = rotations(Duration::ZERO);
+        let now = Utc::now();
+        let failed = rotations
+            .rotate("jti-1", "fam-1", i64::MAX, now, || Err(ApiError::InternalError("signing failed".to_string())))
+            .await;
+        assert!(failed.is_err());
+        assert!(rotations.rotate("jti-1", "fam-1", i64::MAX, now, || Ok(tokens(1))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replicas_sharing_a_store_detect_reuse() {
+        let kv: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::default());
+        let replica_a = RefreshRotations::new(kv.clone(), Duration::from_secs(10));
+        let replica_b = RefreshRotations::new(kv, Duration::from_secs(10));
+        let clock = MockClock::starting_now();
+
+        let first = replica_a.rotate("jti-1", "fam-1", i64::MAX, clock.now(), || Ok(tokens(1))).await.unwrap();
+        let repeat = replica_b.rotate("jti-1", "fam-1", i64::MAX, clock.now(), || Ok(tokens(2))).await.unwrap();
+        assert_eq!(first, repeat);
+
+        clock.advance(chrono::Duration::seconds(11));
+        let reused = replica_b.rotate("jti-1", "fam-1", i64::MAX, clock.now(), || Ok(tokens(3))).await;
+        assert!(matches!(reused, Err(ApiError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_family_refused_after_max_rotations() {
+        let rotations = rotations(Duration::ZERO).with_max_rotations(Some(2));
+        let now = Utc::now();
+        rotations.rotate("jti-1", "fam-1", i64::MAX, now, || Ok(tokens(1))).await.unwrap();
+        rotations.rotate("jti-2", "fam-1", i64::MAX, now, || Ok(tokens(2))).await.unwrap();
+
+        let capped = rotations.rotate("jti-3", "fam-1", i64::MAX, now, || Ok(tokens(3))).await;
+        assert!(matches!(capped, Err(ApiError::Unauthorized(msg)) if msg == "refresh rotation limit reached"));
+        // Other logins are counted separately
+        assert!(rotations.rotate("jti-9", "fam-2", i64::MAX, now, || Ok(tokens(9))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_grace_repeats_do_not_count_towards_cap() {
+        let rotations = rotations(Duration::from_secs(10)).with_max_rotations(Some(1));
+        let now = Utc::now();
+        let first = rotations.rotate("jti-1", "fam-1", i64::MAX, now, || Ok(tokens(1))).await.unwrap();
+        let repeat = rotations.rotate("jti-1", "fam-1", i64::MAX, now, || Ok(tokens(2))).await.unwrap();
+        assert_eq!(first, repeat);
+    }
+}
@@ -0,0 +1,174 @@
+// ==============================================================================
+// REQUEST ID / CORRELATION ID
+// ==============================================================================
+//
+// Gives every request an id and echoes it in `X-Request-Id`:
+//
+// - An incoming `X-Request-Id` (from a proxy or the client) is kept if it is
+//   1-128 characters of `[A-Za-z0-9._-]`; otherwise a UUID v4 is generated
+// - Logs emitted while handling the request carry it (`request_id` span field)
+// - The id is placed in the request extensions (`RequestId`)
+//
+// CORRELATION (`EMBED_CORRELATION_ID=true`):
+// The id is also embedded, as `correlation_id`, in auth audit events
+// (`AuthEventStore::record`) and webhook payloads (`jobs::webhook_event`),
+// so one identifier links a user action across logs, audit records and
+// downstream webhooks. Like `db_budget`, it is kept in a task-local for
+// code without access to the request; read it with `correlation_id()`.
+//
+// ==============================================================================
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::Instrument;
+
+use crate::AppState;
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest incoming request id that is kept as-is.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+/// The current request's id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Correlation id to embed in audit events and webhooks: the current
+/// request's id when `EMBED_CORRELATION_ID` is on, otherwise `None`.
+pub fn correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(Clone::clone).ok()
+}
+
+/// Assign the request id, echo it, and scope logs (and correlation) to it.
+pub async fn request_id_middleware(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_valid_request_id(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!("request", request_id = %id);
+    let mut response = if state.config.embed_correlation_id {
+        CORRELATION_ID.scope(id.clone(), next.run(request)).instrument(span).await
+    } else {
+        next.run(request).instrument(span).await
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(X_REQUEST_ID, value);
+    }
+    response
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    (1..=MAX_REQUEST_ID_LEN).contains(&id.len())
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'))
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::audit::AuthEventKind;
+    use crate::api::ip_rate_limit::IpRateLimiter;
+    use crate::config::AppConfig;
+    use crate::jobs::Job;
+    use crate::test_support::{bearer_request, TestApp};
+    use axum::http::StatusCode;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Import one user as an admin and return the correlation ids on its
+    /// `created` audit event and `user.created` webhook, plus the echoed
+    /// request id.
+    async fn import_user(embed: bool, request_id: Option<&str>) -> (Option<String>, Option<String>, String) {
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let config = AppConfig {
+            embed_correlation_id: embed,
+            webhook_url: Some("https://hooks.example/x".to_string()),
+            ..AppConfig::default()
+        };
+        let app = TestApp::builder().config(config).db_pool(pool).build().await;
+        let admin = crate::api::jwt::generate_access_token(app.clock.as_ref(), 1, "ops@example.com", &["admin".to_string()])
+            .unwrap();
+        let email = format!("correlated-{}@example.com", uuid::Uuid::new_v4());
+        let body = serde_json::json!({ "users": [{ "email": email, "password": "GoodPass123", "name": "Correlated" }] });
+
+        let mut request = bearer_request("POST", "/api/v1/admin/users/import", &admin, body);
+        if let Some(id) = request_id {
+            request.headers_mut().insert(X_REQUEST_ID, HeaderValue::from_str(id).unwrap());
+        }
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json);
+        let echoed = response.headers[&X_REQUEST_ID].to_str().unwrap().to_string();
+        let user_id = response.json["data"][0]["id"].as_i64().unwrap();
+
+        let (events, _) = app.state.auth_events.recent(user_id, 0, 1);
+        assert_eq!(events[0].kind, AuthEventKind::Created);
+        let audit_id = events[0].correlation_id.clone();
+        let Job::FireWebhook { payload, .. } = app.jobs().await[0].clone() else {
+            panic!("expected a webhook job");
+        };
+        assert_eq!(payload["event"], "user.created");
+        assert_eq!(payload["data"]["id"], user_id);
+        let webhook_id = payload["correlation_id"].as_str().map(str::to_string);
+        (audit_id, webhook_id, echoed)
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_audit_event_and_webhook_share_correlation_id() {
+        let (audit, webhook, echoed) = import_user(true, Some("req-123")).await;
+        assert_eq!(echoed, "req-123");
+        assert_eq!(audit.as_deref(), Some("req-123"));
+        assert_eq!(webhook.as_deref(), Some("req-123"));
+
+        // Generated when absent
+        let (audit, webhook, echoed) = import_user(true, None).await;
+        assert!(uuid::Uuid::parse_str(&echoed).is_ok());
+        assert_eq!(audit.as_deref(), Some(echoed.as_str()));
+        assert_eq!(webhook, audit);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_correlation_id_not_embedded_when_disabled() {
+        let (audit, webhook, echoed) = import_user(false, Some("req-123")).await;
+        assert_eq!(echoed, "req-123");
+        assert_eq!((audit, webhook), (None, None));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_responses_carry_request_id() {
+        let app = TestApp::builder()
+            .configure(|state| state.general_limiter = Arc::new(IpRateLimiter::new(Duration::from_secs(60), 1)))
+            .build()
+            .await;
+        app.get("/health/live").await;
+
+        let throttled = app.get("/health/live").await;
+        assert_eq!(throttled.status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(uuid::Uuid::parse_str(throttled.headers[&X_REQUEST_ID].to_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_unsafe_incoming_ids_are_replaced() {
+        assert!(is_valid_request_id("0f8c2a1e-7b3d.trace_1"));
+        for id in ["", "has space", "new\nline", "quote\"", &"x".repeat(129)] {
+            assert!(!is_valid_request_id(id), "{id:?}");
+        }
+    }
+}
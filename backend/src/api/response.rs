@@ -0,0 +1,148 @@
+// ==============================================================================
+// SUCCESS RESPONSE ENVELOPE
+// ==============================================================================
+//
+// Success counterpart to `ApiErrorBody`: every new endpoint returns
+//
+// ```json
+// { "data": { ... }, "meta": { "request_id": "...", "timestamp": "..." } }
+// ```
+//
+// so clients can unwrap responses uniformly. `meta` is omitted when empty.
+// Paginated listings add `meta.page = { limit, offset, total }`, where `limit`
+// is the limit actually applied (requests above the maximum are clamped).
+//
+// USAGE:
+// ```rust
+// async fn handler() -> ApiResponse<User> {
+//     ApiResponse::new(user).with_meta(ResponseMeta::now())
+// }
+// ```
+//
+// NOTE: Existing auth/health responses keep their current shape so deployed
+// mobile clients don't break.
+//
+// ==============================================================================
+
+use axum::response::{IntoResponse, Response};
+use super::json::ApiJson;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Standard `{ data, meta }` envelope for successful responses.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ApiResponse<T> {
+    pub data: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub meta: Option<ResponseMeta>,
+}
+
+/// Response metadata attached to an `ApiResponse`.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ResponseMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub request_id: Option<String>,
+    /// RFC 3339 timestamp of when the response was produced.
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub page: Option<PageMeta>,
+}
+
+/// Pagination details for list responses.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct PageMeta {
+    /// Effective page size (after clamping to the server maximum).
+    pub limit: usize,
+    pub offset: usize,
+    /// Total number of items available.
+    #[ts(type = "number")]
+    pub total: i64,
+}
+
+impl<T> ApiResponse<T> {
+    /// Wrap `data` without metadata.
+    pub fn new(data: T) -> Self {
+        Self { data, meta: None }
+    }
+
+    pub fn with_meta(mut self, meta: ResponseMeta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+}
+
+impl ResponseMeta {
+    /// Metadata stamped with the current time.
+    pub fn now() -> Self {
+        Self {
+            request_id: None,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            page: None,
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    pub fn with_page(mut self, page: PageMeta) -> Self {
+        self.page = Some(page);
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for ApiResponse<T> {
+    fn into_response(self) -> Response {
+        ApiJson(self).into_response()
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::users::domain::entities::User;
+
+    #[test]
+    fn test_user_response_envelope() {
+        let user = User {
+            id: 42,
+            email: "user@example.com".to_string(),
+            password_hash: "secret-hash".to_string(),
+            name: "Test User".to_string(),
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            must_change_password: false,
+            email_verified: true,
+            canonical_email: String::new(),
+        };
+
+        let response = ApiResponse::new(user).with_meta(ResponseMeta::now().with_request_id("req-1"));
+        let json = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(json["data"]["id"], 42);
+        assert_eq!(json["data"]["email"], "user@example.com");
+        assert!(json["data"].get("password_hash").is_none());
+        assert_eq!(json["meta"]["request_id"], "req-1");
+        assert!(json["meta"]["timestamp"].is_string());
+        assert_eq!(json.as_object().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_meta_omitted_when_absent() {
+        let json = serde_json::to_value(ApiResponse::new(1)).unwrap();
+        assert_eq!(json, serde_json::json!({ "data": 1 }));
+    }
+}
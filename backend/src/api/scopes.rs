@@ -0,0 +1,64 @@
+// ==============================================================================
+// TOKEN SCOPES
+// ==============================================================================
+//
+// Scoped tokens (`POST /api/v1/me/tokens`) let users hand a third party a
+// short-lived token that can do only what its `scopes` claim lists.
+//
+// DEFAULT DENY:
+// - `AuthUser` rejects scoped tokens with 403 on every endpoint...
+// - ...unless the route is wrapped in `require_scope(SCOPE)` and the token
+//   lists that scope. First-party (unscoped) tokens pass every guard.
+//
+// USAGE:
+// ```rust
+// .route("/me", get(me).layer(middleware::from_fn_with_state((state.clone(), PROFILE_READ), require_scope)))
+// ```
+//
+// ==============================================================================
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::AppState;
+use super::auth::extract_token_from_request;
+use super::jwt::validate_access_token_checked;
+use super::ApiError;
+
+/// Read the caller's profile (`GET /me`).
+pub const PROFILE_READ: &str = "profile:read";
+/// Read the caller's auth activity (`GET /me/activity`).
+pub const ACTIVITY_READ: &str = "activity:read";
+
+/// Scopes that may be requested when minting a token.
+pub const KNOWN_SCOPES: &[&str] = &[PROFILE_READ, ACTIVITY_READ];
+
+/// Left in the request extensions by `require_scope` once a scoped token
+/// has been checked, telling `AuthUser` to accept it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ScopeGranted;
+
+/// Route guard: scoped tokens must list `scope` (403 otherwise).
+///
+/// Requests without a valid token pass through so the handler's `AuthUser`
+/// produces the usual 401.
+pub async fn require_scope(
+    State((state, scope)): State<(AppState, &'static str)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let claims = match extract_token_from_request(request.headers()) {
+        Some(token) => validate_access_token_checked(&token, state.clock.as_ref(), &state.revocations).await.ok(),
+        None => None,
+    };
+
+    if let Some(claims) = claims.filter(|claims| claims.is_scoped()) {
+        if !claims.has_scope(scope) {
+            return ApiError::Forbidden(format!("Token lacks the {scope} scope")).into_response();
+        }
+        request.extensions_mut().insert(ScopeGranted);
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,51 @@
+// ==============================================================================
+// SECURITY PRIMITIVES
+// ==============================================================================
+//
+// Small helpers shared by anything that compares secrets (CSRF tokens,
+// session bindings, the introspection shared secret).
+//
+// RULE: never compare a secret with `==`. Plain string equality returns as
+// soon as a byte differs, so response timing leaks how much of a guess was
+// right. Values the client already holds don't need this.
+//
+// ==============================================================================
+
+/// Constant-time string comparison to prevent timing attacks.
+///
+/// Only the length is allowed to leak (it returns early on a mismatch).
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut result = 0u8;
+    for (x, y) in a.bytes().zip(b.bytes()) {
+        result |= x ^ y;
+    }
+    result == 0
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq_same() {
+        assert!(constant_time_eq("abc123", "abc123"));
+    }
+
+    #[test]
+    fn test_constant_time_eq_different() {
+        assert!(!constant_time_eq("abc123", "abc124"));
+    }
+
+    #[test]
+    fn test_constant_time_eq_different_length() {
+        assert!(!constant_time_eq("abc", "abcd"));
+    }
+}
@@ -0,0 +1,146 @@
+// ==============================================================================
+// SERVER-TIMING HEADER
+// ==============================================================================
+//
+// Adds a `Server-Timing` header so browser devtools show where a request's
+// time went:
+//
+//   Server-Timing: db;dur=12.4, total;dur=31.0
+//
+// - `db`    time spent in database calls (`record_db`, summed; omitted if none)
+// - `total` time from entering the middleware until the handler responded
+//
+// ENABLED (`SERVER_TIMING`): on by default in development, off in production
+// unless `SERVER_TIMING=true`. Timings reveal a little about internals, so
+// keep it off for public production traffic.
+//
+// The per-request `ServerTiming` is placed in the request extensions and in
+// a task-local, so code without access to the request (e.g. repository
+// helpers) can still record into it.
+//
+// ==============================================================================
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::AppState;
+
+const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+tokio::task_local! {
+    static CURRENT: Arc<ServerTiming>;
+}
+
+/// Timers collected while handling one request.
+#[derive(Debug, Default)]
+pub struct ServerTiming {
+    db_micros: AtomicU64,
+    db_calls: AtomicU64,
+}
+
+impl ServerTiming {
+    pub fn add_db(&self, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.db_micros.fetch_add(micros, Ordering::Relaxed);
+        self.db_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Header value, given the request's total duration.
+    fn header_value(&self, total: Duration) -> String {
+        let mut metrics = Vec::new();
+        if self.db_calls.load(Ordering::Relaxed) > 0 {
+            let db_ms = self.db_micros.load(Ordering::Relaxed) as f64 / 1000.0;
+            metrics.push(format!("db;dur={db_ms:.1}"));
+        }
+        metrics.push(format!("total;dur={:.1}", total.as_secs_f64() * 1000.0));
+        metrics.join(", ")
+    }
+}
+
+/// Record database time against the current request, if it is being timed.
+pub fn record_db(elapsed: Duration) {
+    let _ = CURRENT.try_with(|timing| timing.add_db(elapsed));
+}
+
+/// Time the request and add `Server-Timing` when enabled.
+pub async fn server_timing_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !state.config.server_timing {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let timing = Arc::new(ServerTiming::default());
+    request.extensions_mut().insert(timing.clone());
+
+    let mut response = CURRENT.scope(timing.clone(), next.run(request)).await;
+
+    if let Ok(value) = HeaderValue::from_str(&timing.header_value(started.elapsed())) {
+        response.headers_mut().append(SERVER_TIMING, value);
+    }
+    response
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    use crate::test_support::TestApp;
+
+    async fn test_app(server_timing: bool) -> TestApp {
+        let config = crate::config::AppConfig {
+            server_timing,
+            ..Default::default()
+        };
+        TestApp::builder()
+            .config(config)
+            .route(
+                "/db",
+                get(|| async {
+                    // Stands in for a repository call
+                    record_db(Duration::from_millis(3));
+                    "ok"
+                }),
+            )
+            .build()
+            .await
+    }
+
+    async fn timing_header(app: TestApp, uri: &str) -> Option<String> {
+        let response = app.get(uri).await;
+        response
+            .headers
+            .get(SERVER_TIMING)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_db_request_reports_db_metric() {
+        let header = timing_header(test_app(true).await, "/db").await.unwrap();
+        assert!(header.starts_with("db;dur=3.0, total;dur="), "{header}");
+    }
+
+    #[tokio::test]
+    async fn test_request_without_db_reports_total_only() {
+        let header = timing_header(test_app(true).await, "/health/live").await.unwrap();
+        assert!(header.starts_with("total;dur="), "{header}");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_sends_no_header() {
+        assert_eq!(timing_header(test_app(false).await, "/db").await, None);
+    }
+}
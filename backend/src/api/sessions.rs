@@ -0,0 +1,116 @@
+// ==============================================================================
+// ACTIVE SESSION REGISTRY
+// ==============================================================================
+//
+// Tokens are stateless, so "sessions" exist only as refresh token families:
+// a login starts a family and each refresh extends it. This registry keeps,
+// per user, every family whose newest refresh token hasn't expired yet, so
+// support can see how many sessions a user has (`GET /admin/users/{id}/security`).
+//
+// STORAGE:
+// One map per user in the shared `KeyValueStore` (`sessions:<user_id>`,
+// family -> Unix expiry of its newest refresh token), so counts are shared
+// across replicas and survive restarts with Redis.
+//
+// LIMITATIONS:
+// - Logout revokes the presented refresh token but doesn't remove its
+//   family here, so a logged-out session counts until that token expires
+//
+// ==============================================================================
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::store::{self, KeyValueStore, StoreError};
+use super::jwt::REFRESH_TOKEN_DURATION_DAYS;
+
+/// Lifetime of a refresh token, in seconds.
+const REFRESH_TOKEN_SECONDS: i64 = REFRESH_TOKEN_DURATION_DAYS * 24 * 60 * 60;
+
+/// Live refresh token families per user.
+#[derive(Debug)]
+pub struct SessionRegistry {
+    kv: Arc<dyn KeyValueStore>,
+}
+
+impl SessionRegistry {
+    pub fn new(kv: Arc<dyn KeyValueStore>) -> Self {
+        Self { kv }
+    }
+
+    /// Record that `family` issued a refresh token at `now` (Unix seconds,
+    /// from the app clock), and drop the user's expired families.
+    ///
+    /// Only feeds the admin view, so a store failure is logged, not returned.
+    pub async fn record(&self, user_id: i64, family: &str, now: i64) {
+        let family = family.to_string();
+        let recorded = store::call(&self.kv, move |kv| {
+            let key = key(user_id);
+            let ttl = Duration::from_secs(REFRESH_TOKEN_SECONDS as u64);
+            kv.map_insert(&key, &family, &(now + REFRESH_TOKEN_SECONDS).to_string(), ttl)?;
+
+            let expired: Vec<String> = kv
+                .map_entries(&key)?
+                .into_iter()
+                .filter(|(_, exp)| !is_live(exp, now))
+                .map(|(family, _)| family)
+                .collect();
+            kv.map_remove(&key, &expired)
+        })
+        .await;
+        if let Err(err) = recorded {
+            tracing::error!("Failed to record session: {err}");
+        }
+    }
+
+    /// Number of `user_id`'s families still valid at `now`.
+    pub async fn active(&self, user_id: i64, now: i64) -> Result<usize, StoreError> {
+        let families = store::call(&self.kv, move |kv| kv.map_entries(&key(user_id))).await?;
+        Ok(families.iter().filter(|(_, exp)| is_live(exp, now)).count())
+    }
+}
+
+fn key(user_id: i64) -> String {
+    format!("sessions:{user_id}")
+}
+
+fn is_live(exp: &str, now: i64) -> bool {
+    exp.parse::<i64>().is_ok_and(|exp| exp > now)
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[tokio::test]
+    async fn test_counts_live_families_per_user() {
+        let registry = SessionRegistry::new(Arc::new(MemoryStore::default()));
+        registry.record(1, "laptop", 0).await;
+        registry.record(1, "phone", 0).await;
+        // Refreshing extends a family rather than adding one
+        registry.record(1, "laptop", 100).await;
+        registry.record(2, "tablet", 0).await;
+
+        assert_eq!(registry.active(1, 100).await.unwrap(), 2);
+        assert_eq!(registry.active(1, REFRESH_TOKEN_SECONDS + 50).await.unwrap(), 1);
+        assert_eq!(registry.active(2, REFRESH_TOKEN_SECONDS + 50).await.unwrap(), 0);
+        assert_eq!(registry.active(3, 0).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_expired_families_are_dropped_on_record() {
+        let kv: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::default());
+        let registry = SessionRegistry::new(kv.clone());
+        registry.record(1, "old", 0).await;
+        registry.record(1, "new", REFRESH_TOKEN_SECONDS + 1).await;
+
+        let families = kv.map_entries(&key(1)).unwrap();
+        assert_eq!(families.len(), 1);
+        assert_eq!(families[0].0, "new");
+    }
+}
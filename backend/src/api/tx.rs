@@ -0,0 +1,238 @@
+// ==============================================================================
+// PER-REQUEST DATABASE TRANSACTION EXTRACTOR
+// ==============================================================================
+//
+// `Tx` checks out a pooled connection and opens a transaction before the
+// handler runs, so several database steps can be made atomic without
+// threading a connection through by hand.
+//
+// USAGE (or `Tx::begin(&state)` to open it later, e.g. after slow
+// non-database work like password hashing):
+// ```rust
+// async fn transfer(tx: Tx, ...) -> Result<..., ApiError> {
+//     let mut tx = tx;
+//     let result = async {
+//         tx.run(|conn| debit(conn, ...)).await?;
+//         tx.run(|conn| credit(conn, ...)).await
+//     }
+//     .await;
+//     tx.finish(result).await   // commit on Ok, roll back on Err
+// }
+// ```
+//
+// NOTES:
+// - Each `run` step executes on the blocking pool like `repository::run_db`
+//   (Diesel is synchronous) and is counted in `Server-Timing`
+// - A `Tx` dropped without `finish` (e.g. an early `?`) is rolled back
+// - Without a configured database the extractor rejects with 503
+//
+// ==============================================================================
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use diesel::connection::{AnsiTransactionManager, TransactionManager};
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use std::time::Instant;
+
+use super::db_budget::record_db_call;
+use super::server_timing::record_db;
+use super::ApiError;
+use crate::AppState;
+
+type PooledConn = PooledConnection<ConnectionManager<PgConnection>>;
+
+/// An open transaction on a pooled connection, committed or rolled back by `finish`.
+pub struct Tx {
+    /// `None` only while a step is running, or once finished.
+    conn: Option<PooledConn>,
+}
+
+impl FromRequestParts<AppState> for Tx {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        Tx::begin(state).await
+    }
+}
+
+impl Tx {
+    /// Check out a connection and begin the transaction (503 without a database).
+    pub async fn begin(state: &AppState) -> Result<Self, ApiError> {
+        let pool = state
+            .db_pool
+            .clone()
+            .ok_or_else(|| ApiError::ServiceUnavailable("Database unavailable".to_string()))?;
+
+        let conn = blocking(move || {
+            let mut conn = pool.get().map_err(|e| {
+                tracing::error!("Failed to get DB connection: {}", e);
+                ApiError::InternalError("Database connection failed".to_string())
+            })?;
+            AnsiTransactionManager::begin_transaction(&mut *conn).map_err(|e| {
+                tracing::error!("Failed to begin transaction: {}", e);
+                ApiError::InternalError("Database transaction failed".to_string())
+            })?;
+            Ok(conn)
+        })
+        .await?;
+
+        Ok(Tx { conn: Some(conn) })
+    }
+
+    /// Run one step of the transaction on the blocking pool.
+    pub async fn run<T, F>(&mut self, f: F) -> Result<T, ApiError>
+    where
+        F: FnOnce(&mut PgConnection) -> Result<T, ApiError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let mut conn = self.take_conn()?;
+        let (conn, result) = blocking(move || {
+            let result = f(&mut conn);
+            Ok((conn, result))
+        })
+        .await?;
+        self.conn = Some(conn);
+        result
+    }
+
+    /// Commit if `result` is `Ok`, roll back if it is an error; returns `result`.
+    ///
+    /// A failed commit turns success into an error; a failed rollback is
+    /// logged and the original error returned.
+    pub async fn finish<T>(mut self, result: Result<T, ApiError>) -> Result<T, ApiError> {
+        let mut conn = self.take_conn()?;
+        let commit = result.is_ok();
+        let outcome = blocking(move || {
+            if commit {
+                AnsiTransactionManager::commit_transaction(&mut *conn)
+            } else {
+                AnsiTransactionManager::rollback_transaction(&mut *conn)
+            }
+            .map_err(|e| {
+                tracing::error!("Failed to {} transaction: {}", if commit { "commit" } else { "roll back" }, e);
+                ApiError::InternalError("Database transaction failed".to_string())
+            })
+        })
+        .await;
+
+        match result {
+            Ok(value) => outcome.map(|()| value),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn take_conn(&mut self) -> Result<PooledConn, ApiError> {
+        self.conn
+            .take()
+            .ok_or_else(|| ApiError::InternalError("Database transaction already finished".to_string()))
+    }
+}
+
+impl Drop for Tx {
+    fn drop(&mut self) {
+        let Some(mut conn) = self.conn.take() else {
+            return;
+        };
+        // Roll back off the async thread. Without a runtime, dropping the
+        // connection mid-transaction makes the pool discard it, which
+        // rolls back on the server.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn_blocking(move || {
+                if let Err(e) = AnsiTransactionManager::rollback_transaction(&mut *conn) {
+                    tracing::error!("Failed to roll back abandoned transaction: {}", e);
+                }
+            });
+        }
+    }
+}
+
+/// Run blocking database work on the blocking pool, timing it.
+async fn blocking<T, F>(f: F) -> Result<T, ApiError>
+where
+    F: FnOnce() -> Result<T, ApiError> + Send + 'static,
+    T: Send + 'static,
+{
+    let started = Instant::now();
+    let result = tokio::task::spawn_blocking(f).await.map_err(|e| {
+        tracing::error!("Thread panic in database transaction: {}", e);
+        ApiError::InternalError("Database query panicked".to_string())
+    })?;
+    record_db(started.elapsed());
+    record_db_call();
+    result
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use diesel::RunQueryDsl;
+    use tower::ServiceExt;
+
+    /// Inserts the same key twice; the second insert violates the primary key.
+    async fn insert_twice(mut tx: Tx) -> Result<&'static str, ApiError> {
+        let result = async {
+            tx.run(|conn| insert(conn, 1)).await?;
+            tx.run(|conn| insert(conn, 1)).await?;
+            Ok("inserted")
+        }
+        .await;
+        tx.finish(result).await
+    }
+
+    fn insert(conn: &mut PgConnection, id: i32) -> Result<(), ApiError> {
+        diesel::sql_query(format!("INSERT INTO tx_test (id) VALUES ({id})"))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| ApiError::Conflict(e.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_without_database_rejects_with_503() {
+        let app = Router::new()
+            .route("/tx", post(insert_twice))
+            .with_state(AppState::new(Default::default(), None));
+        let request = Request::builder().method("POST").uri("/tx").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_failed_second_write_rolls_back_first() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+        // One connection, so the temp table is visible to the extractor's transaction
+        let pool = diesel::r2d2::Pool::builder()
+            .max_size(1)
+            .build(ConnectionManager::<PgConnection>::new(url))
+            .unwrap();
+        diesel::sql_query("CREATE TEMP TABLE tx_test (id INT PRIMARY KEY)")
+            .execute(&mut pool.get().unwrap())
+            .unwrap();
+
+        let app = Router::new()
+            .route("/tx", post(insert_twice))
+            .with_state(AppState::new(Default::default(), Some(pool.clone())));
+        let request = Request::builder().method("POST").uri("/tx").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        #[derive(diesel::QueryableByName)]
+        struct Count {
+            #[diesel(sql_type = diesel::sql_types::BigInt)]
+            n: i64,
+        }
+        let count = diesel::sql_query("SELECT COUNT(*) AS n FROM tx_test")
+            .get_result::<Count>(&mut pool.get().unwrap())
+            .unwrap();
+        assert_eq!(count.n, 0, "first insert should have been rolled back");
+    }
+}
@@ -0,0 +1,126 @@
+// ==============================================================================
+// UPLOAD CONTENT-TYPE GUARD
+// ==============================================================================
+//
+// Central allowlist check for upload endpoints, so every upload handler
+// rejects unexpected file types the same way before touching storage.
+//
+// USAGE:
+// ```rust
+// async fn upload_avatar(UploadContentType(mime): UploadContentType, body: Bytes) -> ... {
+//     // mime is an allowed type, e.g. "image/png"
+// }
+// ```
+//
+// RULES:
+// - Allowed types come from `ALLOWED_UPLOAD_TYPES` (default PNG, JPEG, WebP)
+// - Parameters (`; charset=...`) are ignored and matching is case-insensitive
+// - An entry like `image/*` allows every subtype
+// - Missing or disallowed types are a 415 in the `ApiError` envelope
+//
+// NOTE: this trusts the client's declared type. Storage code should still
+// sniff the bytes before serving them back.
+//
+// ==============================================================================
+
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderMap};
+
+use crate::AppState;
+use super::ApiError;
+
+/// The request's `Content-Type` essence (e.g. `image/png`), checked against
+/// the configured allowlist.
+#[allow(dead_code)] // Guard for upload endpoints; none exist yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadContentType(pub String);
+
+impl FromRequestParts<AppState> for UploadContentType {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        check_content_type(&parts.headers, &state.config.allowed_upload_types).map(UploadContentType)
+    }
+}
+
+/// Validate the `Content-Type` header against `allowed`, returning its essence.
+pub fn check_content_type(headers: &HeaderMap, allowed: &[String]) -> Result<String, ApiError> {
+    let essence = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| ApiError::UnsupportedMediaType("Content-Type is required".to_string()))?;
+
+    if allowed.iter().any(|pattern| type_matches(pattern, &essence)) {
+        Ok(essence)
+    } else {
+        tracing::warn!("Rejected upload with content type {:?}", essence);
+        Err(ApiError::UnsupportedMediaType(format!(
+            "Unsupported content type. Allowed: {}",
+            allowed.join(", ")
+        )))
+    }
+}
+
+fn type_matches(pattern: &str, essence: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(top_level) => essence
+            .split_once('/')
+            .is_some_and(|(kind, subtype)| kind == top_level && !subtype.is_empty()),
+        None => pattern == essence,
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderValue, StatusCode};
+    use axum::response::IntoResponse;
+    use crate::config::DEFAULT_ALLOWED_UPLOAD_TYPES;
+
+    fn allowed() -> Vec<String> {
+        DEFAULT_ALLOWED_UPLOAD_TYPES.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers
+    }
+
+    #[test]
+    fn test_accepts_allowed_type() {
+        assert_eq!(check_content_type(&headers("image/png"), &allowed()).unwrap(), "image/png");
+        assert_eq!(
+            check_content_type(&headers("Image/PNG; name=avatar"), &allowed()).unwrap(),
+            "image/png"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_disallowed_type_with_415() {
+        let err = check_content_type(&headers("application/x-msdownload"), &allowed()).unwrap_err();
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["error"].as_str().unwrap().starts_with("Unsupported content type"));
+
+        let missing = check_content_type(&HeaderMap::new(), &allowed()).unwrap_err();
+        assert!(matches!(missing, ApiError::UnsupportedMediaType(_)));
+    }
+
+    #[test]
+    fn test_wildcard_subtype() {
+        let allowed = vec!["image/*".to_string()];
+        assert!(check_content_type(&headers("image/gif"), &allowed).is_ok());
+        assert!(check_content_type(&headers("text/plain"), &allowed).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+// ==============================================================================
+// VERSION ENDPOINT
+// ==============================================================================
+//
+// GET /api/v1/version
+//
+// - Anonymous / regular users: only the semver
+// - Admins: also build commit, uptime and enabled feature flags
+//
+// Build internals are useful to operators but help attackers fingerprint
+// the deployment, so they are only returned to admins.
+//
+// ==============================================================================
+
+use axum::extract::State;
+use super::json::ApiJson;
+use serde::Serialize;
+
+use super::auth_user::AuthUser;
+use crate::AppState;
+
+/// Crate version baked in at compile time.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Git commit, if provided at build time via `GIT_COMMIT=... cargo build`.
+const BUILD_COMMIT: Option<&str> = option_env!("GIT_COMMIT");
+
+#[derive(Debug, Serialize)]
+pub struct VersionResponse {
+    pub version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+}
+
+pub async fn version(State(state): State<AppState>, user: Option<AuthUser>) -> ApiJson<VersionResponse> {
+    if !user.is_some_and(|u| u.is_admin()) {
+        return ApiJson(VersionResponse {
+            version: VERSION,
+            commit: None,
+            uptime_seconds: None,
+            features: None,
+        });
+    }
+
+    ApiJson(VersionResponse {
+        version: VERSION,
+        commit: Some(BUILD_COMMIT.unwrap_or("unknown")),
+        uptime_seconds: Some(state.started_at.elapsed().as_secs()),
+        features: Some(enabled_features(&state)),
+    })
+}
+
+/// Human-readable list of optional behaviours active in this process.
+fn enabled_features(state: &AppState) -> Vec<String> {
+    let mut features = vec![format!("token_format:{}", state.config.token_format.name())];
+
+    if state.csrf_store.is_some() {
+        features.push("csrf:stateful".to_string());
+    } else {
+        features.push("csrf:double_submit".to_string());
+    }
+
+    features.push(
+        match (&state.db_pool, state.config.database_required) {
+            (Some(_), _) => "database:enabled",
+            (None, true) => "database:missing",
+            (None, false) => "database:disabled",
+        }
+        .to_string(),
+    );
+
+    features
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::api::jwt::generate_token_pair;
+    use axum::{body::Body, http::{header, Request, StatusCode}, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn get_version(bearer: Option<String>) -> serde_json::Value {
+        let state = AppState::new(crate::config::AppConfig::default(), None);
+        let app = Router::new()
+            .route("/version", get(version))
+            .with_state(state);
+
+        let mut request = Request::builder().uri("/version");
+        if let Some(token) = bearer {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_version_is_minimal() {
+        let json = get_version(None).await;
+        assert_eq!(json["version"], VERSION);
+        assert_eq!(json.as_object().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_version_is_minimal() {
+        let pair = generate_token_pair(&SystemClock, 1, "user@example.com", &[]).unwrap();
+        let json = get_version(Some(pair.access_token)).await;
+        assert_eq!(json.as_object().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_admin_version_is_detailed() {
+        let pair = generate_token_pair(&SystemClock, 1, "admin@example.com", &["admin".to_string()]).unwrap();
+        let json = get_version(Some(pair.access_token)).await;
+        assert_eq!(json["version"], VERSION);
+        assert!(json["commit"].is_string());
+        assert!(json["uptime_seconds"].is_u64());
+        assert!(json["features"].as_array().unwrap().iter().any(|f| f == "database:disabled"));
+    }
+}
@@ -0,0 +1,141 @@
+// ==============================================================================
+// APPLICATION ROUTER
+// ==============================================================================
+//
+// The one place the HTTP surface is assembled: `main` serves `router(state)`
+// and `test_support::TestApp` builds the same thing, so tests exercise the
+// real routes and middleware stack.
+//
+// - `routes`: `/api/v1` (auth endpoints behind the per-IP auth governor,
+//   read-only mode), `/.well-known/jwks.json` and the health checks
+// - `with_layers`: the process-wide layers around those routes, in order
+//
+// ==============================================================================
+
+use axum::routing::{get, post};
+use axum::Router;
+use tower_governor::{governor::GovernorConfigBuilder, GovernorLayer};
+use tower_http::catch_panic::CatchPanicLayer;
+use tower_http::compression::CompressionLayer;
+use tower_http::normalize_path::NormalizePath;
+use tower_http::trace::TraceLayer;
+
+use crate::{api, config, AppState};
+
+/// Every route, before the process-wide layers.
+pub fn routes(state: &AppState) -> Router<AppState> {
+    // ==========================================================================
+    // RATE LIMITING CONFIGURATION
+    // ==========================================================================
+    //
+    // Two rate limiters:
+    // 1. General API: 50 req/sec, burst 100 (for normal endpoints)
+    // 2. Auth endpoints: 5 req/min, burst 10 (prevent brute force)
+    //
+    // Login is additionally limited per email inside the handler
+    // (see api::rate_limit) to slow distributed attacks on one account.
+    //
+    // The general limiter lives in AppState (see api::ip_rate_limit) so its
+    // bucket state can be reported via X-RateLimit-* and /api/v1/ratelimit.
+    //
+    // ==========================================================================
+
+    // Strict rate limiter for auth endpoints (prevent brute force)
+    let auth_governor = GovernorConfigBuilder::default()
+        .per_second(config::AUTH_RATE_LIMIT_PER_SECOND) // 1 request per second sustained
+        .burst_size(config::AUTH_RATE_LIMIT_BURST) // Allow burst of 5 attempts
+        .finish()
+        .expect("auth governor config");
+
+    // Auth routes with stricter rate limiting
+    let auth_routes = Router::new()
+        .route("/auth/login", post(api::login))
+        .route("/auth/logout", post(api::logout))
+        .route("/auth/refresh", post(api::refresh))
+        .layer(GovernorLayer::new(auth_governor));
+
+    Router::new()
+        .nest(
+            "/api/v1",
+            api::routes(state.clone())
+                .merge(auth_routes)
+                // Service-to-service; authenticated by a shared secret, not cookies
+                .route("/auth/introspect", post(api::introspect))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    api::read_only_middleware,
+                )), // READ_ONLY_MODE: writes get 503 (logout exempt)
+        )
+        .route("/.well-known/jwks.json", get(api::jwks))
+        .route("/health/live", get(api::live))
+        .route("/health/ready", get(api::ready))
+        .route("/health/migrations", get(api::migrations))
+}
+
+/// Wrap `routes` in the process-wide layers and bind `state`.
+///
+/// Fails if the CORS origins are unusable (see `AppConfig::cors_origins`).
+pub fn with_layers(routes: Router<AppState>, state: AppState) -> Result<NormalizePath<Router>, String> {
+    // ==========================================================================
+    // CORS CONFIGURATION FOR SECURE COOKIE-BASED AUTH
+    // ==========================================================================
+    //
+    // Origins are configured via ALLOWED_ORIGINS environment variable.
+    // In production, this MUST be set to your actual domain(s).
+    // In development, defaults to localhost origins.
+    //
+    // ==========================================================================
+    let allowed_origins = state.config.cors_origins()?;
+
+    // Methods, headers and credentials live in `api::cors`, which also
+    // serves them at GET /api/v1/debug/cors in development
+    let cors = api::cors::layer(allowed_origins);
+
+    let app = routes
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::header_size_middleware,
+        )) // Reject cookie-bombing / oversized headers (431)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::force_https_middleware,
+        )) // FORCE_HTTPS: 308 plain HTTP to https (health checks exempt)
+        .layer(CatchPanicLayer::custom(api::panic::handle_panic)) // Panics become a JSON 500
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::server_timing_middleware,
+        )) // Server-Timing: db / total (dev, or SERVER_TIMING=true)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::db_budget_middleware,
+        )) // Warn on requests over MAX_DB_CALLS_PER_REQUEST (N+1 detection)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::set_cookie_limit_middleware,
+        )) // Warn on responses with more than MAX_SET_COOKIES cookies
+        .layer(TraceLayer::new_for_http()) // Request/response logging
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::rate_limit_middleware,
+        )) // General per-IP limit + X-RateLimit-* headers
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::request_id_middleware,
+        )) // X-Request-Id (429s included); tags logs (and, if enabled, audit/webhooks) with it
+        .layer(cors)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::cors_reject_log_middleware,
+        )); // Debug-log origins CORS will block (RUST_LOG=cors=debug)
+    let app = api::overload::limit_concurrency(app, config::MAX_CONCURRENT_REQUESTS, state.config.shed_on_overload)
+        .layer(CompressionLayer::new().quality(state.config.compression_level))
+        .with_state(state);
+
+    // `/api/v1/version/` and `/api/v1/version` reach the same handler
+    Ok(api::normalize_trailing_slash(app))
+}
+
+/// The full application: `routes` inside `with_layers`.
+pub fn router(state: AppState) -> Result<NormalizePath<Router>, String> {
+    with_layers(routes(&state), state)
+}
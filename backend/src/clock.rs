@@ -0,0 +1,140 @@
+// ==============================================================================
+// CLOCK ABSTRACTION
+// ==============================================================================
+//
+// Time-dependent code (token issue/expiry, refresh rotation grace windows,
+// audit timestamps) reads the time through a `Clock` instead of calling
+// `Utc::now()` directly, so tests can control time precisely.
+//
+// USAGE:
+// - Handlers, extractors (`AuthUser`) and middleware use `state.clock` (a
+//   `SystemClock` unless a test swaps it)
+// - Tests use `MockClock` and `advance` it instead of sleeping
+//
+// ISSUE TIMES:
+// Token `iat` comes from `Clock::issued_at`, which never goes backwards: if
+// the wall clock jumps back (NTP correction, VM restore), new tokens keep
+// the last issued time instead of predating tokens already handed out.
+// Otherwise they could fall before a `revoke-before` cutoff set moments
+// earlier and be rejected as soon as they are issued.
+//
+// ==============================================================================
+
+use chrono::{DateTime, Duration, Utc};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+/// Source of the current time.
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current time as Unix seconds.
+    fn unix(&self) -> i64 {
+        self.now().timestamp()
+    }
+
+    /// Unix seconds for a new token's `iat`: the current time, but never
+    /// earlier than a value this clock returned before.
+    fn issued_at(&self) -> i64;
+}
+
+/// Unix seconds that only move forward, whatever the readings fed to it.
+#[derive(Debug, Default)]
+pub struct MonotonicSeconds(AtomicI64);
+
+impl MonotonicSeconds {
+    /// Record `now` and return the latest time seen so far.
+    pub fn observe(&self, now: i64) -> i64 {
+        self.0.fetch_max(now, Ordering::SeqCst).max(now)
+    }
+}
+
+/// Latest `iat` handed out by `SystemClock` in this process.
+static SYSTEM_ISSUED_AT: MonotonicSeconds = MonotonicSeconds(AtomicI64::new(0));
+
+/// The real wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn issued_at(&self) -> i64 {
+        SYSTEM_ISSUED_AT.observe(self.unix())
+    }
+}
+
+/// Manually driven clock for tests; only moves when told to.
+#[allow(dead_code)] // Test support
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+    issued_at: MonotonicSeconds,
+}
+
+#[allow(dead_code)] // Test support
+impl MockClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+            issued_at: MonotonicSeconds::default(),
+        }
+    }
+
+    /// Starts at the current wall-clock time.
+    pub fn starting_now() -> Self {
+        Self::new(Utc::now())
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    pub fn set(&self, to: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = to;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn issued_at(&self) -> i64 {
+        self.issued_at.observe(self.unix())
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::starting_now();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::minutes(5));
+        assert_eq!(clock.now(), start + Duration::minutes(5));
+        assert_eq!(clock.unix(), start.timestamp() + 300);
+    }
+
+    #[test]
+    fn test_issued_at_does_not_regress_when_clock_jumps_back() {
+        let clock = MockClock::starting_now();
+        let first = clock.issued_at();
+
+        clock.advance(Duration::hours(-1));
+        assert_eq!(clock.issued_at(), first);
+
+        clock.advance(Duration::hours(2));
+        assert_eq!(clock.issued_at(), first + 3600);
+    }
+}
@@ -1,6 +1,12 @@
-use diesel::pg::PgConnection;
-use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::pg::{Pg, PgConnection};
+use diesel::r2d2::{Builder, ConnectionManager, ManageConnection, Pool};
 use diesel::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use std::time::Duration;
+
+/// SQL migrations from `migrations/`, compiled into the binary.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
 /// Diesel connection pool type.
 ///
@@ -16,22 +22,19 @@ pub type DbPool = Pool<ConnectionManager<PgConnection>>;
 /// - DB_POOL_MAX_SIZE: Maximum connections (default: 20)
 /// - DB_POOL_MIN_IDLE: Minimum idle connections (default: 5)
 /// - DB_POOL_CONNECTION_TIMEOUT: Connection timeout in seconds (default: 30)
+/// - DB_POOL_MAX_LIFETIME_SECONDS / DB_POOL_IDLE_TIMEOUT_SECONDS: see `PoolLifetimes`
 ///
 /// FAILURE MODES:
 /// - Returns an error string suitable for a startup failure.
 pub fn create_pool(database_url: &str) -> Result<DbPool, String> {
     use std::env;
-    use std::time::Duration;
     
     let max_size = env::var("DB_POOL_MAX_SIZE")
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(20);
     
-    let min_idle = env::var("DB_POOL_MIN_IDLE")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(5);
+    let min_idle = pool_min_idle();
     
     let connection_timeout = env::var("DB_POOL_CONNECTION_TIMEOUT")
         .ok()
@@ -40,14 +43,106 @@ pub fn create_pool(database_url: &str) -> Result<DbPool, String> {
     
     let manager = ConnectionManager::<PgConnection>::new(database_url);
 
-    Pool::builder()
+    let builder = Pool::builder()
         .max_size(max_size)
         .min_idle(Some(min_idle))
-        .connection_timeout(Duration::from_secs(connection_timeout))
+        .connection_timeout(Duration::from_secs(connection_timeout));
+
+    PoolLifetimes::from_env()
+        .apply(builder)
         .build(manager)
         .map_err(|e| format!("failed to create database pool: {e}"))
 }
 
+/// How long pooled connections may live before being recycled.
+///
+/// PURPOSE:
+/// - Long-lived connections accumulate server-side state, and proxies or the
+///   database may silently drop idle ones, surfacing later as "connection
+///   reset by peer". Recycling them periodically avoids both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolLifetimes {
+    /// `DB_POOL_MAX_LIFETIME_SECONDS` (default: 1800); `None` never recycles.
+    pub max_lifetime: Option<Duration>,
+    /// `DB_POOL_IDLE_TIMEOUT_SECONDS` (default: 600); `None` keeps idle connections.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl PoolLifetimes {
+    pub fn from_env() -> Self {
+        Self::parse(
+            std::env::var("DB_POOL_MAX_LIFETIME_SECONDS").ok().as_deref(),
+            std::env::var("DB_POOL_IDLE_TIMEOUT_SECONDS").ok().as_deref(),
+        )
+    }
+
+    /// Parse both settings; `0` disables one, unparsable values use the default.
+    pub fn parse(max_lifetime: Option<&str>, idle_timeout: Option<&str>) -> Self {
+        fn seconds(value: Option<&str>, default: u64) -> Option<Duration> {
+            let secs = value.and_then(|v| v.trim().parse().ok()).unwrap_or(default);
+            // r2d2 panics on a zero duration
+            (secs > 0).then(|| Duration::from_secs(secs))
+        }
+
+        Self {
+            max_lifetime: seconds(max_lifetime, 30 * 60),
+            idle_timeout: seconds(idle_timeout, 10 * 60),
+        }
+    }
+
+    /// Configure `builder` with these lifetimes.
+    pub fn apply<M: ManageConnection>(self, builder: Builder<M>) -> Builder<M> {
+        builder.max_lifetime(self.max_lifetime).idle_timeout(self.idle_timeout)
+    }
+}
+
+/// Minimum idle connections, from `DB_POOL_MIN_IDLE` (default: 5).
+pub fn pool_min_idle() -> u32 {
+    std::env::var("DB_POOL_MIN_IDLE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5)
+}
+
+/// Proactively open `count` connections and verify each with `ping`.
+///
+/// PURPOSE:
+/// - r2d2 fills `min_idle` lazily in the background, so the first requests
+///   after boot can pay connection setup latency. Warming trades a slower
+///   boot for fast first requests. Enabled with `DB_WARM_POOL=true`.
+///
+/// All connections are held at once so the pool must open distinct ones;
+/// they return to the pool as idle connections when this function exits.
+///
+/// IMPORTANT:
+/// - This is a blocking operation.
+/// - Call it from `spawn_blocking` in async contexts.
+pub fn warm_pool<M, F>(pool: &Pool<M>, count: u32, ping: F) -> Result<usize, String>
+where
+    M: ManageConnection,
+    F: Fn(&mut M::Connection) -> Result<(), String>,
+{
+    let mut held = Vec::with_capacity(count as usize);
+
+    for _ in 0..count.min(pool.max_size()) {
+        let mut conn = pool
+            .get()
+            .map_err(|e| format!("failed to warm database pool: {e}"))?;
+        ping(&mut conn)?;
+        held.push(conn);
+    }
+
+    Ok(held.len())
+}
+
+/// `SELECT 1` on a single connection (used to verify warmed connections).
+pub fn ping_connection(conn: &mut PgConnection) -> Result<(), String> {
+    diesel::sql_query("SELECT 1")
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| format!("database warm-up query failed: {e}"))
+}
+
 /// Lightweight database liveness probe.
 ///
 /// PURPOSE:
@@ -56,14 +151,223 @@ pub fn create_pool(database_url: &str) -> Result<DbPool, String> {
 /// IMPORTANT:
 /// - This is a blocking operation.
 /// - Call it from `spawn_blocking` in async contexts.
-pub fn check_database(pool: &DbPool) -> Result<(), String> {
-    let mut conn = pool
-        .get()
-        .map_err(|e| format!("failed to get database connection from pool: {e}"))?;
+pub fn check_database(pool: &DbPool) -> Result<(), DbCheckError> {
+    let mut conn = pool.get().map_err(|e| DbCheckError::Acquire(e.to_string()))?;
 
     diesel::sql_query("SELECT 1")
         .execute(&mut conn)
-        .map_err(|e| format!("database health query failed: {e}"))?;
+        .map_err(|e| DbCheckError::Query(e.to_string()))?;
 
     Ok(())
 }
+
+/// Which step of `check_database` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbCheckError {
+    /// No connection could be checked out of the pool in time.
+    Acquire(String),
+    /// A connection was available but `SELECT 1` failed.
+    Query(String),
+}
+
+impl std::fmt::Display for DbCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Acquire(e) => write!(f, "failed to get database connection from pool: {e}"),
+            Self::Query(e) => write!(f, "database health query failed: {e}"),
+        }
+    }
+}
+
+/// Applied vs pending schema migrations, as reported by `/health/migrations`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MigrationStatus {
+    /// Versions recorded in `__diesel_schema_migrations`.
+    pub applied: Vec<String>,
+    /// Embedded migrations not yet applied, by name, oldest first.
+    pub pending: Vec<String>,
+}
+
+/// Compare the embedded migrations with what the database has applied.
+///
+/// IMPORTANT:
+/// - This is a blocking operation.
+/// - Call it from `spawn_blocking` in async contexts.
+pub fn migration_status(pool: &DbPool) -> Result<MigrationStatus, String> {
+    let mut conn = pool
+        .get()
+        .map_err(|e| format!("failed to get database connection from pool: {e}"))?;
+    migration_status_of(&mut conn)
+}
+
+fn migration_status_of<H: MigrationHarness<Pg>>(harness: &mut H) -> Result<MigrationStatus, String> {
+    let pending = harness
+        .pending_migrations(MIGRATIONS)
+        .map_err(|e| format!("failed to list pending migrations: {e}"))?
+        .iter()
+        .map(|m| m.name().to_string())
+        .collect();
+    let applied = harness
+        .applied_migrations()
+        .map_err(|e| format!("failed to list applied migrations: {e}"))?
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+
+    Ok(MigrationStatus { applied, pending })
+}
+
+/// Apply all pending embedded migrations (startup, with `RUN_MIGRATIONS=true`).
+///
+/// Returns the names of the migrations that were applied.
+///
+/// IMPORTANT:
+/// - This is a blocking operation.
+/// - Call it from `spawn_blocking` in async contexts.
+pub fn run_pending_migrations(pool: &DbPool) -> Result<Vec<String>, String> {
+    let mut conn = pool
+        .get()
+        .map_err(|e| format!("failed to get database connection from pool: {e}"))?;
+    conn.run_pending_migrations(MIGRATIONS)
+        .map(|versions| versions.iter().map(|v| v.to_string()).collect())
+        .map_err(|e| format!("failed to run migrations: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Fake connection manager counting how many connections were opened.
+    #[derive(Debug, Clone, Default)]
+    struct CountingManager {
+        opened: Arc<AtomicUsize>,
+    }
+
+    impl ManageConnection for CountingManager {
+        type Connection = ();
+        type Error = std::io::Error;
+
+        fn connect(&self) -> Result<(), Self::Error> {
+            self.opened.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn is_valid(&self, _conn: &mut ()) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn has_broken(&self, _conn: &mut ()) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_warm_pool_opens_and_pings_requested_connections() {
+        let manager = CountingManager::default();
+        let opened = manager.opened.clone();
+        let pool = Pool::builder()
+            .max_size(10)
+            .min_idle(Some(0))
+            .build(manager)
+            .unwrap();
+
+        let pings = AtomicUsize::new(0);
+        let warmed = warm_pool(&pool, 4, |_| {
+            pings.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(warmed, 4);
+        assert_eq!(pings.load(Ordering::SeqCst), 4);
+        assert_eq!(opened.load(Ordering::SeqCst), 4);
+        assert_eq!(pool.state().idle_connections, 4);
+    }
+
+    #[test]
+    fn test_pool_lifetimes_are_passed_to_builder() {
+        let lifetimes = PoolLifetimes::parse(Some("120"), Some("45"));
+        assert_eq!(lifetimes.max_lifetime, Some(Duration::from_secs(120)));
+        assert_eq!(lifetimes.idle_timeout, Some(Duration::from_secs(45)));
+
+        // r2d2's builder has no getters; its Debug output shows the settings
+        let builder = lifetimes.apply(Pool::<CountingManager>::builder());
+        let debug = format!("{builder:?}");
+        assert!(debug.contains("max_lifetime: Some(120s)"), "{debug}");
+        assert!(debug.contains("idle_timeout: Some(45s)"), "{debug}");
+    }
+
+    #[test]
+    fn test_pool_lifetimes_defaults_and_zero_disables() {
+        let defaults = PoolLifetimes::parse(None, Some("not a number"));
+        assert_eq!(defaults.max_lifetime, Some(Duration::from_secs(1800)));
+        assert_eq!(defaults.idle_timeout, Some(Duration::from_secs(600)));
+
+        let disabled = PoolLifetimes::parse(Some("0"), Some("0"));
+        assert_eq!(disabled.max_lifetime, None);
+        assert_eq!(disabled.idle_timeout, None);
+        // Must not panic on the zero values
+        let _ = disabled.apply(Pool::<CountingManager>::builder());
+    }
+
+    /// Harness over a fake database that has applied exactly `applied`.
+    struct FakeSchema {
+        applied: Vec<diesel::migration::MigrationVersion<'static>>,
+    }
+
+    impl MigrationHarness<Pg> for FakeSchema {
+        fn run_migration(
+            &mut self,
+            _: &dyn diesel::migration::Migration<Pg>,
+        ) -> diesel::migration::Result<diesel::migration::MigrationVersion<'static>> {
+            unreachable!("status checks never run migrations")
+        }
+
+        fn revert_migration(
+            &mut self,
+            _: &dyn diesel::migration::Migration<Pg>,
+        ) -> diesel::migration::Result<diesel::migration::MigrationVersion<'static>> {
+            unreachable!("status checks never revert migrations")
+        }
+
+        fn applied_migrations(
+            &mut self,
+        ) -> diesel::migration::Result<Vec<diesel::migration::MigrationVersion<'static>>> {
+            Ok(self.applied.iter().map(|v| v.as_owned()).collect())
+        }
+    }
+
+    #[test]
+    fn test_migration_status_reports_missing_migration_as_pending() {
+        use diesel::migration::MigrationSource;
+
+        // Pending migrations come in version order, which needn't be name order
+        let mut embedded = MigrationSource::<Pg>::migrations(&MIGRATIONS).unwrap();
+        embedded.sort_by_key(|m| m.name().version().as_owned());
+        let names: Vec<String> = embedded.iter().map(|m| m.name().to_string()).collect();
+        assert!(names.iter().any(|n| n.ends_with("create_users")), "{names:?}");
+
+        let status = migration_status_of(&mut FakeSchema { applied: Vec::new() }).unwrap();
+        assert_eq!(status.pending, names);
+        assert!(status.applied.is_empty());
+
+        let applied = embedded.iter().map(|m| m.name().version().as_owned()).collect();
+        let status = migration_status_of(&mut FakeSchema { applied }).unwrap();
+        assert!(status.pending.is_empty());
+        assert_eq!(status.applied.len(), names.len());
+    }
+
+    #[test]
+    fn test_warm_pool_fails_when_ping_fails() {
+        let pool = Pool::builder()
+            .max_size(2)
+            .min_idle(Some(0))
+            .build(CountingManager::default())
+            .unwrap();
+
+        let result = warm_pool(&pool, 2, |_| Err("boom".to_string()));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,586 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::api::audit::AuthEvent;
+use crate::api::auth_user::AuthUser;
+use crate::api::etag;
+use crate::api::json::ApiJson;
+use crate::api::jwt::{self, Claims};
+use crate::api::scopes::KNOWN_SCOPES;
+use crate::api::response::ApiResponse;
+use crate::api::ApiError;
+use crate::features::users::domain::entities::User;
+use crate::features::users::infrastructure::repository;
+use crate::AppState;
+
+/// GET /me - the authenticated user's profile.
+///
+/// Emits an `ETag` derived from `updated_at`, so clients polling their
+/// profile get `304 Not Modified` until it actually changes.
+pub async fn me(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let user = load_user(&state, auth.user_id).await?;
+    Ok(etag::conditional(&headers, user_etag(&user), ApiResponse::new(user)))
+}
+
+/// GET /users/{id} - a user's profile (password hash excluded).
+///
+/// Users may read only their own record; admins may read any. The access
+/// check runs before the lookup, so a 404 never reveals to a non-admin
+/// whether another id exists.
+pub async fn get_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+) -> Result<ApiResponse<User>, ApiError> {
+    if auth.user_id != user_id && !auth.is_admin() {
+        return Err(ApiError::Forbidden("You can only view your own account".to_string()));
+    }
+
+    let user = load_user(&state, user_id).await?;
+    if auth.user_id != user_id {
+        tracing::info!(target: "audit", admin_id = auth.user_id, user_id, "Admin viewed user record");
+    }
+    Ok(ApiResponse::new(user))
+}
+
+/// Fetch a user, sharing the query with concurrent reads of the same id
+/// when `DB_READ_COALESCING` is on (`state.user_reads`).
+async fn load_user(state: &AppState, user_id: i64) -> Result<User, ApiError> {
+    let pool = state
+        .db_pool
+        .clone()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Database unavailable".to_string()))?;
+
+    match &state.user_reads {
+        Some(reads) => reads.run(user_id, || repository::get_user_by_id(pool, user_id)).await,
+        None => repository::get_user_by_id(pool, user_id).await,
+    }
+}
+
+/// Default and maximum page size for `/me/activity`.
+const DEFAULT_ACTIVITY_LIMIT: usize = 20;
+const MAX_ACTIVITY_LIMIT: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityPage {
+    pub events: Vec<AuthEvent>,
+    pub offset: usize,
+    pub limit: usize,
+    pub total: usize,
+}
+
+/// GET /me/activity - the authenticated user's recent auth events.
+///
+/// Newest first, paginated with `?offset=&limit=` (limit clamped to 1..=100).
+pub async fn me_activity(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<ActivityQuery>,
+) -> ApiResponse<ActivityPage> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ACTIVITY_LIMIT)
+        .clamp(1, MAX_ACTIVITY_LIMIT);
+    let (events, total) = state.auth_events.recent(auth.user_id, query.offset, limit);
+
+    ApiResponse::new(ActivityPage {
+        events,
+        offset: query.offset,
+        limit,
+        total,
+    })
+}
+
+/// Events included in a data export (everything retained).
+const EXPORT_ACTIVITY_LIMIT: usize = 1000;
+
+/// Everything the service holds about a user (password hash excluded).
+#[derive(Debug, Serialize)]
+pub struct UserExport {
+    pub exported_at: DateTime<Utc>,
+    pub profile: User,
+    /// Sessions are not stored server-side; this is the session making the request.
+    pub sessions: Vec<SessionInfo>,
+    pub activity: Vec<AuthEvent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub issued_at: i64,
+    pub expires_at: i64,
+    pub roles: Vec<String>,
+}
+
+/// GET /me/export - download the authenticated user's data as JSON.
+///
+/// Limited per account (`EXPORTS_PER_EMAIL_PER_MINUTE`) since it is far more
+/// expensive than a profile read.
+pub async fn me_export(State(state): State<AppState>, auth: AuthUser) -> Result<Response, ApiError> {
+    if let Err(retry_after) = state.export_limiter.check(&auth.email, state.clock.now()).await {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+            ApiJson(serde_json::json!({ "error": "Too many export requests. Please try again later" })),
+        )
+            .into_response());
+    }
+
+    let pool = state
+        .db_pool
+        .clone()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Database unavailable".to_string()))?;
+    let profile = repository::get_user_by_id(pool, auth.user_id).await?;
+    let (activity, _) = state.auth_events.recent(auth.user_id, 0, EXPORT_ACTIVITY_LIMIT);
+
+    Ok(export_response(build_export(profile, &auth.claims, activity, state.clock.now())))
+}
+
+fn build_export(profile: User, claims: &Claims, activity: Vec<AuthEvent>, exported_at: DateTime<Utc>) -> UserExport {
+    UserExport {
+        exported_at,
+        sessions: vec![SessionInfo {
+            issued_at: claims.iat,
+            expires_at: claims.exp,
+            roles: claims.roles.clone(),
+        }],
+        profile,
+        activity,
+    }
+}
+
+/// Serve `export` as a JSON file download; never cached.
+fn export_response(export: UserExport) -> Response {
+    let disposition = format!("attachment; filename=\"user-{}-export.json\"", export.profile.id);
+    (
+        [
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        ApiJson(export),
+    )
+        .into_response()
+}
+
+/// Lifetime of a scoped token when `expires_in` is omitted, in seconds.
+const DEFAULT_SCOPED_TOKEN_SECONDS: i64 = 15 * 60;
+/// Longest lifetime a scoped token may be minted with, in seconds.
+const MAX_SCOPED_TOKEN_SECONDS: i64 = 60 * 60;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateTokenRequest {
+    pub scopes: Vec<String>,
+    /// Requested lifetime in seconds; capped at `MAX_SCOPED_TOKEN_SECONDS`.
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScopedToken {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    pub scopes: Vec<String>,
+}
+
+/// POST /me/tokens - mint a short-lived token limited to `scopes`.
+///
+/// Requires a first-party token (`AuthUser` rejects scoped ones), so a
+/// scoped token can't mint itself a wider or longer-lived one. The minted
+/// token carries no roles and cannot be refreshed.
+pub async fn create_scoped_token(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ApiJson(request): ApiJson<CreateTokenRequest>,
+) -> Result<ApiResponse<ScopedToken>, ApiError> {
+    let mut scopes = request.scopes;
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(ApiError::BadRequest("At least one scope is required".to_string()));
+    }
+    if let Some(unknown) = scopes.iter().find(|s| !KNOWN_SCOPES.contains(&s.as_str())) {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown scope {unknown:?}; expected one of {}",
+            KNOWN_SCOPES.join(", ")
+        )));
+    }
+
+    let expires_in = request.expires_in.unwrap_or(DEFAULT_SCOPED_TOKEN_SECONDS);
+    if expires_in <= 0 {
+        return Err(ApiError::BadRequest("expires_in must be positive".to_string()));
+    }
+    let expires_in = expires_in.min(MAX_SCOPED_TOKEN_SECONDS);
+
+    let access_token = jwt::generate_scoped_token(
+        state.clock.as_ref(),
+        auth.user_id,
+        &auth.email,
+        &scopes,
+        chrono::Duration::seconds(expires_in),
+    )?;
+    tracing::info!(
+        target: "audit",
+        user_id = auth.user_id,
+        scopes = %scopes.join(" "),
+        expires_in,
+        "Scoped token minted"
+    );
+
+    Ok(ApiResponse::new(ScopedToken {
+        access_token,
+        token_type: "Bearer",
+        expires_in,
+        scopes,
+    }))
+}
+
+/// Entity tag for a user: changes whenever the row is updated.
+pub fn user_etag(user: &User) -> String {
+    etag::entity_tag(&format!("u{}-{}", user.id, user.updated_at.timestamp_micros()))
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::api::audit::AuthEventKind;
+    use crate::config::AppConfig;
+    use crate::test_support::{bearer_request, TestApp};
+    use axum::body::Body;
+    use axum::http::{HeaderValue, Request};
+    use chrono::{Duration, Utc};
+    use tower::ServiceExt;
+
+    fn user() -> User {
+        let now = Utc::now();
+        User {
+            id: 7,
+            email: "me@example.com".to_string(),
+            password_hash: String::new(),
+            name: "Me".to_string(),
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+            must_change_password: false,
+            email_verified: true,
+            canonical_email: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_first_fetch_returns_200_with_etag_then_304() {
+        let user = user();
+        let tag = user_etag(&user);
+
+        let first = etag::conditional(&HeaderMap::new(), tag.clone(), ApiResponse::new(user.clone()));
+        assert_eq!(first.status(), StatusCode::OK);
+        let sent = first.headers()[header::ETAG].clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, sent);
+        let second = etag::conditional(&headers, tag, ApiResponse::new(user));
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn test_etag_changes_when_user_updated() {
+        let before = user();
+        let mut after = before.clone();
+        after.updated_at += Duration::seconds(1);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(&user_etag(&before)).unwrap());
+        let response = etag::conditional(&headers, user_etag(&after), ApiResponse::new(after));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_activity_lists_own_events_newest_first() {
+        let state = AppState::new(AppConfig::default(), None);
+        let ip = "198.51.100.7".parse().ok();
+        state.auth_events.record(7, AuthEventKind::Login, Utc::now(), ip, Some("Mozilla/5.0 Firefox/120.0"));
+        state.auth_events.record(8, AuthEventKind::Login, Utc::now(), ip, None);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        state.auth_events.record(7, AuthEventKind::Refresh, Utc::now(), ip, None);
+
+        let token = crate::api::jwt::generate_access_token(&SystemClock, 7, "me@example.com", &[]).unwrap();
+        let app = crate::features::users::api::routes(state.clone()).with_state(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/me/activity?limit=10")
+                    .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let kinds: Vec<_> = json["data"]["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["kind"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(kinds, ["refresh", "login"]);
+        assert_eq!(json["data"]["total"], 2);
+        assert_eq!(json["data"]["limit"], 10);
+        assert!(!body.windows(12).any(|w| w == b"198.51.100.7"));
+    }
+
+    #[tokio::test]
+    async fn test_export_contains_profile_and_is_an_attachment() {
+        let user = user();
+        let claims = Claims::new_access(user.id, &user.email, &SystemClock);
+        let state = AppState::new(AppConfig::default(), None);
+        state.auth_events.record(user.id, AuthEventKind::Login, Utc::now(), None, None);
+        let (activity, _) = state.auth_events.recent(user.id, 0, 10);
+
+        let response = export_response(build_export(user, &claims, activity, Utc::now()));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"user-7-export.json\""
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["profile"]["email"], "me@example.com");
+        assert!(json["profile"].get("password_hash").is_none());
+        assert_eq!(json["sessions"].as_array().unwrap().len(), 1);
+        assert_eq!(json["activity"][0]["kind"], "login");
+    }
+
+    #[tokio::test]
+    async fn test_export_is_rate_limited_per_account() {
+        let token = crate::api::jwt::generate_access_token(&SystemClock, 7, "me@example.com", &[]).unwrap();
+        let state = AppState::new(AppConfig::default(), None);
+        let app = crate::features::users::api::routes(state.clone()).with_state(state);
+        let export = || {
+            Request::builder()
+                .uri("/me/export")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // First call passes the limiter (then fails without a database)
+        let first = app.clone().oneshot(export()).await.unwrap();
+        assert_eq!(first.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let second = app.oneshot(export()).await.unwrap();
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(second.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    async fn mint(app: &axum::Router, bearer: &str, body: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/me/tokens")
+                    .header(header::AUTHORIZATION, format!("Bearer {bearer}"))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    async fn get_status(app: &axum::Router, uri: &str, bearer: &str) -> StatusCode {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header(header::AUTHORIZATION, format!("Bearer {bearer}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_scoped_token_reaches_only_allowed_endpoints() {
+        let full = crate::api::jwt::generate_access_token(&SystemClock, 7, "me@example.com", &[]).unwrap();
+        let state = AppState::new(AppConfig::default(), None);
+        let app = crate::features::users::api::routes(state.clone()).with_state(state);
+
+        let (status, json) = mint(&app, &full, r#"{"scopes":["activity:read"],"expires_in":86400}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["data"]["expires_in"], MAX_SCOPED_TOKEN_SECONDS);
+        let scoped = json["data"]["access_token"].as_str().unwrap().to_string();
+
+        assert_eq!(get_status(&app, "/me/activity", &scoped).await, StatusCode::OK);
+        // Guarded by a scope it lacks
+        assert_eq!(get_status(&app, "/me", &scoped).await, StatusCode::FORBIDDEN);
+        // Not open to scoped tokens at all
+        assert_eq!(get_status(&app, "/me/export", &scoped).await, StatusCode::FORBIDDEN);
+        // Can't mint further tokens
+        let (status, _) = mint(&app, &scoped, r#"{"scopes":["activity:read"]}"#).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    async fn get_user_status(app: &TestApp, id: i64, bearer: &str) -> StatusCode {
+        let request = bearer_request("GET", &format!("/api/v1/users/{id}"), bearer, serde_json::Value::Null);
+        app.send(request).await.status
+    }
+
+    #[tokio::test]
+    async fn test_reading_another_user_is_forbidden() {
+        let app = TestApp::builder().build().await;
+        let token = crate::api::jwt::generate_access_token(app.clock.as_ref(), 7, "me@example.com", &[]).unwrap();
+
+        assert_eq!(get_user_status(&app, 8, &token).await, StatusCode::FORBIDDEN);
+        // Own record and admins get past the access check (then need a database)
+        assert_eq!(get_user_status(&app, 7, &token).await, StatusCode::SERVICE_UNAVAILABLE);
+        let admin = crate::api::jwt::generate_access_token(app.clock.as_ref(), 1, "ops@example.com", &["admin".to_string()])
+            .unwrap();
+        assert_eq!(get_user_status(&app, 8, &admin).await, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_get_user_access_control() {
+        use crate::schema::users;
+        use diesel::prelude::*;
+
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let insert = |email: String| -> i64 {
+            diesel::insert_into(users::table)
+                .values((
+                    users::email.eq(&email),
+                    users::canonical_email.eq(&email),
+                    users::password_hash.eq("x"),
+                    users::name.eq("Someone"),
+                ))
+                .returning(users::id)
+                .get_result(&mut pool.get().unwrap())
+                .unwrap()
+        };
+        let me = insert(format!("me-{}@example.com", uuid::Uuid::new_v4()));
+        let other = insert(format!("other-{}@example.com", uuid::Uuid::new_v4()));
+
+        let app = TestApp::builder().db_pool(pool).build().await;
+        let token = crate::api::jwt::generate_access_token(app.clock.as_ref(), me, "me@example.com", &[]).unwrap();
+        let admin = crate::api::jwt::generate_access_token(app.clock.as_ref(), 1, "ops@example.com", &["admin".to_string()])
+            .unwrap();
+
+        let response = app
+            .send(bearer_request("GET", &format!("/api/v1/users/{me}"), &token, serde_json::Value::Null))
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.json["data"]["id"], me);
+        assert!(response.json["data"].get("password_hash").is_none());
+
+        assert_eq!(get_user_status(&app, other, &token).await, StatusCode::FORBIDDEN);
+        assert_eq!(get_user_status(&app, other, &admin).await, StatusCode::OK);
+        assert_eq!(get_user_status(&app, 0, &admin).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_concurrent_user_reads_share_one_query() {
+        use crate::api::db_budget::DbCallBudget;
+        use crate::schema::users;
+        use axum::{routing::get, Extension};
+        use diesel::prelude::*;
+        use std::sync::Arc;
+
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let email = format!("coalesced-{}@example.com", uuid::Uuid::new_v4());
+        let id: i64 = diesel::insert_into(users::table)
+            .values((
+                users::email.eq(&email),
+                users::canonical_email.eq(&email),
+                users::password_hash.eq("x"),
+                users::name.eq("Coalesced"),
+            ))
+            .returning(users::id)
+            .get_result(&mut pool.get().unwrap())
+            .unwrap();
+
+        // Four concurrent `load_user` calls in one request; responds with the
+        // number of database calls the request made
+        let reads = get(
+            |State(state): State<AppState>, Path(id): Path<i64>, Extension(budget): Extension<Arc<DbCallBudget>>| async move {
+                let users =
+                    tokio::join!(load_user(&state, id), load_user(&state, id), load_user(&state, id), load_user(&state, id));
+                for user in [users.0, users.1, users.2, users.3] {
+                    assert_eq!(user.unwrap().id, id);
+                }
+                axum::Json(budget.calls())
+            },
+        );
+        for (coalesce, calls) in [(false, 4), (true, 1)] {
+            let config = AppConfig {
+                coalesce_db_reads: coalesce,
+                ..Default::default()
+            };
+            let app = TestApp::builder()
+                .config(config)
+                .db_pool(pool.clone())
+                .route("/reads/{id}", reads.clone())
+                .build()
+                .await;
+
+            // Hold the reads back until all four have started
+            let mut lock = pool.get().unwrap();
+            diesel::sql_query("BEGIN").execute(&mut lock).unwrap();
+            diesel::sql_query("LOCK TABLE users IN ACCESS EXCLUSIVE MODE").execute(&mut lock).unwrap();
+            let (response, _) = tokio::join!(
+                app.send(Request::builder().uri(format!("/reads/{id}")).body(Body::empty()).unwrap()),
+                async {
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    diesel::sql_query("COMMIT").execute(&mut lock).unwrap();
+                },
+            );
+            assert_eq!(response.status, StatusCode::OK);
+            assert_eq!(response.json, calls, "coalesce_db_reads={coalesce}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_minting_rejects_unknown_scopes() {
+        let full = crate::api::jwt::generate_access_token(&SystemClock, 7, "me@example.com", &[]).unwrap();
+        let state = AppState::new(AppConfig::default(), None);
+        let app = crate::features::users::api::routes(state.clone()).with_state(state);
+
+        let (status, json) = mint(&app, &full, r#"{"scopes":["admin:everything"]}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(json["error"].as_str().unwrap().contains("admin:everything"));
+
+        let (status, _) = mint(&app, &full, r#"{"scopes":[]}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,41 @@
+// ==============================================================================
+// USERS API
+// ==============================================================================
+//
+// HTTP layer for the users feature (API → Domain → Infrastructure).
+//
+// ROUTES (mounted under /api/v1):
+// - GET /me            Current user's profile (supports `If-None-Match` → 304)
+// - GET /me/activity   Recent login/refresh/logout events, newest first
+// - GET /me/export     Downloadable JSON of the user's data (GDPR portability)
+// - POST /me/tokens    Mint a short-lived scoped token for a third party
+// - GET /users/{id}    A user's profile: their own, or anyone's for admins
+//
+// `/me` and `/me/activity` also accept scoped tokens carrying their scope
+// (see `api::scopes`); everything else requires a first-party token.
+//
+// ==============================================================================
+
+pub mod handlers;
+
+use axum::middleware::from_fn_with_state;
+use axum::routing::{get, post};
+use axum::Router;
+
+use crate::api::scopes::{self, require_scope};
+use crate::AppState;
+
+pub fn routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/me",
+            get(handlers::me).layer(from_fn_with_state((state.clone(), scopes::PROFILE_READ), require_scope)),
+        )
+        .route(
+            "/me/activity",
+            get(handlers::me_activity).layer(from_fn_with_state((state, scopes::ACTIVITY_READ), require_scope)),
+        )
+        .route("/me/export", get(handlers::me_export))
+        .route("/me/tokens", post(handlers::create_scoped_token))
+        .route("/users/{id}", get(handlers::get_user))
+}
@@ -0,0 +1,99 @@
+use email_address::EmailAddress;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::entities::UserError;
+
+/// A syntactically valid, normalized email address.
+///
+/// The only way to build one is `Email::parse` (also used when deserializing),
+/// so anything typed `Email` has already been validated and can go straight
+/// to the database.
+///
+/// NORMALIZATION:
+/// - Surrounding whitespace is trimmed
+/// - The whole address is lowercased, so `User@Example.com` and
+///   `user@example.com` are one account
+/// - Provider alias rules (`canonical_email`) are NOT applied: the address
+///   stored is the one the user typed
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Email(String);
+
+impl Email {
+    pub fn parse(email: &str) -> Result<Self, UserError> {
+        let email = email.trim().to_lowercase();
+        if EmailAddress::is_valid(&email) {
+            Ok(Email(email))
+        } else {
+            Err(UserError::InvalidEmail)
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Email {
+    type Error = UserError;
+
+    fn try_from(email: String) -> Result<Self, Self::Error> {
+        Email::parse(&email)
+    }
+}
+
+impl From<Email> for String {
+    fn from(email: Email) -> Self {
+        email.0
+    }
+}
+
+impl AsRef<str> for Email {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accepts_valid_addresses() {
+        assert_eq!(Email::parse("user@example.com").unwrap().as_str(), "user@example.com");
+        assert!(Email::parse("first.last+tag@sub.example.co.uk").is_ok());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_addresses() {
+        for invalid in ["", "   ", "no-at-sign", "@example.com", "user@", "two@@example.com"] {
+            assert_eq!(Email::parse(invalid), Err(UserError::InvalidEmail), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_trims_and_lowercases() {
+        let email = Email::parse("  User.Name@Example.COM \n").unwrap();
+        assert_eq!(email.as_str(), "user.name@example.com");
+        assert_eq!(email, Email::parse("user.name@example.com").unwrap());
+    }
+
+    #[test]
+    fn test_deserialize_validates() {
+        let email: Email = serde_json::from_str(r#"" Me@Example.com""#).unwrap();
+        assert_eq!(email.to_string(), "me@example.com");
+        assert!(serde_json::from_str::<Email>(r#""not an email""#).is_err());
+        assert_eq!(serde_json::to_string(&email).unwrap(), r#""me@example.com""#);
+    }
+}
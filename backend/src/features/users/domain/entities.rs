@@ -3,6 +3,7 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use super::Email;
 use crate::schema::users;
 
 /// User entity - maps to the `users` database table.
@@ -28,6 +29,14 @@ pub struct User {
     pub created_at: DateTime<Utc>,
     #[ts(type = "string")]
     pub updated_at: DateTime<Utc>,
+    /// Set for admin-provisioned accounts until the user picks a password
+    pub must_change_password: bool,
+    /// Whether the user has confirmed they own `email`
+    pub email_verified: bool,
+    /// Uniqueness key for `email` (see `canonical_email`); never sent to clients
+    #[serde(skip)]
+    #[ts(skip)]
+    pub canonical_email: String,
 }
 
 #[allow(dead_code)]
@@ -42,7 +51,8 @@ pub struct UserResponse {
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct CreateUserRequest {
-    pub email: String,
+    #[ts(type = "string")]
+    pub email: Email,
     pub password: String,
     pub name: String,
 }
@@ -51,7 +61,8 @@ pub struct CreateUserRequest {
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct UpdateUserRequest {
-    pub email: Option<String>,
+    #[ts(type = "string | null")]
+    pub email: Option<Email>,
     pub name: Option<String>,
 }
 
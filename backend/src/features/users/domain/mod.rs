@@ -1,4 +1,6 @@
+mod email;
 pub mod entities;
 mod validation;
 
-pub use validation::validate_email;
+pub use email::Email;
+pub use validation::canonical_email;
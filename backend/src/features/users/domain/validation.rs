@@ -1,12 +1,75 @@
-use email_address::EmailAddress;
+/// How a mail provider treats variations of the local part.
+struct AliasRule {
+    domains: &'static [&'static str],
+    /// Domain all of `domains` canonicalize to.
+    canonical_domain: &'static str,
+    /// Dots in the local part are ignored (`a.b` == `ab`).
+    ignores_dots: bool,
+}
+
+/// Providers whose `+tag` sub-addressing is documented. Only these are
+/// rewritten: elsewhere `+` and `.` may be significant (and Yahoo, for one,
+/// uses `-` rather than `+` for its aliases).
+const ALIAS_RULES: &[AliasRule] = &[
+    AliasRule { domains: &["gmail.com", "googlemail.com"], canonical_domain: "gmail.com", ignores_dots: true },
+    AliasRule { domains: &["outlook.com", "hotmail.com", "live.com"], canonical_domain: "", ignores_dots: false },
+    AliasRule { domains: &["icloud.com", "me.com", "mac.com"], canonical_domain: "", ignores_dots: false },
+    AliasRule { domains: &["fastmail.com"], canonical_domain: "", ignores_dots: false },
+];
+
+/// Uniqueness key for `email` (`NORMALIZE_EMAIL_ALIASES`).
+///
+/// Always trimmed and lowercased. With `normalize_aliases`, known providers'
+/// aliases also collapse to one key (`a.b+x@gmail.com` -> `ab@gmail.com`) so one
+/// mailbox can't register many accounts. The original address is still what
+/// gets displayed and mailed; this key is stored alongside it in
+/// `users.canonical_email`, which is unique.
+pub fn canonical_email(email: &str, normalize_aliases: bool) -> String {
+    let email = email.trim().to_lowercase();
+    if !normalize_aliases {
+        return email;
+    }
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return email;
+    };
+    let Some(rule) = ALIAS_RULES.iter().find(|rule| rule.domains.contains(&domain)) else {
+        return email;
+    };
+
+    let mut local = local.split('+').next().unwrap_or(local).to_string();
+    if rule.ignores_dots {
+        local.retain(|c| c != '.');
+    }
+    let domain = if rule.canonical_domain.is_empty() { domain } else { rule.canonical_domain };
+    format!("{local}@{domain}")
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-use super::entities::UserError;
+    #[test]
+    fn test_gmail_aliases_collide_when_enabled() {
+        assert_eq!(canonical_email("a.b+x@gmail.com", true), canonical_email("ab@gmail.com", true));
+        assert_eq!(canonical_email("A.B@GoogleMail.com", true), "ab@gmail.com");
+    }
+
+    #[test]
+    fn test_aliases_kept_apart_when_disabled() {
+        assert_ne!(canonical_email("a.b+x@gmail.com", false), canonical_email("ab@gmail.com", false));
+        assert_eq!(canonical_email(" User@Example.com ", false), "user@example.com");
+    }
 
-#[allow(dead_code)] // Used by repository stubs - will be active when schema.rs exists
-pub fn validate_email(email: &str) -> Result<(), UserError> {
-    if EmailAddress::is_valid(email) {
-        Ok(())
-    } else {
-        Err(UserError::InvalidEmail)
+    #[test]
+    fn test_rules_apply_per_provider() {
+        // Outlook strips +tags but dots are significant
+        assert_eq!(canonical_email("a.b+news@outlook.com", true), "a.b@outlook.com");
+        // Unknown providers are left alone
+        assert_eq!(canonical_email("a.b+x@example.com", true), "a.b+x@example.com");
+        assert_eq!(canonical_email("a-b@yahoo.com", true), "a-b@yahoo.com");
     }
 }
@@ -20,11 +20,125 @@
 
 use crate::DbPool;
 use crate::features::users::domain::entities::{User, CreateUserRequest, UpdateUserRequest, UserError};
+use crate::features::users::domain::{canonical_email, Email};
 use crate::api::ApiError;
 use crate::api::password;
 use crate::schema::users;
 use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
 use chrono::Utc;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+// ==============================================================================
+// QUERY EXECUTION
+// ==============================================================================
+
+/// Default slow-query warning threshold.
+const DEFAULT_SLOW_QUERY_MS: u64 = 500;
+
+static SLOW_QUERY_THRESHOLD: OnceLock<Duration> = OnceLock::new();
+
+/// Queries taking longer than this are logged (`SLOW_QUERY_MS`, default 500).
+fn slow_query_threshold() -> Duration {
+    *SLOW_QUERY_THRESHOLD.get_or_init(|| {
+        Duration::from_millis(
+            std::env::var("SLOW_QUERY_MS")
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(DEFAULT_SLOW_QUERY_MS),
+        )
+    })
+}
+
+/// Run blocking database work on the blocking thread pool.
+///
+/// Timing covers the whole closure (including waiting for a pooled
+/// connection); anything over `SLOW_QUERY_MS` is logged with `operation`.
+/// Each call counts against the request's `MAX_DB_CALLS_PER_REQUEST` budget.
+async fn run_db<T, F>(operation: &'static str, f: F) -> Result<T, ApiError>
+where
+    F: FnOnce() -> Result<T, ApiError> + Send + 'static,
+    T: Send + 'static,
+{
+    run_db_with_threshold(operation, slow_query_threshold(), f).await
+}
+
+async fn run_db_with_threshold<T, F>(operation: &'static str, threshold: Duration, f: F) -> Result<T, ApiError>
+where
+    F: FnOnce() -> Result<T, ApiError> + Send + 'static,
+    T: Send + 'static,
+{
+    let started = Instant::now();
+    let result = tokio::task::spawn_blocking(f).await.map_err(|e| {
+        tracing::error!("Thread panic in database operation {}: {}", operation, e);
+        ApiError::InternalError("Database query panicked".to_string())
+    })?;
+
+    let elapsed = started.elapsed();
+    crate::api::server_timing::record_db(elapsed);
+    crate::api::db_budget::record_db_call();
+    if elapsed > threshold {
+        tracing::warn!(
+            operation,
+            elapsed_ms = elapsed.as_millis() as u64,
+            threshold_ms = threshold.as_millis() as u64,
+            "Slow database query"
+        );
+    }
+
+    result
+}
+
+/// Check out a pooled connection (blocking; call inside `run_db`).
+fn get_conn(pool: &DbPool) -> Result<PooledConnection<ConnectionManager<PgConnection>>, ApiError> {
+    pool.get().map_err(|e| {
+        let e = e.to_string();
+        if is_too_many_connections(&e) {
+            tracing::warn!("Database refused a new connection: {}", e);
+            return database_unavailable();
+        }
+        tracing::error!("Failed to get DB connection: {}", e);
+        ApiError::InternalError("Database connection failed".to_string())
+    })
+}
+
+/// Seconds clients should wait before retrying when the database is saturated.
+const DATABASE_BUSY_RETRY_AFTER_SECONDS: u64 = 5;
+
+/// Postgres refusing connections (SQLSTATE 53300 `too_many_connections`,
+/// including per-role/per-database limits and reserved superuser slots).
+///
+/// Diesel doesn't expose the SQLSTATE, so this matches the server's messages.
+fn is_too_many_connections(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("too many clients")
+        || message.contains("too many connections")
+        || message.contains("remaining connection slots are reserved")
+}
+
+/// 503 for a saturated database: transient, so clients are told to retry.
+fn database_unavailable() -> ApiError {
+    ApiError::ServiceUnavailableRetry {
+        message: "Database is busy, please retry".to_string(),
+        retry_after: Duration::from_secs(DATABASE_BUSY_RETRY_AFTER_SECONDS),
+    }
+}
+
+/// Map an unexpected Diesel error to an `ApiError`, logging it with `context`.
+///
+/// Connection exhaustion is transient and becomes a 503 with `Retry-After`;
+/// anything else is a 500 carrying the client-safe `message`.
+fn database_error(e: diesel::result::Error, context: &str, message: &str) -> ApiError {
+    if let diesel::result::Error::DatabaseError(_, info) = &e {
+        if is_too_many_connections(info.message()) {
+            tracing::warn!("{}: {}", context, e);
+            return database_unavailable();
+        }
+    }
+    tracing::error!("{}: {}", context, e);
+    ApiError::InternalError(message.to_string())
+}
 
 // ==============================================================================
 // USER REPOSITORY
@@ -62,12 +176,8 @@ pub async fn get_user_by_id(
     pool: DbPool,
     user_id: i64,
 ) -> Result<User, ApiError> {
-    tokio::task::spawn_blocking(move || {
-        let mut conn = pool.get()
-            .map_err(|e| {
-                tracing::error!("Failed to get DB connection: {}", e);
-                ApiError::InternalError("Database connection failed".to_string())
-            })?;
+    run_db("get_user_by_id", move || {
+        let mut conn = get_conn(&pool)?;
         
         users::table
             .find(user_id)
@@ -76,43 +186,35 @@ pub async fn get_user_by_id(
                 diesel::result::Error::NotFound => {
                     ApiError::NotFound(format!("User {} not found", user_id))
                 }
-                _ => {
-                    tracing::error!("Database query error: {}", e);
-                    ApiError::InternalError("Database query failed".to_string())
-                }
+                _ => database_error(e, "Database query error", "Database query failed"),
             })
     })
     .await
-    .map_err(|e| {
-        tracing::error!("Thread panic in database query: {}", e);
-        ApiError::InternalError("Database query panicked".to_string())
-    })?
 }
 
 /// Create new user
 ///
 /// PERFORMANCE FIX: Uses spawn_blocking for database insert.
+///
+/// `normalize_aliases` is `NORMALIZE_EMAIL_ALIASES`: an address whose
+/// `canonical_email` matches an existing account's is a conflict.
 pub async fn create_user(
     pool: DbPool,
     data: CreateUserRequest,
+    normalize_aliases: bool,
 ) -> Result<User, ApiError> {
-    // Validate email before hitting database
-    crate::features::users::domain::validate_email(&data.email)
-        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
-    
+    // `data.email` is an `Email`, so it was validated when deserialized
+
     // Hash password before database insert
     let password_hash = password::hash_password(&data.password)?;
     
-    tokio::task::spawn_blocking(move || {
-        let mut conn = pool.get()
-            .map_err(|e| {
-                tracing::error!("Failed to get DB connection: {}", e);
-                ApiError::InternalError("Database connection failed".to_string())
-            })?;
+    run_db("create_user", move || {
+        let mut conn = get_conn(&pool)?;
         
         diesel::insert_into(users::table)
             .values((
-                users::email.eq(&data.email),
+                users::email.eq(data.email.as_str()),
+                users::canonical_email.eq(canonical_email(data.email.as_str(), normalize_aliases)),
                 users::password_hash.eq(&password_hash),
                 users::name.eq(&data.name),
             ))
@@ -123,17 +225,86 @@ pub async fn create_user(
                 ) => {
                     ApiError::Conflict("Email already exists".to_string())
                 }
-                _ => {
-                    tracing::error!("Database insert error: {}", e);
-                    ApiError::InternalError("Database insert failed".to_string())
-                }
+                _ => database_error(e, "Database insert error", "Database insert failed"),
             })
     })
     .await
-    .map_err(|e| {
-        tracing::error!("Thread panic in database insert: {}", e);
-        ApiError::InternalError("Database insert panicked".to_string())
-    })?
+}
+
+/// Create many users at once (bulk import): one statement on `conn`, with
+/// `password_hashes` in the same order as `data`. Either all users are
+/// created or, e.g. on a duplicate email, none are. Duplicates are judged as
+/// in `create_user`. Callers run it inside an `api::tx::Tx`.
+pub fn insert_users(
+    conn: &mut PgConnection,
+    data: &[CreateUserRequest],
+    password_hashes: &[String],
+    normalize_aliases: bool,
+) -> Result<Vec<User>, ApiError> {
+    let rows: Vec<_> = data
+        .iter()
+        .zip(password_hashes)
+        .map(|(user, password_hash)| {
+            (
+                users::email.eq(user.email.as_str()),
+                users::canonical_email.eq(canonical_email(user.email.as_str(), normalize_aliases)),
+                users::password_hash.eq(password_hash),
+                users::name.eq(&user.name),
+            )
+        })
+        .collect();
+
+    diesel::insert_into(users::table)
+        .values(&rows)
+        .get_results::<User>(conn)
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation, _
+            ) => {
+                ApiError::Conflict("Email already exists".to_string())
+            }
+            _ => database_error(e, "Database insert error", "Database insert failed"),
+        })
+}
+
+/// Create many users, each independently (best-effort bulk import).
+///
+/// Returns a result per row, in input order: a weak password or a duplicate
+/// email fails only that row. Rows are inserted one statement at a time on
+/// one connection, so successful rows stay committed.
+pub async fn import_users_each(
+    pool: DbPool,
+    data: Vec<CreateUserRequest>,
+    normalize_aliases: bool,
+) -> Result<Vec<Result<User, ApiError>>, ApiError> {
+    let passwords = data.iter().map(|user| user.password.clone()).collect();
+    let password_hashes = password::hash_passwords_each(passwords).await?;
+
+    run_db("import_users_each", move || {
+        let mut conn = get_conn(&pool)?;
+        Ok(data
+            .iter()
+            .zip(password_hashes)
+            .map(|(user, password_hash)| {
+                let password_hash = password_hash?;
+                diesel::insert_into(users::table)
+                    .values((
+                        users::email.eq(user.email.as_str()),
+                        users::canonical_email.eq(canonical_email(user.email.as_str(), normalize_aliases)),
+                        users::password_hash.eq(&password_hash),
+                        users::name.eq(&user.name),
+                    ))
+                    .get_result::<User>(&mut conn)
+                    .map_err(|e| match e {
+                        diesel::result::Error::DatabaseError(
+                            diesel::result::DatabaseErrorKind::UniqueViolation, _
+                        ) => ApiError::Conflict("Email already exists".to_string()),
+                        _ => database_error(e, "Database insert error", "Database insert failed"),
+                    })
+            })
+            .collect())
+    })
+    .await
 }
 
 /// Update user
@@ -143,19 +314,10 @@ pub async fn update_user(
     pool: DbPool,
     user_id: i64,
     data: UpdateUserRequest,
+    normalize_aliases: bool,
 ) -> Result<User, ApiError> {
-    // Validate email if provided
-    if let Some(ref email) = data.email {
-        crate::features::users::domain::validate_email(email)
-            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
-    }
-    
-    tokio::task::spawn_blocking(move || {
-        let mut conn = pool.get()
-            .map_err(|e| {
-                tracing::error!("Failed to get DB connection: {}", e);
-                ApiError::InternalError("Database connection failed".to_string())
-            })?;
+    run_db("update_user", move || {
+        let mut conn = get_conn(&pool)?;
         
         let now = Utc::now();
         
@@ -166,7 +328,8 @@ pub async fn update_user(
         let updated_rows = if let (Some(email), Some(name)) = (&data.email, &data.name) {
             diesel::update(target)
                 .set((
-                    users::email.eq(email),
+                    users::email.eq(email.as_str()),
+                    users::canonical_email.eq(canonical_email(email.as_str(), normalize_aliases)),
                     users::name.eq(name),
                     users::updated_at.eq(now),
                 ))
@@ -174,7 +337,8 @@ pub async fn update_user(
         } else if let Some(email) = &data.email {
             diesel::update(target)
                 .set((
-                    users::email.eq(email),
+                    users::email.eq(email.as_str()),
+                    users::canonical_email.eq(canonical_email(email.as_str(), normalize_aliases)),
                     users::updated_at.eq(now),
                 ))
                 .execute(&mut conn)
@@ -189,9 +353,11 @@ pub async fn update_user(
             // No fields to update
             Ok(0)
         }
-        .map_err(|e| {
-            tracing::error!("Database update error: {}", e);
-            ApiError::InternalError("Database update failed".to_string())
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation, _
+            ) => ApiError::Conflict("Email already exists".to_string()),
+            _ => database_error(e, "Database update error", "Database update failed"),
         })?;
         
         if updated_rows == 0 {
@@ -202,16 +368,31 @@ pub async fn update_user(
         users::table
             .find(user_id)
             .first::<User>(&mut conn)
-            .map_err(|e| {
-                tracing::error!("Failed to fetch updated user: {}", e);
-                ApiError::InternalError("Database query failed".to_string())
-            })
+            .map_err(|e| database_error(e, "Failed to fetch updated user", "Database query failed"))
+    })
+    .await
+}
+
+/// Store a new password hash and clear `must_change_password`.
+pub async fn set_password(pool: DbPool, user_id: i64, password_hash: String) -> Result<(), ApiError> {
+    run_db("set_password", move || {
+        let mut conn = get_conn(&pool)?;
+
+        let updated_rows = diesel::update(users::table.find(user_id))
+            .set((
+                users::password_hash.eq(&password_hash),
+                users::must_change_password.eq(false),
+                users::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut conn)
+            .map_err(|e| database_error(e, "Database update error", "Database update failed"))?;
+
+        if updated_rows == 0 {
+            return Err(ApiError::NotFound(format!("User {} not found", user_id)));
+        }
+        Ok(())
     })
     .await
-    .map_err(|e| {
-        tracing::error!("Thread panic in database update: {}", e);
-        ApiError::InternalError("Database update panicked".to_string())
-    })?
 }
 
 /// Delete user (soft delete)
@@ -221,12 +402,8 @@ pub async fn delete_user(
     pool: DbPool,
     user_id: i64,
 ) -> Result<(), ApiError> {
-    tokio::task::spawn_blocking(move || {
-        let mut conn = pool.get()
-            .map_err(|e| {
-                tracing::error!("Failed to get DB connection: {}", e);
-                ApiError::InternalError("Database connection failed".to_string())
-            })?;
+    run_db("delete_user", move || {
+        let mut conn = get_conn(&pool)?;
         
         let now = Utc::now();
         
@@ -236,10 +413,7 @@ pub async fn delete_user(
                 users::updated_at.eq(now),
             ))
             .execute(&mut conn)
-            .map_err(|e| {
-                tracing::error!("Database delete error: {}", e);
-                ApiError::InternalError("Database delete failed".to_string())
-            })?;
+            .map_err(|e| database_error(e, "Database delete error", "Database delete failed"))?;
         
         if updated_rows == 0 {
             return Err(ApiError::NotFound(format!("User {} not found", user_id)));
@@ -248,43 +422,54 @@ pub async fn delete_user(
         Ok(())
     })
     .await
-    .map_err(|e| {
-        tracing::error!("Thread panic in database delete: {}", e);
-        ApiError::InternalError("Database delete panicked".to_string())
-    })?
+}
+
+/// List users ordered by id, plus the total count (admin listing)
+pub async fn list_users(
+    pool: DbPool,
+    offset: usize,
+    limit: usize,
+) -> Result<(Vec<User>, i64), ApiError> {
+    run_db("list_users", move || {
+        let mut conn = get_conn(&pool)?;
+
+        let total = users::table
+            .count()
+            .get_result::<i64>(&mut conn)
+            .map_err(|e| database_error(e, "Database count error", "Database query failed"))?;
+
+        let page = users::table
+            .order(users::id.asc())
+            .offset(offset as i64)
+            .limit(limit as i64)
+            .load::<User>(&mut conn)
+            .map_err(|e| database_error(e, "Database query error", "Database query failed"))?;
+
+        Ok((page, total))
+    })
+    .await
 }
 
 /// Get user by email (for authentication)
 pub async fn get_user_by_email(
     pool: DbPool,
-    email: String,
+    email: Email,
 ) -> Result<User, ApiError> {
-    tokio::task::spawn_blocking(move || {
-        let mut conn = pool.get()
-            .map_err(|e| {
-                tracing::error!("Failed to get DB connection: {}", e);
-                ApiError::InternalError("Database connection failed".to_string())
-            })?;
+    run_db("get_user_by_email", move || {
+        let mut conn = get_conn(&pool)?;
         
         users::table
-            .filter(users::email.eq(&email))
+            .filter(users::email.eq(email.as_str()))
             .filter(users::is_active.eq(true))
             .first::<User>(&mut conn)
             .map_err(|e| match e {
                 diesel::result::Error::NotFound => {
                     ApiError::NotFound("User not found".to_string())
                 }
-                _ => {
-                    tracing::error!("Database query error: {}", e);
-                    ApiError::InternalError("Database query failed".to_string())
-                }
+                _ => database_error(e, "Database query error", "Database query failed"),
             })
     })
     .await
-    .map_err(|e| {
-        tracing::error!("Thread panic in database query: {}", e);
-        ApiError::InternalError("Database query panicked".to_string())
-    })?
 }
 
 // ==============================================================================
@@ -337,10 +522,117 @@ pub async fn get_user_by_email(
 //
 // ==============================================================================
 
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
 #[cfg(test)]
 mod tests {
     #[allow(unused_imports)]
     use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Captures formatted log output for assertions.
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl LogBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_logs_warning_and_fast_does_not() {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let threshold = Duration::from_millis(50);
+        run_db_with_threshold("fast_op", threshold, || Ok(())).await.unwrap();
+        run_db_with_threshold("slow_op", threshold, || {
+            std::thread::sleep(Duration::from_millis(80));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let output = logs.contents();
+        assert!(output.contains("Slow database query"));
+        assert!(output.contains("slow_op"));
+        assert!(!output.contains("fast_op"));
+    }
+
+    #[test]
+    fn test_too_many_connections_maps_to_503_with_retry_after() {
+        use axum::response::IntoResponse;
+        use diesel::result::{DatabaseErrorKind, Error};
+
+        let refused = Error::DatabaseError(
+            DatabaseErrorKind::Unknown,
+            Box::new("FATAL: sorry, too many clients already".to_string()),
+        );
+        let response = database_error(refused, "Database query error", "Database query failed").into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[axum::http::header::RETRY_AFTER], "5");
+
+        // Other database errors stay 500s
+        let other = Error::DatabaseError(DatabaseErrorKind::Unknown, Box::new("syntax error".to_string()));
+        let response = database_error(other, "Database query error", "Database query failed").into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_detects_connection_limit_messages() {
+        assert!(is_too_many_connections("FATAL:  sorry, too many clients already"));
+        assert!(is_too_many_connections("too many connections for role \"app\""));
+        assert!(is_too_many_connections(
+            "timed out waiting for connection: FATAL: remaining connection slots are reserved for superuser"
+        ));
+        assert!(!is_too_many_connections("connection refused"));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL pointing at a disposable Postgres"]
+    async fn test_gmail_alias_conflicts_when_normalizing() {
+        let pool = crate::db::create_pool(&std::env::var("DATABASE_URL").expect("DATABASE_URL")).unwrap();
+        crate::db::run_pending_migrations(&pool).unwrap();
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let request = |email: String| CreateUserRequest {
+            email: Email::parse(&email).unwrap(),
+            password: "Mailbox1234".to_string(),
+            name: "Alias".to_string(),
+        };
+
+        let user = create_user(pool.clone(), request(format!("ab{id}@gmail.com")), true).await.unwrap();
+        assert_eq!(user.canonical_email, format!("ab{id}@gmail.com"));
+
+        let alias = format!("a.b{id}+x@gmail.com");
+        let err = create_user(pool.clone(), request(alias.clone()), true).await.unwrap_err();
+        assert!(matches!(err, ApiError::Conflict(_)), "{err:?}");
+        let result = import_users_each(pool.clone(), vec![request(alias.clone())], true).await.unwrap().remove(0);
+        assert!(matches!(result, Err(ApiError::Conflict(_))), "{result:?}");
+
+        // Without normalization the alias is its own account
+        let user = create_user(pool, request(alias.clone()), false).await.unwrap();
+        assert_eq!(user.email, alias);
+    }
 
     // NOTE: These are examples - actual tests require database setup
     
@@ -0,0 +1,85 @@
+// ==============================================================================
+// OUTBOUND HTTP CLIENT
+// ==============================================================================
+//
+// Integrations (breach-password checks, webhooks, mail APIs) share one
+// `reqwest::Client`, built at startup and kept in `state.http_client`.
+// Cloning it is cheap and clones share the connection pool, so handlers and
+// jobs reuse keep-alive connections instead of opening one per call.
+//
+// TIMEOUTS:
+// `HTTP_CLIENT_TIMEOUT_MS` bounds both connecting and the whole request
+// (headers and body). A slow or hung third party fails the call with a
+// timeout error rather than holding a request task or worker indefinitely.
+//
+// ==============================================================================
+
+use std::time::Duration;
+
+/// How long an idle pooled connection is kept before being closed.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// `User-Agent` sent with outbound requests.
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Build the shared client with `timeout` for connecting and for each request.
+pub fn build(timeout: Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| format!("failed to build HTTP client: {e}"))
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one request per connection, answering `204` after `delay`.
+    async fn mock_server(delay: Duration) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = socket.read(&mut buf).await;
+                    tokio::time::sleep(delay).await;
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                        .await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_times_out_slow_server() {
+        let addr = mock_server(Duration::from_secs(5)).await;
+        let client = build(Duration::from_millis(100)).unwrap();
+
+        let started = std::time::Instant::now();
+        let err = client.get(format!("http://{addr}/")).send().await.unwrap_err();
+        assert!(err.is_timeout(), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_fast_server_within_timeout() {
+        let addr = mock_server(Duration::ZERO).await;
+        let client = build(Duration::from_millis(1000)).unwrap();
+
+        let response = client.get(format!("http://{addr}/")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+    }
+}
@@ -0,0 +1,284 @@
+// ==============================================================================
+// IN-PROCESS JOB QUEUE
+// ==============================================================================
+//
+// Side effects that don't need to finish before the response is sent
+// (emails, webhooks, audit flushes) are enqueued here instead of awaited
+// inline, so they don't add latency to requests.
+//
+// DESIGN:
+// - Bounded `tokio::sync::mpsc` channel; `enqueue` never blocks and fails
+//   fast when the queue is full
+// - A single worker task spawned at startup processes jobs in order
+// - On shutdown the queue stops accepting jobs and the worker drains what
+//   is left, up to a timeout
+//
+// LIMITATIONS:
+// - Jobs live in memory only: a crash loses queued jobs. Anything that must
+//   not be lost belongs in a durable queue (e.g. a database table).
+// - Nothing buffers audit events or metrics behind the queue, so there is
+//   nothing to flush at shutdown beyond the drain; the auth event log
+//   (`api::audit`) is in memory and resets on restart by design.
+//
+// ==============================================================================
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Maximum number of queued (not yet processed) jobs.
+pub const JOB_QUEUE_CAPACITY: usize = 1024;
+
+/// How long shutdown waits for queued jobs to drain.
+pub const JOB_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deferred side effect.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Job {
+    SendEmail {
+        from: String,
+        to: String,
+        subject: String,
+        body: String,
+    },
+    FireWebhook {
+        url: String,
+        payload: serde_json::Value,
+    },
+    AuditFlush,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum EnqueueError {
+    #[error("job queue is full")]
+    Full,
+
+    #[error("job queue is shut down")]
+    Closed,
+}
+
+/// Cheaply cloneable handle used by handlers to enqueue jobs.
+#[derive(Debug, Clone)]
+pub struct JobQueue {
+    tx: mpsc::Sender<Job>,
+}
+
+impl JobQueue {
+    /// Enqueue `job` without waiting for it to run.
+    pub fn enqueue(&self, job: Job) -> Result<(), EnqueueError> {
+        self.tx.try_send(job).map_err(|err| match err {
+            mpsc::error::TrySendError::Full(_) => EnqueueError::Full,
+            mpsc::error::TrySendError::Closed(_) => EnqueueError::Closed,
+        })
+    }
+}
+
+/// Handle to the worker task, used to shut it down.
+#[derive(Debug)]
+pub struct JobWorker {
+    shutdown_tx: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl JobWorker {
+    /// Stop accepting jobs and drain the queue.
+    ///
+    /// Returns `false` if draining didn't finish within `timeout`; remaining
+    /// jobs are dropped.
+    pub async fn shutdown(self, timeout: Duration) -> bool {
+        let _ = self.shutdown_tx.send(());
+        let abort = self.handle.abort_handle();
+
+        match tokio::time::timeout(timeout, self.handle).await {
+            Ok(_) => true,
+            Err(_) => {
+                abort.abort();
+                false
+            }
+        }
+    }
+}
+
+/// Spawn the worker task and return the queue handle plus the worker.
+///
+/// MUST be called from within a Tokio runtime.
+pub fn spawn_worker<F, Fut>(capacity: usize, handler: F) -> (JobQueue, JobWorker)
+where
+    F: Fn(Job) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::channel(capacity);
+    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+
+    let handle = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                job = rx.recv() => match job {
+                    Some(job) => handler(job).await,
+                    None => return,
+                },
+                _ = &mut shutdown_rx => break,
+            }
+        }
+
+        // Reject new jobs, then process everything already queued
+        rx.close();
+        while let Some(job) = rx.recv().await {
+            handler(job).await;
+        }
+    });
+
+    (JobQueue { tx }, JobWorker { shutdown_tx, handle })
+}
+
+/// Subject of the security notice sent after a password change.
+pub const PASSWORD_CHANGED_SUBJECT: &str = "Your password was changed";
+
+/// Queue the "your password was changed" notice from `from` to `to`.
+///
+/// For password-changing flows, once the new hash is stored. Does nothing
+/// unless mail is configured (`from` is `MAIL_FROM`); a missing or full
+/// queue is logged rather than failing the password change.
+pub fn notify_password_changed(queue: Option<&JobQueue>, from: Option<&str>, to: &str) {
+    let Some(from) = from else {
+        return;
+    };
+    let Some(queue) = queue else {
+        tracing::warn!("Mail is configured but no job queue is running; password change notification dropped");
+        return;
+    };
+    let job = Job::SendEmail {
+        from: from.to_string(),
+        to: to.to_string(),
+        subject: PASSWORD_CHANGED_SUBJECT.to_string(),
+        body: "The password for your account was just changed. If this wasn't you, \
+               reset your password and contact support immediately."
+            .to_string(),
+    };
+    if let Err(err) = queue.enqueue(job) {
+        tracing::warn!("Failed to queue password change notification: {err}");
+    }
+}
+
+/// A `FireWebhook` job delivering `event` with `data` to `url`.
+///
+/// The payload is `{ "event", "data" }`, plus `correlation_id` (the current
+/// request's id) when `EMBED_CORRELATION_ID` is on.
+pub fn webhook_event(url: &str, event: &str, data: serde_json::Value) -> Job {
+    let mut payload = serde_json::json!({ "event": event, "data": data });
+    if let Some(id) = crate::api::request_id::correlation_id() {
+        payload["correlation_id"] = id.into();
+    }
+    Job::FireWebhook {
+        url: url.to_string(),
+        payload,
+    }
+}
+
+/// Default job handler.
+///
+/// There is no mailer or webhook client yet, so jobs are logged; plug real
+/// delivery in here as those integrations land.
+pub async fn run_job(job: Job) {
+    match job {
+        // Recipient deliberately not logged (PII)
+        Job::SendEmail { subject, .. } => {
+            tracing::info!(%subject, "Processing SendEmail job");
+        }
+        Job::FireWebhook { url, .. } => {
+            tracing::info!(%url, "Processing FireWebhook job");
+        }
+        Job::AuditFlush => {
+            tracing::info!("Processing AuditFlush job");
+        }
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn recording_worker(capacity: usize, delay: Duration) -> (JobQueue, JobWorker, Arc<Mutex<Vec<Job>>>) {
+        let processed = Arc::new(Mutex::new(Vec::new()));
+        let sink = processed.clone();
+        let (queue, worker) = spawn_worker(capacity, move |job| {
+            let sink = sink.clone();
+            async move {
+                tokio::time::sleep(delay).await;
+                sink.lock().unwrap().push(job);
+            }
+        });
+        (queue, worker, processed)
+    }
+
+    #[tokio::test]
+    async fn test_enqueued_jobs_are_processed() {
+        let (queue, worker, processed) = recording_worker(8, Duration::ZERO);
+
+        queue.enqueue(Job::AuditFlush).unwrap();
+        queue
+            .enqueue(Job::FireWebhook {
+                url: "https://hooks.example/x".to_string(),
+                payload: serde_json::json!({ "event": "test" }),
+            })
+            .unwrap();
+
+        for _ in 0..100 {
+            if processed.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(processed.lock().unwrap()[0], Job::AuditFlush);
+        assert_eq!(processed.lock().unwrap().len(), 2);
+
+        assert!(worker.shutdown(Duration::from_secs(1)).await);
+    }
+
+    #[tokio::test]
+    async fn test_password_change_queues_notification_email() {
+        let (queue, worker, processed) = recording_worker(8, Duration::ZERO);
+
+        notify_password_changed(Some(&queue), Some("security@example.com"), "user@example.com");
+        // Mail not configured: nothing is queued
+        notify_password_changed(Some(&queue), None, "nobody@example.com");
+
+        assert!(worker.shutdown(Duration::from_secs(1)).await);
+        let processed = processed.lock().unwrap();
+        assert_eq!(processed.len(), 1);
+        assert!(matches!(
+            &processed[0],
+            Job::SendEmail { from, to, subject, .. }
+                if from == "security@example.com" && to == "user@example.com" && subject == PASSWORD_CHANGED_SUBJECT
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queued_jobs() {
+        let (queue, worker, processed) = recording_worker(16, Duration::from_millis(10));
+
+        for _ in 0..5 {
+            queue.enqueue(Job::AuditFlush).unwrap();
+        }
+
+        assert!(worker.shutdown(Duration::from_secs(2)).await);
+        assert_eq!(processed.lock().unwrap().len(), 5);
+        assert_eq!(queue.enqueue(Job::AuditFlush), Err(EnqueueError::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_without_blocking() {
+        let (queue, worker, _) = recording_worker(1, Duration::from_millis(200));
+
+        let results: Vec<_> = (0..3).map(|_| queue.enqueue(Job::AuditFlush)).collect();
+        assert!(results.contains(&Err(EnqueueError::Full)));
+
+        worker.shutdown(Duration::from_millis(10)).await;
+    }
+}
@@ -0,0 +1,74 @@
+// ==============================================================================
+// TCP LISTENER
+// ==============================================================================
+//
+// Binds the server socket.
+//
+// - Default: bind exactly `config.addr()` (`BACKEND_HOST:BACKEND_PORT`)
+// - `DUAL_STACK=true`: bind `[::]:BACKEND_PORT` with `IPV6_V6ONLY` disabled,
+//   so one socket accepts both IPv6 and IPv4 (as v4-mapped addresses)
+//
+// Whether dual-stack is the OS default varies (Linux: usually yes, Windows/BSD:
+// no), so the option is set explicitly instead of relying on it.
+//
+// ==============================================================================
+
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{Ipv6Addr, SocketAddr};
+
+use crate::config::AppConfig;
+
+/// Pending connection backlog for the listening socket.
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Bind the server listener according to `config`.
+pub fn bind(config: &AppConfig) -> io::Result<tokio::net::TcpListener> {
+    if !config.dual_stack {
+        let listener = std::net::TcpListener::bind(config.addr())?;
+        listener.set_nonblocking(true)?;
+        return tokio::net::TcpListener::from_std(listener);
+    }
+
+    let socket = dual_stack_socket(config.port)?;
+    socket.listen(LISTEN_BACKLOG)?;
+    tokio::net::TcpListener::from_std(socket.into())
+}
+
+/// Non-blocking `[::]:port` socket accepting both IPv4 and IPv6 (not yet listening).
+fn dual_stack_socket(port: u16) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::IPV6, Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_only_v6(false)?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)).into())?;
+    Ok(socket)
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dual_stack_socket_accepts_ipv4() {
+        let socket = dual_stack_socket(0).unwrap();
+
+        assert!(!socket.only_v6().unwrap());
+        let addr = socket.local_addr().unwrap().as_socket().unwrap();
+        assert_eq!(addr.ip(), Ipv6Addr::UNSPECIFIED);
+    }
+
+    #[tokio::test]
+    async fn test_bind_uses_configured_address_without_dual_stack() {
+        let config = AppConfig {
+            port: 0,
+            ..Default::default()
+        };
+        let listener = bind(&config).unwrap();
+        assert_eq!(listener.local_addr().unwrap().ip(), config.host);
+    }
+}
@@ -20,25 +20,31 @@
 // ==============================================================================
 
 mod api;
+mod app;
+mod clock;
 mod config;
 mod db;
 mod features;
+mod http_client;
+mod jobs;
+mod listener;
 mod schema;
+mod self_test;
+mod single_flight;
+mod store;
+mod tls;
+#[cfg(test)]
+mod test_support;
 
 #[allow(unused_imports)] // Required for into_make_service_with_connect_info
 use axum::extract::ConnectInfo;
-use axum::http::{header, Method};
-use axum::routing::get;
-use axum::Router;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use config::AppConfig;
-use tower::limit::ConcurrencyLimitLayer;
-use tower_governor::{governor::GovernorConfigBuilder, GovernorLayer};
 use tracing::info;
 use tracing_subscriber::EnvFilter;
-use tower_http::compression::CompressionLayer;
-use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 
 pub type DbPool = db::DbPool;
 
@@ -46,13 +52,89 @@ pub type DbPool = db::DbPool;
 pub struct AppState {
     pub config: AppConfig,
     pub db_pool: Option<DbPool>,
+    /// Per-account login limiter (complements the per-IP auth governor).
+    pub login_limiter: Arc<api::rate_limit::EmailRateLimiter>,
+    /// Server-side CSRF token store; `Some` only when `CSRF_MODE=stateful`.
+    pub csrf_store: Option<Arc<api::csrf::CsrfStore>>,
+    /// Process start time (reported as uptime to admins).
+    pub started_at: Instant,
+    /// Short-lived cache of the `/health/ready` database check.
+    pub health_cache: Arc<api::HealthCache>,
+    /// Client IPs seen per refresh session (`REFRESH_IP_PINNING`).
+    pub refresh_ip_tracker: Arc<api::ip_pinning::RefreshIpTracker>,
+    /// Deferred side effects; `None` when no worker is running (e.g. tests).
+    pub jobs: Option<jobs::JobQueue>,
+    /// Recent login/refresh/logout events per user (`GET /me/activity`).
+    pub auth_events: Arc<api::audit::AuthEventStore>,
+    /// Per-account limiter for `GET /me/export`.
+    pub export_limiter: Arc<api::rate_limit::EmailRateLimiter>,
+    /// Retired refresh tokens (rotation + concurrent-refresh grace window).
+    pub refresh_rotations: Arc<api::refresh_rotation::RefreshRotations>,
+    /// Live refresh token families per user (admin security view).
+    pub sessions: Arc<api::sessions::SessionRegistry>,
+    /// Tokens revoked by `jti` (logout); shared through `kv_store` with a shared backend.
+    pub revocations: Arc<dyn api::jwt::RevocationStore>,
+    /// General per-IP limiter applied to every request.
+    pub general_limiter: Arc<api::ip_rate_limit::IpRateLimiter>,
+    /// Shared key-value backend for stateful stores (`STORE_BACKEND`).
+    pub kv_store: Arc<dyn store::KeyValueStore>,
+    /// Writes under `/api/v1` are refused while set (`READ_ONLY_MODE`, admin toggle).
+    pub read_only: Arc<AtomicBool>,
+    /// Set once shutdown starts; `/health/ready` then reports 503.
+    pub draining: Arc<AtomicBool>,
+    /// Time source for token issue/expiry, rotation windows and audit events.
+    pub clock: Arc<dyn clock::Clock>,
+    /// Shared outbound HTTP client (pooled, `HTTP_CLIENT_TIMEOUT_MS`).
+    #[allow(dead_code)] // For integrations (breach checks, webhooks, mailer) as they land
+    pub http_client: reqwest::Client,
+    /// In-flight user reads by id; `Some` only when `DB_READ_COALESCING=true`.
+    pub user_reads: Option<Arc<single_flight::SingleFlight<i64, Result<features::users::domain::entities::User, api::ApiError>>>>,
+}
+
+impl AppState {
+    /// State with every shared store in a fresh in-memory `KeyValueStore`.
+    pub fn new(config: AppConfig, db_pool: Option<DbPool>) -> Self {
+        Self::with_kv_store(config, db_pool, Arc::new(store::MemoryStore::default()))
+    }
+
+    /// State whose shared stores (limiters, sessions, refresh rotations, and
+    /// revoked tokens unless `STORE_BACKEND=memory`) live in `kv_store`.
+    pub fn with_kv_store(config: AppConfig, db_pool: Option<DbPool>, kv_store: Arc<dyn store::KeyValueStore>) -> Self {
+        let revocations: Arc<dyn api::jwt::RevocationStore> = match config.store_backend {
+            store::StoreBackend::Memory => Arc::new(api::jwt::MemoryRevocationStore::new(Arc::new(clock::SystemClock))),
+            store::StoreBackend::Redis(_) => Arc::new(api::jwt::SharedRevocationStore::new(kv_store.clone())),
+        };
+        Self {
+            health_cache: Arc::new(api::HealthCache::new(config.health_cache_ttl)),
+            http_client: http_client::build(config.http_client_timeout).expect("outbound HTTP client"),
+            read_only: Arc::new(AtomicBool::new(config.read_only)),
+            user_reads: config.coalesce_db_reads.then(Default::default),
+            config,
+            db_pool,
+            login_limiter: Arc::new(api::rate_limit::EmailRateLimiter::login(kv_store.clone())),
+            csrf_store: None,
+            started_at: Instant::now(),
+            refresh_ip_tracker: Arc::new(api::ip_pinning::RefreshIpTracker::default()),
+            jobs: None,
+            auth_events: Arc::new(api::audit::AuthEventStore::default()),
+            export_limiter: Arc::new(api::rate_limit::EmailRateLimiter::export(kv_store.clone())),
+            refresh_rotations: Arc::new(api::refresh_rotation::RefreshRotations::new(
+                kv_store.clone(),
+                api::refresh_rotation::DEFAULT_GRACE,
+            )),
+            sessions: Arc::new(api::sessions::SessionRegistry::new(kv_store.clone())),
+            revocations,
+            general_limiter: Arc::new(api::ip_rate_limit::IpRateLimiter::default()),
+            kv_store,
+            draining: Arc::new(AtomicBool::new(false)),
+            clock: Arc::new(clock::SystemClock),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+    init_tracing();
 
     let config = match AppConfig::from_env() {
         Ok(cfg) => cfg,
@@ -62,13 +144,41 @@ async fn main() {
         }
     };
 
+    info!("{}", config.summary());
+
+    api::jwt::init_settings(config.token_settings());
+    api::password::init_pepper(config.password_pepper.clone());
+    api::json::init_pretty_json(config.pretty_json);
+    api::ErrorVerbosity::init(config.error_verbosity);
+    if let Err(err) = api::jwt::check_keys() {
+        eprintln!("Configuration error: {err}");
+        std::process::exit(1);
+    }
+
+    if self_test::requested() {
+        match self_test::run(&config).await {
+            Ok(()) => {
+                info!("Self-test passed");
+                std::process::exit(0);
+            }
+            Err(err) => {
+                eprintln!("Self-test failed: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
     let db_pool = match (&config.database_url, config.database_required) {
-        (Some(url), _) => match db::create_pool(url) {
+        (Some(url), required) => match db::create_pool(url) {
             Ok(pool) => Some(pool),
-            Err(err) => {
+            Err(err) if required => {
                 eprintln!("Database pool error: {err}");
                 std::process::exit(1);
             }
+            Err(err) => {
+                tracing::warn!("Database unavailable, continuing without it: {err}");
+                None
+            }
         },
         (None, true) => {
             eprintln!("Configuration error: DATABASE_REQUIRED=true but DATABASE_URL is missing");
@@ -77,86 +187,81 @@ async fn main() {
         (None, false) => None,
     };
 
-    let state = AppState {
-        config: config.clone(),
-        db_pool,
-    };
+    if let Some(pool) = db_pool.clone().filter(|_| config::env_flag("DB_WARM_POOL")) {
+        let count = db::pool_min_idle();
+        let warmed =
+            tokio::task::spawn_blocking(move || db::warm_pool(&pool, count, db::ping_connection))
+                .await;
+        match warmed {
+            Ok(Ok(warmed)) => info!("Warmed database pool with {warmed} connections"),
+            Ok(Err(err)) => {
+                eprintln!("Database pool warm-up error: {err}");
+                std::process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("Database pool warm-up panicked: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
 
-    // ==========================================================================
-    // CORS CONFIGURATION FOR SECURE COOKIE-BASED AUTH
-    // ==========================================================================
-    //
-    // Origins are configured via ALLOWED_ORIGINS environment variable.
-    // In production, this MUST be set to your actual domain(s).
-    // In development, defaults to localhost origins.
-    //
-    // ==========================================================================
-    let allowed_origins: Vec<axum::http::HeaderValue> = config
-        .allowed_origins
-        .iter()
-        .filter_map(|origin| origin.parse().ok())
-        .collect();
-
-    if allowed_origins.is_empty() {
-        eprintln!("Warning: No valid CORS origins configured");
+    if let Some(pool) = db_pool.clone().filter(|_| config.run_migrations) {
+        match tokio::task::spawn_blocking(move || db::run_pending_migrations(&pool)).await {
+            Ok(Ok(applied)) if applied.is_empty() => info!("Database schema is up to date"),
+            Ok(Ok(applied)) => info!("Applied {} migration(s): {}", applied.len(), applied.join(", ")),
+            Ok(Err(err)) => {
+                eprintln!("Database migration error: {err}");
+                std::process::exit(1);
+            }
+            Err(err) => {
+                eprintln!("Database migration panicked: {err}");
+                std::process::exit(1);
+            }
+        }
     }
 
-    let allowed_headers = [
-        header::CONTENT_TYPE,
-        header::AUTHORIZATION,
-        header::ACCEPT,
-        header::HeaderName::from_static("x-client-type"),
-    ];
-
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers(allowed_headers)
-        .allow_origin(allowed_origins)
-        .allow_credentials(true);
-
-    // ==========================================================================
-    // RATE LIMITING CONFIGURATION
-    // ==========================================================================
-    //
-    // Two rate limiters:
-    // 1. General API: 50 req/sec, burst 100 (for normal endpoints)
-    // 2. Auth endpoints: 5 req/min, burst 10 (prevent brute force)
-    //
-    // ==========================================================================
-    
-    // General rate limiter for most endpoints
-    let general_governor = GovernorConfigBuilder::default()
-        .per_second(50)
-        .burst_size(100)
-        .finish()
-        .expect("general governor config");
-
-    // Strict rate limiter for auth endpoints (prevent brute force)
-    let auth_governor = GovernorConfigBuilder::default()
-        .per_second(1) // 1 request per second sustained
-        .burst_size(5) // Allow burst of 5 attempts
-        .finish()
-        .expect("auth governor config");
-
-    // Auth routes with stricter rate limiting
-    let auth_routes = Router::new()
-        .route("/auth/login", axum::routing::post(api::login))
-        .route("/auth/logout", axum::routing::post(api::logout))
-        .route("/auth/refresh", axum::routing::post(api::refresh))
-        .layer(GovernorLayer::new(auth_governor));
-
-    let app = Router::new()
-        .nest("/api/v1", api::routes().merge(auth_routes))
-        .route("/health/live", get(api::live))
-        .route("/health/ready", get(api::ready))
-        .layer(TraceLayer::new_for_http()) // Request/response logging
-        .layer(GovernorLayer::new(general_governor))
-        .layer(cors)
-        .layer(ConcurrencyLimitLayer::new(256))
-        .layer(CompressionLayer::new())
-        .with_state(state);
-
-    let listener = match tokio::net::TcpListener::bind(config.addr()).await {
+    let kv_store = match store::connect(&config.store_backend) {
+        Ok(kv_store) => kv_store,
+        Err(err) => {
+            eprintln!("Store backend error: {err}");
+            std::process::exit(1);
+        }
+    };
+    let mut state = AppState::with_kv_store(config.clone(), db_pool, kv_store);
+    if api::csrf::CsrfMode::from_env() == api::csrf::CsrfMode::Stateful {
+        state.csrf_store = Some(Arc::new(
+            api::csrf::CsrfStore::new(state.kv_store.clone(), state.clock.clone())
+                .with_max_per_session(api::csrf::CsrfStore::max_per_session_from_env()),
+        ));
+    }
+    state.refresh_ip_tracker = Arc::new(api::ip_pinning::RefreshIpTracker::new(
+        api::ip_pinning::IpPinningMode::from_env(),
+    ));
+    state.refresh_rotations = Arc::new(api::refresh_rotation::RefreshRotations::from_env(state.kv_store.clone()));
+    tokio::spawn(api::jwt::run_revocation_sync(state.kv_store.clone()));
+    match api::password::init_denylist() {
+        Ok(0) => {}
+        Ok(entries) => info!("Password denylist loaded ({entries} entries)"),
+        Err(err) => {
+            eprintln!("Password denylist error: {err}");
+            std::process::exit(1);
+        }
+    }
+
+    let (job_queue, job_worker) = jobs::spawn_worker(jobs::JOB_QUEUE_CAPACITY, jobs::run_job);
+    state.jobs = Some(job_queue);
+    let draining = state.draining.clone();
+    let shutdown_drain = config.shutdown_drain;
+
+    let app = match app::router(state) {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("Configuration error: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let listener = match listener::bind(&config) {
         Ok(l) => l,
         Err(err) => {
             eprintln!("Failed to bind to {}: {err}", config.addr());
@@ -167,7 +272,11 @@ async fn main() {
     info!("backend listening on http://{}", config.addr());
 
     // Graceful shutdown handling
-    let shutdown_signal = async {
+    //
+    // SIGTERM: fail /health/ready first, keep serving for `shutdown_drain`
+    // so the load balancer stops routing here, then stop accepting
+    // connections and drain in-flight requests. Ctrl+C (local) skips the wait.
+    let shutdown_signal = async move {
         let ctrl_c = async {
             tokio::signal::ctrl_c()
                 .await
@@ -190,19 +299,63 @@ async fn main() {
                 info!("Received Ctrl+C, starting graceful shutdown...");
             },
             _ = terminate => {
-                info!("Received SIGTERM, starting graceful shutdown...");
+                draining.store(true, Ordering::Relaxed);
+                info!(
+                    "Received SIGTERM, reporting not ready for {}s before shutting down...",
+                    shutdown_drain.as_secs()
+                );
+                tokio::time::sleep(shutdown_drain).await;
             },
         }
+        draining.store(true, Ordering::Relaxed);
     };
 
     // Use into_make_service_with_connect_info for rate limiter to extract peer IP
-    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
-        .with_graceful_shutdown(shutdown_signal);
+    let make_service = axum::ServiceExt::<axum::extract::Request>::into_make_service_with_connect_info::<
+        SocketAddr,
+    >(app);
+    let server = axum::serve(listener, make_service).with_graceful_shutdown(shutdown_signal);
 
     if let Err(err) = server.await {
         eprintln!("Server error: {err}");
         std::process::exit(1);
     }
 
+    if !job_worker.shutdown(jobs::JOB_DRAIN_TIMEOUT).await {
+        eprintln!("Warning: job queue did not drain within {:?}; remaining jobs dropped", jobs::JOB_DRAIN_TIMEOUT);
+    }
+
     info!("Server shutdown complete");
 }
+
+/// Install the global `fmt` subscriber (filtered by `RUST_LOG`).
+///
+/// Uses `try_init` so an already-installed subscriber (integration tests,
+/// embedding) is kept instead of panicking. Returns whether ours was installed.
+fn init_tracing() -> bool {
+    match tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init()
+    {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::debug!("Tracing subscriber already set, keeping it: {err}");
+            false
+        }
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_tracing_twice_does_not_panic() {
+        init_tracing();
+        assert!(!init_tracing());
+    }
+}
@@ -14,5 +14,9 @@ diesel::table! {
         is_active -> Bool,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        must_change_password -> Bool,
+        email_verified -> Bool,
+        #[max_length = 255]
+        canonical_email -> Varchar,
     }
 }
@@ -0,0 +1,79 @@
+// ==============================================================================
+// STARTUP SELF-TEST
+// ==============================================================================
+//
+// `backend --check` (or `SELF_TEST=true`) verifies a deployment artifact
+// without serving traffic:
+//
+// 1. Configuration loads and passes validation
+// 2. Database answers `SELECT 1` (only if `DATABASE_URL` is set)
+// 3. A throwaway token can be generated and validated
+//
+// The process then exits 0 on success or 1 on the first failure, so CI and
+// smoke tests get a single command to run.
+//
+// ==============================================================================
+
+use std::sync::Arc;
+
+use crate::api::jwt;
+use crate::clock::SystemClock;
+use crate::config::AppConfig;
+use crate::db;
+
+/// True when the self-test was requested via `--check` or `SELF_TEST=true`.
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--check") || crate::config::env_flag("SELF_TEST")
+}
+
+/// Run every check, returning a description of the first failure.
+pub async fn run(config: &AppConfig) -> Result<(), String> {
+    config.validate().map_err(|e| format!("config: {e}"))?;
+
+    if let Some(url) = config.database_url.clone() {
+        tokio::task::spawn_blocking(move || {
+            let pool = db::create_pool(&url)?;
+            db::check_database(&pool).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| format!("database: check panicked: {e}"))?
+        .map_err(|e| format!("database: {e}"))?;
+    }
+
+    let pair = jwt::generate_token_pair(&SystemClock, 0, "self-test@localhost", &[])
+        .map_err(|e| format!("token: generation failed: {e}"))?;
+    let revocations: Arc<dyn jwt::RevocationStore> = Arc::new(jwt::MemoryRevocationStore::new(Arc::new(SystemClock)));
+    jwt::validate_access_token_checked(&pair.access_token, &SystemClock, &revocations)
+        .await
+        .map_err(|e| format!("token: validation failed: {e}"))?;
+    jwt::validate_refresh_token_checked(&pair.refresh_token, &SystemClock, &revocations)
+        .await
+        .map_err(|e| format!("token: refresh validation failed: {e}"))?;
+
+    Ok(())
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_self_test_passes_with_valid_config() {
+        assert_eq!(run(&AppConfig::default()).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_self_test_fails_with_invalid_config() {
+        let config = AppConfig {
+            environment: "production".to_string(),
+            allowed_origins: Vec::new(),
+            ..Default::default()
+        };
+        let err = run(&config).await.unwrap_err();
+        assert!(err.starts_with("config:"), "{err}");
+    }
+}
@@ -0,0 +1,173 @@
+// ==============================================================================
+// SINGLE-FLIGHT READ COALESCING
+// ==============================================================================
+//
+// `SingleFlight` lets concurrent identical reads share one execution: the
+// first caller for a key runs the query, callers arriving while it is in
+// flight wait for and clone its result. Once it completes the key is
+// retired, so the next caller queries afresh. Nothing is cached.
+//
+// USED FOR:
+// Thundering herds on hot keys, e.g. every client re-fetching `/me` right
+// after a deploy. Enabled with `DB_READ_COALESCING=true` (`state.user_reads`).
+//
+// TRADE-OFF:
+// A caller that joins an in-flight read gets a result from a query that
+// started slightly before its own request, so it may miss a write committed
+// in that window (at most one query round trip). That's why it is opt-in.
+//
+// If the caller running the query is cancelled (client disconnect), one of
+// the waiting callers runs it instead. Every caller retires the key when it
+// finishes or is dropped, so a flight abandoned by all of its callers
+// doesn't stay in the map.
+//
+// ==============================================================================
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// In-flight reads by key; see the module docs.
+#[derive(Debug)]
+pub struct SingleFlight<K, V> {
+    in_flight: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> SingleFlight<K, V> {
+    /// Run `query` for `key`, or share the result of the one already running.
+    pub async fn run<F, Fut>(&self, key: K, query: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let cell = self.lock().entry(key.clone()).or_default().clone();
+        let _retire = Retire {
+            flight: self,
+            key: &key,
+            cell: &cell,
+        };
+        cell.get_or_init(query).await.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<K, Arc<OnceCell<V>>>> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Retires `key` when a caller of `run` returns or is cancelled.
+struct Retire<'a, K: Eq + Hash, V> {
+    flight: &'a SingleFlight<K, V>,
+    key: &'a K,
+    cell: &'a Arc<OnceCell<V>>,
+}
+
+impl<K: Eq + Hash, V> Drop for Retire<'_, K, V> {
+    fn drop(&mut self) {
+        // The first caller to get here retires the key; a newer flight for
+        // the same key (a different cell) is left alone
+        let mut in_flight = self.flight.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        if in_flight.get(self.key).is_some_and(|current| Arc::ptr_eq(current, self.cell)) {
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Stands in for `get_user_by_id`, counting executions.
+    async fn query(executions: &AtomicUsize, id: i64) -> Result<String, String> {
+        executions.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(format!("user {id}"))
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_reads_for_one_key_share_a_query() {
+        let flight = Arc::new(SingleFlight::default());
+        let executions = Arc::new(AtomicUsize::new(0));
+
+        let reads: Vec<_> = (0..50)
+            .map(|_| {
+                let (flight, executions) = (flight.clone(), executions.clone());
+                tokio::spawn(async move { flight.run(7, || query(&executions, 7)).await })
+            })
+            .collect();
+        for read in reads {
+            assert_eq!(read.await.unwrap(), Ok("user 7".to_string()));
+        }
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+
+        // Retired once done: a later read queries again
+        assert_eq!(flight.run(7, || query(&executions, 7)).await, Ok("user 7".to_string()));
+        assert_eq!(executions.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_different_keys_query_separately() {
+        let flight = SingleFlight::default();
+        let executions = AtomicUsize::new(0);
+
+        let (a, b) = tokio::join!(
+            flight.run(1, || query(&executions, 1)),
+            flight.run(2, || query(&executions, 2)),
+        );
+        assert_eq!((a.unwrap(), b.unwrap()), ("user 1".to_string(), "user 2".to_string()));
+        assert_eq!(executions.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_leader_hands_over_to_a_waiter() {
+        let flight = Arc::new(SingleFlight::default());
+        let executions = Arc::new(AtomicUsize::new(0));
+
+        let leader = {
+            let (flight, executions) = (flight.clone(), executions.clone());
+            tokio::spawn(async move { flight.run(7, || query(&executions, 7)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let waiter = {
+            let (flight, executions) = (flight.clone(), executions.clone());
+            tokio::spawn(async move { flight.run(7, || query(&executions, 7)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+
+        assert_eq!(waiter.await.unwrap(), Ok("user 7".to_string()));
+        assert_eq!(executions.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_sole_caller_retires_its_key() {
+        let flight = Arc::new(SingleFlight::default());
+        let executions = Arc::new(AtomicUsize::new(0));
+
+        let leader = {
+            let (flight, executions) = (flight.clone(), executions.clone());
+            tokio::spawn(async move { flight.run(7, || query(&executions, 7)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(flight.lock().len(), 1);
+        leader.abort();
+        assert!(leader.await.unwrap_err().is_cancelled());
+
+        assert!(flight.lock().is_empty());
+    }
+}
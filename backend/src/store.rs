@@ -0,0 +1,615 @@
+// ==============================================================================
+// KEY-VALUE STORE
+// ==============================================================================
+//
+// Shared storage backend for stateful subsystems, so they can survive
+// restarts and be shared across replicas instead of each keeping its own
+// in-memory map. Stores on it (all held on `AppState`):
+// - stateful CSRF tokens (`api::csrf::CsrfStore`)
+// - per-account login/export limits and lockouts (`api::rate_limit`)
+// - live sessions per user (`api::sessions`)
+// - refresh token rotation and reuse detection (`api::refresh_rotation`)
+// - the `revoke-before` cutoff (`api::jwt::revoke_issued_before`)
+// - tokens revoked by `jti` at logout (`api::jwt::RevocationStore`)
+//
+// Still per-process: the per-IP limiters, refresh IP pinning and the recent
+// auth event log.
+//
+// BACKENDS (`STORE_BACKEND`):
+// - memory (default) per-process `HashMap`; lost on restart
+// - redis            shared Redis at `REDIS_URL` (may be a `_FILE` secret)
+//
+// NOTES:
+// - The trait is synchronous like Diesel: the Redis client blocks on I/O.
+//   Async code goes through `call`, which runs blocking backends on the
+//   blocking thread pool (and the in-memory one inline)
+// - Every value has a TTL, so abandoned keys never accumulate
+// - Values are strings; callers encode structured data themselves
+// - `push_capped`, `set_if_absent`, `increment` and the `map_*` operations
+//   are each atomic, so concurrent writers (across replicas) never lose
+//   updates
+//
+// ==============================================================================
+
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+
+/// Number of writes between sweeps of expired in-memory entries.
+const SWEEP_INTERVAL: u64 = 256;
+
+/// Redis connections kept by the pool.
+const REDIS_POOL_MAX_SIZE: u32 = 8;
+
+/// Which `KeyValueStore` implementation to use (`STORE_BACKEND`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreBackend {
+    Memory,
+    /// Redis at the given URL (`REDIS_URL`).
+    Redis(String),
+}
+
+impl StoreBackend {
+    /// Resolve `STORE_BACKEND` (`memory` | `redis`). Redis requires a URL.
+    pub fn parse(backend: Option<&str>, redis_url: Option<String>) -> Result<Self, String> {
+        match backend.map(|v| v.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("memory") => Ok(StoreBackend::Memory),
+            Some("redis") => redis_url
+                .map(StoreBackend::Redis)
+                .ok_or_else(|| "STORE_BACKEND=redis but REDIS_URL is missing".to_string()),
+            Some(other) => Err(format!("STORE_BACKEND must be memory or redis (got {other:?})")),
+        }
+    }
+
+    /// Backend name, safe to log (never the URL).
+    pub fn name(&self) -> &'static str {
+        match self {
+            StoreBackend::Memory => "memory",
+            StoreBackend::Redis(_) => "redis",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// The backend could not be reached or returned an error.
+    #[error("store backend error: {0}")]
+    Backend(String),
+}
+
+/// String key-value storage with per-key expiry.
+pub trait KeyValueStore: std::fmt::Debug + Send + Sync {
+    /// Value for `key`, or `None` if absent or expired.
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError>;
+
+    /// Store `value` under `key`, replacing any previous value; it expires after `ttl`.
+    fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), StoreError>;
+
+    /// Remove `key`; removing an absent key is not an error.
+    fn delete(&self, key: &str) -> Result<(), StoreError>;
+
+    /// Append `value` to the list at `key`, keep only the newest `max`
+    /// entries (minimum 1) and return the dropped ones, oldest first. The
+    /// list expires after `ttl`. Atomic: each entry is dropped exactly once.
+    fn push_capped(&self, key: &str, value: &str, max: usize, ttl: Duration) -> Result<Vec<String>, StoreError>;
+
+    /// Store `value` under `key` only if it is absent; true if this call
+    /// stored it. Atomic, so exactly one concurrent caller wins.
+    fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, StoreError>;
+
+    /// Add one to the counter at `key` (starting from 0) and return the new
+    /// count. The counter expires `ttl` after the last increment.
+    fn increment(&self, key: &str, ttl: Duration) -> Result<i64, StoreError>;
+
+    /// Set `field` of the map at `key`; the map expires `ttl` after the last insert.
+    fn map_insert(&self, key: &str, field: &str, value: &str, ttl: Duration) -> Result<(), StoreError>;
+
+    /// Every `(field, value)` of the map at `key` (empty if absent).
+    fn map_entries(&self, key: &str) -> Result<Vec<(String, String)>, StoreError>;
+
+    /// Remove `fields` from the map at `key`; absent fields are ignored.
+    fn map_remove(&self, key: &str, fields: &[String]) -> Result<(), StoreError>;
+
+    /// Whether calls block on I/O (and so must stay off async worker threads).
+    fn blocks(&self) -> bool {
+        true
+    }
+}
+
+/// Run `f` against `kv` from async code without stalling the runtime:
+/// on the blocking thread pool for backends that do I/O, inline otherwise.
+pub async fn call<T, F>(kv: &Arc<dyn KeyValueStore>, f: F) -> Result<T, StoreError>
+where
+    F: FnOnce(&dyn KeyValueStore) -> Result<T, StoreError> + Send + 'static,
+    T: Send + 'static,
+{
+    if !kv.blocks() {
+        return f(kv.as_ref());
+    }
+    let kv = kv.clone();
+    tokio::task::spawn_blocking(move || f(kv.as_ref()))
+        .await
+        .map_err(|e| StoreError::Backend(format!("store call panicked: {e}")))?
+}
+
+/// Connect to the configured backend.
+///
+/// FAILURE MODES:
+/// - Returns an error string suitable for a startup failure if Redis is unreachable.
+pub fn connect(backend: &StoreBackend) -> Result<Arc<dyn KeyValueStore>, String> {
+    match backend {
+        StoreBackend::Memory => Ok(Arc::new(MemoryStore::default())),
+        StoreBackend::Redis(url) => Ok(Arc::new(RedisStore::connect(url)?)),
+    }
+}
+
+// ==============================================================================
+// IN-MEMORY
+// ==============================================================================
+
+#[derive(Debug)]
+enum Value {
+    Text(String),
+    List(VecDeque<String>),
+    Map(HashMap<String, String>),
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    expires_at: DateTime<Utc>,
+}
+
+/// Same error Redis gives for e.g. a string operation on a list.
+fn wrong_type(key: &str) -> StoreError {
+    StoreError::Backend(format!("WRONGTYPE: {key} holds a different kind of value"))
+}
+
+/// The live entry at `key`, replacing a missing or expired one with `empty()`.
+fn live_entry<'a>(
+    entries: &'a mut HashMap<String, Entry>,
+    key: &str,
+    now: DateTime<Utc>,
+    empty: fn() -> Value,
+) -> &'a mut Entry {
+    let entry = entries.entry(key.to_string()).or_insert_with(|| Entry {
+        value: empty(),
+        expires_at: now,
+    });
+    if entry.expires_at <= now {
+        entry.value = empty();
+    }
+    entry
+}
+
+/// Per-process store (the default backend).
+#[derive(Debug)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, Entry>>,
+    clock: Arc<dyn Clock>,
+    writes: AtomicU64,
+}
+
+impl MemoryStore {
+    /// Expiry is judged against `clock`.
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            clock,
+            writes: AtomicU64::new(0),
+        }
+    }
+
+    /// Lock the entries for a write, sweeping expired ones every `SWEEP_INTERVAL` writes.
+    fn lock_for_write(&self, now: DateTime<Utc>) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if self.writes.fetch_add(1, Ordering::Relaxed) % SWEEP_INTERVAL == SWEEP_INTERVAL - 1 {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        entries
+    }
+}
+
+/// `now + ttl`, saturating.
+fn expiry(now: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+    now.checked_add_signed(ttl).unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemClock))
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        let now = self.clock.now();
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key).filter(|entry| entry.expires_at > now) {
+            None => Ok(None),
+            Some(Entry { value: Value::Text(value), .. }) => Ok(Some(value.clone())),
+            Some(_) => Err(wrong_type(key)),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), StoreError> {
+        let now = self.clock.now();
+        self.lock_for_write(now).insert(
+            key.to_string(),
+            Entry {
+                value: Value::Text(value.to_string()),
+                expires_at: expiry(now, ttl),
+            },
+        );
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StoreError> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+        Ok(())
+    }
+
+    fn push_capped(&self, key: &str, value: &str, max: usize, ttl: Duration) -> Result<Vec<String>, StoreError> {
+        let now = self.clock.now();
+        let mut entries = self.lock_for_write(now);
+        let entry = live_entry(&mut entries, key, now, || Value::List(VecDeque::new()));
+        let Value::List(list) = &mut entry.value else {
+            return Err(wrong_type(key));
+        };
+
+        list.push_back(value.to_string());
+        let excess = list.len().saturating_sub(max.max(1));
+        let dropped = list.drain(..excess).collect();
+        entry.expires_at = expiry(now, ttl);
+        Ok(dropped)
+    }
+
+    fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, StoreError> {
+        let now = self.clock.now();
+        let mut entries = self.lock_for_write(now);
+        if entries.get(key).is_some_and(|entry| entry.expires_at > now) {
+            return Ok(false);
+        }
+        entries.insert(
+            key.to_string(),
+            Entry {
+                value: Value::Text(value.to_string()),
+                expires_at: expiry(now, ttl),
+            },
+        );
+        Ok(true)
+    }
+
+    fn increment(&self, key: &str, ttl: Duration) -> Result<i64, StoreError> {
+        let now = self.clock.now();
+        let mut entries = self.lock_for_write(now);
+        let entry = live_entry(&mut entries, key, now, || Value::Text("0".to_string()));
+        let Value::Text(count) = &mut entry.value else {
+            return Err(wrong_type(key));
+        };
+
+        let next = count
+            .parse::<i64>()
+            .map_err(|_| StoreError::Backend(format!("{key} is not a counter")))?
+            .saturating_add(1);
+        *count = next.to_string();
+        entry.expires_at = expiry(now, ttl);
+        Ok(next)
+    }
+
+    fn map_insert(&self, key: &str, field: &str, value: &str, ttl: Duration) -> Result<(), StoreError> {
+        let now = self.clock.now();
+        let mut entries = self.lock_for_write(now);
+        let entry = live_entry(&mut entries, key, now, || Value::Map(HashMap::new()));
+        let Value::Map(map) = &mut entry.value else {
+            return Err(wrong_type(key));
+        };
+
+        map.insert(field.to_string(), value.to_string());
+        entry.expires_at = expiry(now, ttl);
+        Ok(())
+    }
+
+    fn map_entries(&self, key: &str) -> Result<Vec<(String, String)>, StoreError> {
+        let now = self.clock.now();
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key).filter(|entry| entry.expires_at > now) {
+            None => Ok(Vec::new()),
+            Some(Entry { value: Value::Map(map), .. }) => {
+                Ok(map.iter().map(|(field, value)| (field.clone(), value.clone())).collect())
+            }
+            Some(_) => Err(wrong_type(key)),
+        }
+    }
+
+    fn map_remove(&self, key: &str, fields: &[String]) -> Result<(), StoreError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get_mut(key).map(|entry| &mut entry.value) {
+            None => Ok(()),
+            Some(Value::Map(map)) => {
+                for field in fields {
+                    map.remove(field);
+                }
+                Ok(())
+            }
+            Some(_) => Err(wrong_type(key)),
+        }
+    }
+
+    fn blocks(&self) -> bool {
+        false
+    }
+}
+
+// ==============================================================================
+// REDIS
+// ==============================================================================
+
+/// Store backed by a shared Redis instance.
+#[derive(Debug)]
+pub struct RedisStore {
+    pool: r2d2::Pool<redis::Client>,
+}
+
+impl RedisStore {
+    /// Open a connection pool to `url`, failing if Redis is unreachable.
+    pub fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("invalid REDIS_URL: {e}"))?;
+        let pool = r2d2::Pool::builder()
+            .max_size(REDIS_POOL_MAX_SIZE)
+            .build(client)
+            .map_err(|e| format!("failed to connect to Redis: {e}"))?;
+        Ok(Self { pool })
+    }
+
+    fn query<T: redis::FromRedisValue>(&self, cmd: &redis::Cmd) -> Result<T, StoreError> {
+        let mut conn = self.pool.get().map_err(|e| StoreError::Backend(e.to_string()))?;
+        cmd.query(&mut *conn).map_err(|e| StoreError::Backend(e.to_string()))
+    }
+}
+
+impl KeyValueStore for RedisStore {
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        self.query(redis::cmd("GET").arg(key))
+    }
+
+    fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), StoreError> {
+        // PX takes milliseconds and rejects 0
+        let ttl_ms = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1);
+        self.query(redis::cmd("SET").arg(key).arg(value).arg("PX").arg(ttl_ms))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), StoreError> {
+        self.query(redis::cmd("DEL").arg(key))
+    }
+
+    fn push_capped(&self, key: &str, value: &str, max: usize, ttl: Duration) -> Result<Vec<String>, StoreError> {
+        let max = isize::try_from(max.max(1)).unwrap_or(isize::MAX);
+        let ttl_ms = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX).max(1);
+        let mut conn = self.pool.get().map_err(|e| StoreError::Backend(e.to_string()))?;
+        // MULTI/EXEC: everything but the newest `max` is read and trimmed in one step
+        let (dropped,): (Vec<String>,) = redis::pipe()
+            .atomic()
+            .rpush(key, value)
+            .ignore()
+            .lrange(key, 0, -max - 1)
+            .ltrim(key, -max, -1)
+            .ignore()
+            .pexpire(key, ttl_ms)
+            .ignore()
+            .query(&mut *conn)
+            .map_err(|e| StoreError::Backend(e.to_string()))?;
+        Ok(dropped)
+    }
+
+    fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, StoreError> {
+        let ttl_ms = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1);
+        // Replies OK when set, nil when the key already exists
+        let reply: Option<String> = self.query(redis::cmd("SET").arg(key).arg(value).arg("NX").arg("PX").arg(ttl_ms))?;
+        Ok(reply.is_some())
+    }
+
+    fn increment(&self, key: &str, ttl: Duration) -> Result<i64, StoreError> {
+        let ttl_ms = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX).max(1);
+        let mut conn = self.pool.get().map_err(|e| StoreError::Backend(e.to_string()))?;
+        let (count,): (i64,) = redis::pipe()
+            .atomic()
+            .incr(key, 1)
+            .pexpire(key, ttl_ms)
+            .ignore()
+            .query(&mut *conn)
+            .map_err(|e| StoreError::Backend(e.to_string()))?;
+        Ok(count)
+    }
+
+    fn map_insert(&self, key: &str, field: &str, value: &str, ttl: Duration) -> Result<(), StoreError> {
+        let ttl_ms = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX).max(1);
+        let mut conn = self.pool.get().map_err(|e| StoreError::Backend(e.to_string()))?;
+        redis::pipe()
+            .atomic()
+            .hset(key, field, value)
+            .ignore()
+            .pexpire(key, ttl_ms)
+            .ignore()
+            .query(&mut *conn)
+            .map_err(|e| StoreError::Backend(e.to_string()))
+    }
+
+    fn map_entries(&self, key: &str) -> Result<Vec<(String, String)>, StoreError> {
+        let map: HashMap<String, String> = self.query(redis::cmd("HGETALL").arg(key))?;
+        Ok(map.into_iter().collect())
+    }
+
+    fn map_remove(&self, key: &str, fields: &[String]) -> Result<(), StoreError> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+        self.query(redis::cmd("HDEL").arg(key).arg(fields))
+    }
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    /// Behaviour every backend must share.
+    fn run_store_suite(store: &dyn KeyValueStore) {
+        let ttl = Duration::from_secs(60);
+        let key = format!("suite:{}", uuid::Uuid::new_v4());
+
+        assert_eq!(store.get(&key).unwrap(), None);
+
+        store.set(&key, "one", ttl).unwrap();
+        assert_eq!(store.get(&key).unwrap().as_deref(), Some("one"));
+
+        store.set(&key, "two", ttl).unwrap();
+        assert_eq!(store.get(&key).unwrap().as_deref(), Some("two"));
+
+        store.delete(&key).unwrap();
+        assert_eq!(store.get(&key).unwrap(), None);
+        store.delete(&key).unwrap();
+
+        let other = format!("{key}:other");
+        store.set(&other, "kept", ttl).unwrap();
+        store.delete(&key).unwrap();
+        assert_eq!(store.get(&other).unwrap().as_deref(), Some("kept"));
+        store.delete(&other).unwrap();
+
+        let list = format!("{key}:list");
+        assert!(store.push_capped(&list, "a", 2, ttl).unwrap().is_empty());
+        assert!(store.push_capped(&list, "b", 2, ttl).unwrap().is_empty());
+        assert_eq!(store.push_capped(&list, "c", 2, ttl).unwrap(), vec!["a".to_string()]);
+        assert_eq!(store.push_capped(&list, "d", 1, ttl).unwrap(), vec!["b".to_string(), "c".to_string()]);
+        assert!(store.get(&list).is_err());
+        store.delete(&list).unwrap();
+        assert!(store.push_capped(&list, "e", 1, ttl).unwrap().is_empty());
+        store.delete(&list).unwrap();
+
+        let claim = format!("{key}:claim");
+        assert!(store.set_if_absent(&claim, "first", ttl).unwrap());
+        assert!(!store.set_if_absent(&claim, "second", ttl).unwrap());
+        assert_eq!(store.get(&claim).unwrap().as_deref(), Some("first"));
+        store.delete(&claim).unwrap();
+
+        let counter = format!("{key}:counter");
+        assert_eq!(store.increment(&counter, ttl).unwrap(), 1);
+        assert_eq!(store.increment(&counter, ttl).unwrap(), 2);
+        assert_eq!(store.get(&counter).unwrap().as_deref(), Some("2"));
+        store.delete(&counter).unwrap();
+
+        let map = format!("{key}:map");
+        assert!(store.map_entries(&map).unwrap().is_empty());
+        store.map_insert(&map, "a", "1", ttl).unwrap();
+        store.map_insert(&map, "b", "2", ttl).unwrap();
+        store.map_insert(&map, "a", "3", ttl).unwrap();
+        let mut entries = store.map_entries(&map).unwrap();
+        entries.sort();
+        assert_eq!(entries, vec![("a".to_string(), "3".to_string()), ("b".to_string(), "2".to_string())]);
+        store.map_remove(&map, &["a".to_string(), "missing".to_string()]).unwrap();
+        assert_eq!(store.map_entries(&map).unwrap(), vec![("b".to_string(), "2".to_string())]);
+        store.delete(&map).unwrap();
+    }
+
+    #[test]
+    fn test_memory_store_suite() {
+        run_store_suite(&MemoryStore::default());
+    }
+
+    #[test]
+    #[ignore = "requires REDIS_URL pointing at a disposable Redis"]
+    fn test_redis_store_suite() {
+        let url = std::env::var("REDIS_URL").expect("REDIS_URL");
+        run_store_suite(&RedisStore::connect(&url).unwrap());
+    }
+
+    #[test]
+    fn test_memory_entries_expire_after_ttl() {
+        let clock = Arc::new(MockClock::starting_now());
+        let store = MemoryStore::new(clock.clone());
+        store.set("k", "v", Duration::from_secs(10)).unwrap();
+
+        clock.advance(chrono::Duration::seconds(9));
+        assert_eq!(store.get("k").unwrap().as_deref(), Some("v"));
+
+        clock.advance(chrono::Duration::seconds(1));
+        assert_eq!(store.get("k").unwrap(), None);
+    }
+
+    #[test]
+    fn test_memory_push_capped_is_atomic() {
+        let store = Arc::new(MemoryStore::default());
+        let handles: Vec<_> = (0..8)
+            .map(|thread| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    (0..50)
+                        .flat_map(|i| store.push_capped("list", &format!("{thread}-{i}"), 10, Duration::from_secs(60)).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let dropped: Vec<String> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+
+        // Every pushed entry is either still listed or dropped exactly once
+        assert_eq!(dropped.len(), 8 * 50 - 10);
+        let unique: std::collections::HashSet<_> = dropped.iter().collect();
+        assert_eq!(unique.len(), dropped.len());
+        assert_eq!(store.push_capped("list", "last", 10, Duration::from_secs(60)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_memory_list_expires_after_ttl() {
+        let clock = Arc::new(MockClock::starting_now());
+        let store = MemoryStore::new(clock.clone());
+        store.push_capped("list", "old", 2, Duration::from_secs(10)).unwrap();
+
+        clock.advance(chrono::Duration::seconds(10));
+        store.push_capped("list", "a", 2, Duration::from_secs(10)).unwrap();
+        assert!(store.push_capped("list", "b", 2, Duration::from_secs(10)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_memory_claims_and_counters_expire_after_ttl() {
+        let clock = Arc::new(MockClock::starting_now());
+        let store = MemoryStore::new(clock.clone());
+        let ttl = Duration::from_secs(10);
+        assert!(store.set_if_absent("claim", "a", ttl).unwrap());
+        store.increment("counter", ttl).unwrap();
+        store.map_insert("map", "field", "v", ttl).unwrap();
+
+        clock.advance(chrono::Duration::seconds(10));
+        assert!(store.set_if_absent("claim", "b", ttl).unwrap());
+        assert_eq!(store.increment("counter", ttl).unwrap(), 1);
+        assert!(store.map_entries("map").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_call_runs_store_operations() {
+        let kv: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::default());
+        call(&kv, |kv| kv.set("k", "v", Duration::from_secs(60))).await.unwrap();
+        let value = call(&kv, |kv| kv.get("k")).await.unwrap();
+        assert_eq!(value.as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn test_backend_parsing() {
+        assert_eq!(StoreBackend::parse(None, None), Ok(StoreBackend::Memory));
+        assert_eq!(StoreBackend::parse(Some("Memory"), None), Ok(StoreBackend::Memory));
+        assert_eq!(
+            StoreBackend::parse(Some("redis"), Some("redis://cache:6379".into())),
+            Ok(StoreBackend::Redis("redis://cache:6379".into()))
+        );
+        assert!(StoreBackend::parse(Some("redis"), None).is_err());
+        assert!(StoreBackend::parse(Some("etcd"), None).is_err());
+    }
+}
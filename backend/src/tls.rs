@@ -0,0 +1,122 @@
+// ==============================================================================
+// TLS SETTINGS
+// ==============================================================================
+//
+// The server speaks plain HTTP today (TLS terminates at a reverse proxy).
+// This module holds the rustls settings the TLS listener will use, so the
+// policy is validated at startup before that listener exists.
+//
+// MINIMUM PROTOCOL VERSION (`TLS_MIN_VERSION`):
+// - `1.2` (default): TLS 1.2 and 1.3
+// - `1.3`: TLS 1.3 only
+// - Anything else (including 1.0/1.1, which rustls doesn't implement) fails
+//   startup rather than silently falling back to a weaker default
+//
+// ==============================================================================
+
+use rustls::server::{ServerConfig, WantsServerCert};
+use rustls::{ConfigBuilder, SupportedProtocolVersion};
+use std::sync::Arc;
+
+static TLS12_AND_UP: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13, &rustls::version::TLS12];
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+/// Lowest TLS protocol version the server negotiates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TlsMinVersion {
+    #[default]
+    Tls12,
+    Tls13,
+}
+
+impl TlsMinVersion {
+    /// Parse `TLS_MIN_VERSION` (`1.2` | `1.3`; a `TLSv`/`TLS` prefix is accepted).
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let trimmed = value.trim().to_ascii_lowercase();
+        let version = trimmed
+            .strip_prefix("tlsv")
+            .or_else(|| trimmed.strip_prefix("tls"))
+            .unwrap_or(&trimmed);
+        match version {
+            "1.2" => Ok(Self::Tls12),
+            "1.3" => Ok(Self::Tls13),
+            _ => Err(format!("TLS_MIN_VERSION must be 1.2 or 1.3 (got {value:?})")),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tls12 => "1.2",
+            Self::Tls13 => "1.3",
+        }
+    }
+
+    /// Protocol versions enabled with this minimum.
+    pub fn protocol_versions(self) -> &'static [&'static SupportedProtocolVersion] {
+        match self {
+            Self::Tls12 => TLS12_AND_UP,
+            Self::Tls13 => TLS13_ONLY,
+        }
+    }
+}
+
+/// rustls server config builder restricted to `min` and newer, ready for
+/// the listener's certificate.
+#[allow(dead_code)] // Used by the TLS listener once it lands
+pub fn server_config_builder(min: TlsMinVersion) -> Result<ConfigBuilder<ServerConfig, WantsServerCert>, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(min.protocol_versions())
+        .map(|builder| builder.with_no_client_auth())
+        .map_err(|e| format!("invalid TLS configuration: {e}"))
+}
+
+// ==============================================================================
+// TESTS
+// ==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::server::{ClientHello, ResolvesServerCert};
+    use rustls::sign::CertifiedKey;
+
+    /// Stands in for the listener's certificate; never used for a handshake.
+    #[derive(Debug)]
+    struct NoCertificate;
+
+    impl ResolvesServerCert for NoCertificate {
+        fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+            None
+        }
+    }
+
+    fn enabled_versions(min: TlsMinVersion) -> String {
+        let config = server_config_builder(min)
+            .unwrap()
+            .with_cert_resolver(Arc::new(NoCertificate));
+        // `ServerConfig` doesn't expose its versions; its Debug output lists them
+        let debug = format!("{config:?}");
+        let start = debug.find("versions: ").unwrap();
+        let end = start + debug[start..].find(']').unwrap();
+        debug[start..=end].to_string()
+    }
+
+    #[test]
+    fn test_server_config_honours_min_version() {
+        let tls12 = enabled_versions(TlsMinVersion::Tls12);
+        assert!(tls12.contains("TLSv1_2") && tls12.contains("TLSv1_3"), "{tls12}");
+
+        let tls13 = enabled_versions(TlsMinVersion::Tls13);
+        assert!(!tls13.contains("TLSv1_2") && tls13.contains("TLSv1_3"), "{tls13}");
+    }
+
+    #[test]
+    fn test_parse_min_version() {
+        assert_eq!(TlsMinVersion::parse("1.2"), Ok(TlsMinVersion::Tls12));
+        assert_eq!(TlsMinVersion::parse(" TLSv1.3 "), Ok(TlsMinVersion::Tls13));
+        assert!(TlsMinVersion::parse("1.1").is_err());
+        assert!(TlsMinVersion::parse("1.0").is_err());
+        assert!(TlsMinVersion::parse("").is_err());
+    }
+}